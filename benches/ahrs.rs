@@ -5,7 +5,7 @@ extern crate increment;
 extern crate rand;
 
 use test::Bencher;
-use increment::ahrs::{ahrs::Ahrs, madgwick_filter::Madgwick};
+use increment::ahrs::{ahrs::Ahrs, ekf::Ekf, madgwick_filter::Madgwick, mahony_filter::Mahony};
 use rand::{Rng};
 
 macro_rules! get_rand_n(
@@ -58,4 +58,12 @@ bench_ahrs!(_bench_madgwick_update,           Madgwick, update,     1);
 bench_ahrs!(_bench_madgwick_update_x1000,     Madgwick, update,     1000);
 bench_ahrs!(_bench_madgwick_update_imu,       Madgwick, update_imu, 1);
 bench_ahrs!(_bench_madgwick_update_imu_x1000, Madgwick, update_imu, 1000);
+bench_ahrs!(_bench_mahony_update,             Mahony,   update,     1);
+bench_ahrs!(_bench_mahony_update_x1000,       Mahony,   update,     1000);
+bench_ahrs!(_bench_mahony_update_imu,         Mahony,   update_imu, 1);
+bench_ahrs!(_bench_mahony_update_imu_x1000,   Mahony,   update_imu, 1000);
+bench_ahrs!(_bench_ekf_update,                Ekf,      update,     1);
+bench_ahrs!(_bench_ekf_update_x1000,          Ekf,      update,     1000);
+bench_ahrs!(_bench_ekf_update_imu,            Ekf,      update_imu, 1);
+bench_ahrs!(_bench_ekf_update_imu_x1000,      Ekf,      update_imu, 1000);
 
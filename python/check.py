@@ -6,6 +6,173 @@
 
 from lobster_rust import ahrs
 
-mw: ahrs.MadgwickP = ahrs.MadgwickP(0.2, 0.2, [1, 0, 0, 0])
-mw.update(gyroscope=[1, 0, 0], accelerometer=[1, 0, 0], magnetometer=[1, 0, 0])
-print(mw)
+identity = ahrs.Quaternion(w=1.0, x=0.0, y=0.0, z=0.0)
+assert identity.to_list(order="xyzw") == [0.0, 0.0, 0.0, 1.0]
+assert ahrs.Quaternion.from_list([0, 0, 0, 1], order="xyzw").w == 1.0
+assert (identity * identity.conjugate()).normalize().to_list() == [1.0, 0.0, 0.0, 0.0]
+
+mw: ahrs.MadgwickP = ahrs.MadgwickP(0.2, 0.2, identity)
+drifting = ahrs.MadgwickP(0.2, 0.2, quat=identity, zeta=0.015)
+assert ahrs.MadgwickP.new_with_zeta(0.2, 0.2, 0.015, identity).to_json() == drifting.to_json()
+assert ahrs.MadgwickP.new_with_quat(0.2, 0.2, identity).to_json() == ahrs.MadgwickP(0.2, 0.2).to_json()
+quat: ahrs.Quaternion = mw.update(gyroscope=[1, 0, 0], accelerometer=[1, 0, 0], magnetometer=[1, 0, 0])
+print(mw, quat)
+
+mh: ahrs.MahonyP = ahrs.MahonyP.new_with_quat(0.2, 0.5, 0.0, identity)
+assert ahrs.MahonyP(0.2, 0.5, 0.0).update_imu([0, 0, 0], [0, 0, 1]).w == 1.0
+mh.update(gyroscope=[1, 0, 0], accelerometer=[1, 0, 0], magnetometer=[1, 0, 0])
+print(mh)
+try:
+    mh.update_imu(gyroscope=[0, 0, 0], accelerometer=[0, 0, 0])
+    assert False
+except ahrs.AccelerometerNormError:
+    pass
+
+ekf = ahrs.EkfP(0.01, 0.005, 0.0001, 0.01, 0.01)
+quat, covariance = ekf.update_imu(gyroscope=[0, 0, 0], accelerometer=[0, 0, 1])
+assert quat.w == 1.0 and len(covariance) == 7
+ekf = ahrs.EkfP.new_with_quat(0.01, 0.005, 0.0001, 0.01, 0.01, identity)
+print(ekf.update(gyroscope=[0, 0, 0], accelerometer=[0, 0, 1], magnetometer=[0.6, 0, -0.8]))
+
+import numpy as np
+
+quats, failed = mw.update_batch(np.ones((10, 3)), np.ones((10, 3)), np.ones((10, 3)))
+assert quats.shape == (10, 4) and not failed.any()
+quats, failed = mw.update_imu_batch(np.ones((10, 3)), np.zeros((10, 3)), order="xyzw")
+assert failed.all()
+print(quats)
+
+sphere = np.random.default_rng(0).normal(size=(200, 3))
+sphere = 50 * sphere / np.linalg.norm(sphere, axis=1, keepdims=True)
+cal: ahrs.MagCalibration = ahrs.calibrate_magnetometer((sphere * [1.2, 0.9, 1.0] + [12, -30, 4.5]).tolist())
+assert cal.fit_error < 1e-6
+mw.set_mag_calibration(cal)
+mh.set_mag_calibration(cal)
+ekf.set_mag_calibration(cal)
+print(cal.hard_iron, cal.soft_iron)
+
+positions = np.vstack([np.eye(3), -np.eye(3)])
+accel_cal, residuals = ahrs.calibrate_accelerometer((positions * 1.02 + 0.01).tolist(), gravity=1.0)
+assert max(residuals) < 1e-9
+gyro_cal, _ = ahrs.calibrate_gyroscope([[0.01, -0.02, 0.0]] * 10)
+mw.set_accel_calibration(accel_cal)
+mw.set_gyro_calibration(gyro_cal)
+mh.set_accel_calibration(accel_cal)
+ekf.set_gyro_calibration(gyro_cal)
+try:
+    ahrs.calibrate_gyroscope([])
+    assert False
+except ahrs.CalibrationError:
+    pass
+print(accel_cal.scale, accel_cal.bias, gyro_cal.bias)
+
+import pickle
+
+restored: ahrs.MadgwickP = pickle.loads(pickle.dumps(mw))
+assert isinstance(restored, ahrs.MadgwickP) and restored.to_json() == mw.to_json()
+assert restored.update([0.1, 0, 0], [0, 0, 1], [0.6, 0, -0.8]).to_list() == \
+    mw.update([0.1, 0, 0], [0, 0, 1], [0.6, 0, -0.8]).to_list()
+sample_period, beta, quat, zeta, earth_frame = restored.__getnewargs__()
+assert (sample_period, beta, zeta, earth_frame) == (mw.sample_period, mw.beta, 0.0, "nwu")
+assert quat.to_list() == mw.quaternion.to_list()
+assert pickle.loads(pickle.dumps(identity)).to_list() == identity.to_list()
+ned_restored = pickle.loads(pickle.dumps(ahrs.MadgwickP(0.01, 0.1, zeta=0.015, earth_frame="ned")))
+assert ned_restored.to_json() == ahrs.MadgwickP(0.01, 0.1, zeta=0.015, earth_frame="ned").to_json()
+assert ahrs.MadgwickP.from_json(mw.to_json()).euler() == mw.euler()
+
+mw.beta = 0.5
+mw.sample_period = 0.01
+mw.quaternion = ahrs.Quaternion(w=0.0, x=1.0, y=0.0, z=0.0)
+assert mw.beta == 0.5 and mw.sample_period == 0.01 and mw.quaternion.x == 1.0
+try:
+    mw.beta = -1.0
+    assert False
+except ValueError:
+    pass
+
+mw.set_gain_schedule(1.0, 2.0)
+mw.set_accel_adaptation(0.1, 0.2, gravity=1.0)
+assert mw.current_beta() == 1.0
+mw.disable_accel_adaptation()
+
+mw.set_mag_rejection(0.1, 0.05, weight=0.0)
+mw.update(gyroscope=[0, 0, 0], accelerometer=[0, 0, 1], magnetometer=[0.6, 0, -0.8])
+assert not mw.mag_rejected()
+mw.update(gyroscope=[0, 0, 0], accelerometer=[0, 0, 1], magnetometer=[1.2, 0, -1.6])
+assert mw.mag_rejected()
+mw.disable_mag_rejection()
+
+quat, body, earth = mw.update(gyroscope=[0, 0, 0], accelerometer=[0, 0, 9.81], magnetometer=[0.6, 0, -0.8],
+                              linear_acceleration=True, gravity=9.81, unit="m/s^2", output_unit="m/s^2")
+assert len(body) == 3 and len(earth) == 3 and isinstance(quat, ahrs.Quaternion)
+print(quat, body, earth)
+
+ins = ahrs.Ins(0.01, 0.0, gravity=1.0, unit="g")
+for _ in range(100):
+    position = ins.update_imu(gyroscope=[0, 0, 0], accelerometer=[0.1, 0, 1])
+print(position, ins.velocity)
+drift = ins.zero_velocity_update()
+ins.correct_position([0, 0, 0], weight=0.5)
+ins.reset()
+assert ins.position == [0, 0, 0]
+
+detector = ahrs.StationaryDetector(16, accel_noise=0.01, gyro_noise=0.005, gravity=1.0, threshold=30.0)
+labels = detector.label(np.zeros((50, 3)), np.tile([0.0, 0.0, 1.0], (50, 1)))
+assert ahrs.stationary_intervals(list(labels)) == [(15, 50)]
+still = ahrs.MadgwickP(0.01, 0.1)
+still.set_stationary_detector(detector, time_constant=0.5)
+still.update_imu(gyroscope=[0.01, 0, 0], accelerometer=[0, 0, 1])
+assert still.is_stationary()
+still.disable_stationary_detector()
+
+ned = ahrs.MadgwickP(0.01, 0.1, earth_frame="ned", mag_axes="y,x,-z",
+                     mounting=ahrs.Quaternion(w=0.7071067811865476, z=0.7071067811865476))
+ned.update(gyroscope=[0, 0, 0], accelerometer=[0, 0, 1], magnetometer=[0, 0.6, 0.8])
+print(ned.euler(degrees=True))
+heading = ahrs.Quaternion(w=0.9238795325112867, z=0.3826834323650898)
+enu = ahrs.MadgwickP(0.01, 0.1, heading, zeta=0.015, earth_frame="enu", gyro_axes="y,x,-z")
+assert abs(enu.quaternion.w - heading.w) < 1e-12 and abs(enu.quaternion.z - heading.z) < 1e-12
+assert ahrs.MadgwickP.new_with_zeta(0.01, 0.1, 0.015, heading, earth_frame="enu", gyro_axes="y,x,-z").to_json() == \
+    enu.to_json()
+assert ahrs.MadgwickP.new_with_quat(0.01, 0.1, heading, earth_frame="ned", mag_axes="y,x,-z").to_json() == \
+    ahrs.MadgwickP(0.01, 0.1, heading, earth_frame="ned", mag_axes="y,x,-z").to_json()
+
+trajectory = ahrs.Trajectory(0.01, initial=ahrs.Quaternion(w=0.9950041652780258, z=0.09983341664682815))
+trajectory.still(1.0)
+trajectory.sinusoidal(2.0, rate=[0.3, -0.2, 0.8], frequency=0.5)
+trajectory.constant(1.0, rate=[0, 0, 0.5], acceleration=[0.1, 0, 0])
+gyro_model = ahrs.SensorModel(white_noise=0.005, bias=[0.01, 0, 0], random_walk=0.001)
+time, truth, gyro, accel, mag = trajectory.simulate(gyroscope=gyro_model, seed=3)
+assert gyro.shape == (len(time), 3) and truth.shape == (len(time), 4)
+assert np.array_equal(gyro, trajectory.simulate(gyroscope=gyro_model, seed=3)[2])
+assert np.array_equal(truth, trajectory.simulate(gyroscope=gyro_model, seed=3, order="wxyz")[1])
+
+tau, adev, noise = ahrs.allan_deviation(gyro, 0.01)
+assert adev.shape == (len(tau), 3) and noise.shape == (3, 3)
+print(tau[0], adev[0], noise)
+
+marg = ahrs.MadgwickP(0.01, 0.1)
+estimates, _ = marg.update_batch(gyro, accel, mag)
+errors = ahrs.angular_error(estimates, truth)
+assert errors.shape == (len(time),)
+assert np.allclose(ahrs.angular_error(-estimates, truth), errors)
+accuracy = ahrs.evaluate(estimates, truth, list(time), threshold=0.05)
+print(accuracy["rmse"], accuracy["euler_rmse"], accuracy["convergence_time"], accuracy["heading_drift_rate"])
+
+import os
+import tempfile
+
+with tempfile.TemporaryDirectory() as directory:
+    log = os.path.join(directory, "log.csv")
+    orientations = os.path.join(directory, "orientations.csv")
+    columns = np.column_stack((time * 1000.0, np.degrees(gyro), accel, mag / 100.0))
+    np.savetxt(log, columns, delimiter=";", header="t;wx;wy;wz;ax;ay;az;mx;my;mz", comments="")
+
+    replayed = ahrs.MadgwickP(0.01, 0.1)
+    samples, failed = ahrs.replay_csv(log, replayed, orientations, timestamp="t", gyroscope=["wx", "wy", "wz"],
+                                      time_scale=0.001, gyro_unit="deg/s", mag_unit="gauss", delimiter=";",
+                                      degrees=True)
+    assert (samples, failed) == (len(time), 0)
+    output = np.genfromtxt(orientations, delimiter=",", names=True)
+    assert len(output) == len(time)
+    print(output[-1])
@@ -0,0 +1,112 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Quaternion, UnitQuaternion, Vector3};
+use std::str::FromStr;
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+use crate::ahrs::error::ConfigError;
+
+/// Standard gravity, in m/s².
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
+/// Unit of an acceleration.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum AccelUnit {
+    /// Multiples of standard gravity.
+    G,
+    /// Meters per second squared.
+    MetersPerSecondSquared,
+}
+
+impl AccelUnit {
+    /// Converts an acceleration `value` from this unit to the unit `to`.
+    pub fn convert<N: RealField>(&self, value: N, to: AccelUnit) -> N {
+        let g: N = na::convert(STANDARD_GRAVITY);
+
+        match (self, to) {
+            (AccelUnit::G, AccelUnit::MetersPerSecondSquared) => value * g,
+            (AccelUnit::MetersPerSecondSquared, AccelUnit::G) => value / g,
+            _ => value,
+        }
+    }
+}
+
+impl FromStr for AccelUnit {
+    type Err = ConfigError;
+
+    /// Parses `"g"`, or `"m/s^2"`, `"m/s2"` or `"m/s²"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "g" => Ok(AccelUnit::G),
+            "m/s^2" | "m/s2" | "m/s²" => Ok(AccelUnit::MetersPerSecondSquared),
+            _ => Err(ConfigError::InvalidUnit),
+        }
+    }
+}
+
+/// Acceleration of the sensor with gravity removed.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+pub struct LinearAcceleration<N: RealField> {
+    /// Linear acceleration in the sensor frame.
+    pub body: Vector3<N>,
+    /// Linear acceleration in the Earth frame.
+    pub earth: Vector3<N>,
+}
+
+/// Rotates an `accelerometer` measurement from the sensor frame into the Earth frame using the
+/// orientation `quat`.
+pub fn earth_acceleration<N: RealField>(quat: &Quaternion<N>, accelerometer: &Vector3<N>) -> Vector3<N> {
+    UnitQuaternion::from_quaternion(*quat) * accelerometer
+}
+
+/// Removes gravity from an `accelerometer` measurement using the orientation `quat`.
+///
+/// A stationary accelerometer measures `gravity` along the Earth frame z axis, so that is subtracted
+/// in the Earth frame and the result is rotated back into the sensor frame.
+///
+/// # Arguments
+///
+/// * `quat` - Orientation of the sensor, as estimated by a filter.
+/// * `accelerometer` - Accelerometer measurement, in `unit`.
+/// * `gravity` - Magnitude of gravity, in `unit`.
+/// * `unit` - Unit of `accelerometer` and `gravity`.
+/// * `output` - Unit of the returned linear acceleration.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::{Quaternion, Vector3};
+/// use lobster_rust::ahrs::acceleration::{linear_acceleration, AccelUnit};
+///
+/// fn main() {
+///     let linear = linear_acceleration(
+///         &Quaternion::new(1.0f64, 0.0, 0.0, 0.0),
+///         &Vector3::new(0.1, 0.0, 1.0),
+///         1.0,
+///         AccelUnit::G,
+///         AccelUnit::MetersPerSecondSquared
+///     );
+/// }
+/// ```
+pub fn linear_acceleration<N: RealField>(
+    quat: &Quaternion<N>,
+    accelerometer: &Vector3<N>,
+    gravity: N,
+    unit: AccelUnit,
+    output: AccelUnit,
+) -> LinearAcceleration<N> {
+    let rotation = UnitQuaternion::from_quaternion(*quat);
+
+    let earth = rotation * accelerometer - Vector3::new(N::zero(), N::zero(), gravity);
+    let body = rotation.inverse() * earth;
+
+    LinearAcceleration {
+        body: body.map(|x| unit.convert(x, output)),
+        earth: earth.map(|x| unit.convert(x, output)),
+    }
+}
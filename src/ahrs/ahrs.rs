@@ -3,6 +3,8 @@ extern crate alga;
 
 use na::{Vector3, Quaternion};
 use alga::general::RealField;
+use crate::ahrs::error::AhrsError;
+use crate::ahrs::frame::EarthFrame;
 
 /// Trait for implementing an AHRS filter.
 pub trait Ahrs<N: RealField> {
@@ -10,13 +12,57 @@ pub trait Ahrs<N: RealField> {
     /// `accelerometer`, and `magnetometer`.
     ///
     /// Returns a reference to the updated quaternion on success, or in the case of failure, an
-    /// `Err(&str)` containing the reason.
-    fn update(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, magnetometer: &Vector3<N>) -> Result<&Quaternion<N>, &'static str>;
+    /// `Err(AhrsError)` containing the reason.
+    fn update(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, magnetometer: &Vector3<N>) -> Result<&Quaternion<N>, AhrsError>;
 
     /// Attempts to update the current state quaternion using 6dof IMU values, made up by `gyroscope` &
     /// `accelerometer`.
     ///
     /// Returns a reference to the updated quaternion on success, or in the case of failure, an
-    /// `Err(&str)` containing the reason.
-    fn update_imu(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>) -> Result<&Quaternion<N>, &'static str>;
+    /// `Err(AhrsError)` containing the reason.
+    fn update_imu(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>) -> Result<&Quaternion<N>, AhrsError>;
+
+    /// Attempts to update the current state quaternion using 9dof IMU values, made up by `gyroscope`,
+    /// `accelerometer`, and `magnetometer`, sampled `dt` seconds after the previous update.
+    ///
+    /// Returns a reference to the updated quaternion on success, or in the case of failure, an
+    /// `Err(AhrsError)` containing the reason.
+    fn update_dt(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, magnetometer: &Vector3<N>, dt: N) -> Result<&Quaternion<N>, AhrsError>;
+
+    /// Attempts to update the current state quaternion using 6dof IMU values, made up by `gyroscope` &
+    /// `accelerometer`, sampled `dt` seconds after the previous update.
+    ///
+    /// Returns a reference to the updated quaternion on success, or in the case of failure, an
+    /// `Err(AhrsError)` containing the reason.
+    fn update_imu_dt(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, dt: N) -> Result<&Quaternion<N>, AhrsError>;
+
+    /// Earth frame of the state quaternion, north-west-up unless the filter is configured
+    /// otherwise.
+    fn earth_frame(&self) -> EarthFrame {
+        EarthFrame::Nwu
+    }
+
+    /// Applies the corrections the filter applies before fusion to an `accelerometer` measurement,
+    /// expressing it in the body frame of the state quaternion.
+    fn correct_accelerometer(&self, accelerometer: &Vector3<N>) -> Vector3<N> {
+        *accelerometer
+    }
+}
+
+/// Checks that the time step `dt` is positive and finite.
+pub(crate) fn validate_dt<N: RealField>(dt: N) -> Result<N, AhrsError> {
+    if dt > N::zero() && dt.is_finite() {
+        Ok(dt)
+    } else {
+        Err(AhrsError::InvalidTimeStep)
+    }
+}
+
+/// Checks that every component of the sensor measurement `v` is finite.
+pub(crate) fn validate_finite<N: RealField>(v: &Vector3<N>) -> Result<(), AhrsError> {
+    if v.iter().all(|x| x.is_finite()) {
+        Ok(())
+    } else {
+        Err(AhrsError::NonFiniteInput)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,230 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Matrix3, Vector3};
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+use crate::ahrs::ahrs::validate_dt;
+use crate::ahrs::error::AhrsError;
+
+/// Overlapping Allan deviation of a sensor stream over a range of cluster times.
+#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AllanDeviation<N: RealField> {
+    /// Cluster times, in seconds, in increasing order.
+    pub tau: Vec<N>,
+    /// Allan deviation at every cluster time, in sensor units.
+    pub deviation: Vec<N>,
+    /// Approximate relative error of every deviation, from the number of independent clusters.
+    pub error: Vec<N>,
+}
+
+/// Noise coefficients fitted to an Allan deviation curve.
+///
+/// For a gyroscope in rad/s the coefficients are the angle random walk in rad/√s, the bias
+/// instability in rad/s and the rate random walk in rad/s/√s. For an accelerometer the same
+/// terms are the velocity random walk, bias instability and acceleration random walk.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct NoiseParameters<N: RealField> {
+    /// White noise coefficient, the deviation at a cluster time of one second on the -1/2 slope.
+    pub angle_random_walk: N,
+    /// Flicker noise coefficient, from the flat part of the curve.
+    pub bias_instability: N,
+    /// Random walk coefficient, the deviation at a cluster time of three seconds on the +1/2
+    /// slope.
+    pub rate_random_walk: N,
+}
+
+/// Cluster sizes spaced logarithmically with `per_decade` sizes per decade, from a single sample
+/// up to half of `samples`, the largest cluster an overlapping Allan deviation can use.
+pub fn cluster_sizes(samples: usize, per_decade: usize) -> Vec<usize> {
+    let mut sizes: Vec<usize> = Vec::new();
+    let per_decade = per_decade.max(1) as f64;
+
+    for k in 0.. {
+        let size = 10f64.powf(k as f64 / per_decade).round() as usize;
+        if size > samples / 2 {
+            break;
+        }
+
+        if sizes.last() != Some(&size) {
+            sizes.push(size);
+        }
+    }
+
+    sizes
+}
+
+/// Computes the overlapping Allan deviation of a sensor stream.
+///
+/// Clusters larger than half of the samples are skipped. Returns an `Err(AhrsError)` if the
+/// sample period is not positive or none of the clusters fit in the samples.
+///
+/// # Arguments
+///
+/// * `samples` - Sensor measurements taken every sampling period.
+/// * `sample_period` - Sampling period, in seconds.
+/// * `clusters` - Cluster sizes, in samples, for example from `cluster_sizes`.
+///
+/// # Example
+///
+/// ```
+/// use lobster_rust::ahrs::allan::{allan_deviation, cluster_sizes};
+///
+/// fn main() {
+///     let samples: Vec<f64> = (0..1000).map(|n| (n as f64 * 1.7).sin()).collect();
+///
+///     let adev = allan_deviation(&samples, 0.01, &cluster_sizes(samples.len(), 10)).unwrap();
+///     let noise = adev.fit().unwrap();
+/// }
+/// ```
+pub fn allan_deviation<N: RealField>(samples: &[N], sample_period: N, clusters: &[usize]) -> Result<AllanDeviation<N>, AhrsError> {
+    let sample_period = validate_dt(sample_period)?;
+
+    if samples.is_empty() {
+        return Err(AhrsError::NoSamples);
+    }
+
+    // Integrate the stream with the mean removed, which leaves the deviation unchanged but keeps
+    // the differences below from cancelling
+    let mean = samples.iter().fold(N::zero(), |sum, s| sum + *s) / na::convert(samples.len() as f64);
+    let mut theta = Vec::with_capacity(samples.len() + 1);
+    theta.push(N::zero());
+    for s in samples {
+        let last = theta[theta.len() - 1];
+        theta.push(last + (*s - mean) * sample_period);
+    }
+
+    let mut sizes: Vec<usize> = clusters.iter().cloned().filter(|&m| m > 0 && 2 * m < theta.len()).collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    if sizes.is_empty() {
+        return Err(AhrsError::NoSamples);
+    }
+
+    let two: N = na::convert(2.0);
+    let mut tau = Vec::with_capacity(sizes.len());
+    let mut deviation = Vec::with_capacity(sizes.len());
+    let mut error = Vec::with_capacity(sizes.len());
+
+    for m in sizes {
+        let t = sample_period * na::convert(m as f64);
+        let count = theta.len() - 2 * m;
+
+        let sum = (0..count).fold(N::zero(), |sum, k| {
+            let d = theta[k + 2 * m] - theta[k + m] * two + theta[k];
+            sum + d * d
+        });
+
+        tau.push(t);
+        deviation.push((sum / (two * t * t * na::convert(count as f64))).sqrt());
+
+        let independent: N = na::convert((samples.len() / m) as f64);
+        error.push(N::one() / (two * (independent - N::one())).sqrt());
+    }
+
+    Ok(AllanDeviation { tau, deviation, error })
+}
+
+/// Computes the overlapping Allan deviation of every axis of a three-axis sensor stream.
+///
+/// See `allan_deviation`.
+pub fn allan_deviation_xyz<N: RealField>(samples: &[Vector3<N>], sample_period: N, clusters: &[usize]) -> Result<[AllanDeviation<N>; 3], AhrsError> {
+    let axis = |i: usize| {
+        let values: Vec<N> = samples.iter().map(|s| s[i]).collect();
+        allan_deviation(&values, sample_period, clusters)
+    };
+
+    Ok([axis(0)?, axis(1)?, axis(2)?])
+}
+
+impl<N: RealField> AllanDeviation<N> {
+    /// Fits white noise, bias instability and random walk coefficients to the curve.
+    ///
+    /// The Allan variance is modelled as `N²/τ + (2 ln 2 / π) B² + K² τ / 3`, fitted by
+    /// non-negative least squares on the relative deviation of every point from the model,
+    /// weighted by the inverse of its estimated error so the few clusters of the longest cluster
+    /// times do not dominate. Terms that do not show in the curve are zero. Returns an
+    /// `Err(AhrsError)` if the curve has no points.
+    pub fn fit(&self) -> Result<NoiseParameters<N>, AhrsError> {
+        if self.tau.is_empty() {
+            return Err(AhrsError::NoSamples);
+        }
+
+        // Rows of the relative equations, which are all equal to one at a perfect fit, and the
+        // weight of every row
+        let rows: Vec<(Vector3<N>, N)> = (0..self.tau.len())
+            .filter(|&n| self.deviation[n] > N::zero())
+            .map(|n| {
+                let (t, d) = (self.tau[n], self.deviation[n]);
+                (Vector3::new(N::one() / t, N::one(), t) / (d * d), N::one() / self.error[n])
+            })
+            .collect();
+
+        let mut normal = Matrix3::zeros();
+        let mut target = Vector3::zeros();
+        for (row, weight) in &rows {
+            normal += row * row.transpose() * (*weight * *weight);
+            target += row * (*weight * *weight);
+        }
+
+        // Solve every subset of the terms and keep the best one without negative coefficients
+        let mut best: Option<(N, Vector3<N>)> = None;
+
+        for subset in 1..8usize {
+            let used = |i: usize| subset & (1 << i) != 0;
+
+            let mut m = normal;
+            let mut b = target;
+            for i in (0..3).filter(|&i| !used(i)) {
+                m.row_mut(i).fill(N::zero());
+                m.column_mut(i).fill(N::zero());
+                m[(i, i)] = N::one();
+                b[i] = N::zero();
+            }
+
+            let x = match m.cholesky() {
+                Some(c) => c.solve(&b),
+                None => continue,
+            };
+
+            if x.iter().any(|c| *c < N::zero()) {
+                continue;
+            }
+
+            let residual = rows.iter().fold(N::zero(), |sum, (row, weight)| {
+                let r = (row.dot(&x) - N::one()) * *weight;
+                sum + r * r
+            });
+
+            best = match best {
+                Some((r, _)) if r <= residual => best,
+                _ => Some((residual, x)),
+            };
+        }
+
+        let x = best.map_or_else(Vector3::zeros, |(_, x)| x);
+        let flicker = N::two_pi() / (na::convert::<f64, N>(4.0) * N::ln_2());
+
+        Ok(NoiseParameters {
+            angle_random_walk: x[0].sqrt(),
+            bias_instability: (x[1] * flicker).sqrt(),
+            rate_random_walk: (x[2] * na::convert(3.0)).sqrt(),
+        })
+    }
+}
+
+impl<N: RealField> NoiseParameters<N> {
+    /// Allan deviation of the noise model at cluster time `tau`, in seconds.
+    pub fn deviation(&self, tau: N) -> N {
+        let flicker = na::convert::<f64, N>(4.0) * N::ln_2() / N::two_pi();
+        let three: N = na::convert(3.0);
+
+        (self.angle_random_walk * self.angle_random_walk / tau
+            + self.bias_instability * self.bias_instability * flicker
+            + self.rate_random_walk * self.rate_random_walk * tau / three).sqrt()
+    }
+}
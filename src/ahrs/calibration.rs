@@ -0,0 +1,534 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Cholesky, Matrix3, Matrix4, Matrix4x3, MatrixN, Quaternion, SymmetricEigen, Vector3, Vector4, VectorN, U3, U9};
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+use crate::ahrs::ahrs::Ahrs;
+use crate::ahrs::error::{AhrsError, ConfigError};
+use crate::ahrs::frame::EarthFrame;
+use crate::ahrs::init;
+
+/// Magnetometer calibration correcting hard-iron and soft-iron distortion.
+///
+/// A raw measurement `m` is corrected as `soft_iron * (m - hard_iron)`, which maps the ellipsoid
+/// traced by the raw measurements back onto a sphere.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct MagCalibration<N: RealField> {
+    /// Offset of the ellipsoid center from the origin, in sensor units.
+    hard_iron: Vector3<N>,
+    /// Symmetric matrix mapping the centered ellipsoid onto a sphere.
+    soft_iron: Matrix3<N>,
+    /// Radius of the sphere after correction, in sensor units.
+    field_strength: N,
+    /// Root mean square distance of the corrected samples from the sphere, relative to its radius.
+    fit_error: N,
+}
+
+impl<N: RealField> MagCalibration<N> {
+    /// Creates a new `MagCalibration` from known distortion parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `hard_iron` - Hard-iron offset, in sensor units.
+    /// * `soft_iron` - Soft-iron correction matrix.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::{Matrix3, Vector3};
+    /// use lobster_rust::ahrs::calibration::MagCalibration;
+    ///
+    /// fn main() {
+    ///     let cal = MagCalibration::new(Vector3::new(0.1f64, -0.2, 0.05), Matrix3::identity());
+    /// }
+    /// ```
+    pub fn new(hard_iron: Vector3<N>, soft_iron: Matrix3<N>) -> Self {
+        MagCalibration {
+            hard_iron,
+            soft_iron,
+            field_strength: N::one(),
+            fit_error: N::zero(),
+        }
+    }
+
+    /// Fits an ellipsoid to a collection of raw magnetometer `samples`, ideally covering as many
+    /// orientations of the sensor as possible.
+    ///
+    /// The soft-iron matrix is scaled so the corrected field keeps the mean strength of the raw
+    /// samples. Returns an `Err(ConfigError::InvalidCalibration)` for fewer than nine samples or
+    /// samples that do not lie on an ellipsoid.
+    pub fn fit(samples: &[Vector3<N>]) -> Result<Self, ConfigError> {
+        let one: N = N::one();
+        let two: N = na::convert(2.0);
+
+        if samples.len() < 9 {
+            return Err(ConfigError::InvalidCalibration);
+        }
+
+        // Center and scale the samples to keep the least squares problem well conditioned
+        let count: N = na::convert(samples.len() as f64);
+        let mean = samples.iter().fold(Vector3::zeros(), |acc, s| acc + s) / count;
+        let scale = (samples.iter().fold(N::zero(), |acc, s| acc + (s - mean).norm_squared()) / count).sqrt();
+
+        if !scale.is_finite() || scale <= N::zero() {
+            return Err(ConfigError::InvalidCalibration);
+        }
+
+        // Least squares fit of a x² + b y² + c z² + 2d xy + 2e xz + 2f yz + 2g x + 2h y + 2i z = 1
+        let mut normal = MatrixN::<N, U9>::zeros();
+        let mut rhs = VectorN::<N, U9>::zeros();
+
+        for s in samples {
+            let p = (s - mean) / scale;
+            let row = VectorN::<N, U9>::from_column_slice(&[
+                p[0] * p[0], p[1] * p[1], p[2] * p[2],
+                two * p[0] * p[1], two * p[0] * p[2], two * p[1] * p[2],
+                two * p[0], two * p[1], two * p[2],
+            ]);
+
+            normal += row * row.transpose();
+            rhs += row;
+        }
+
+        let v = match Cholesky::new(normal) {
+            Some(c) => c.solve(&rhs),
+            None => return Err(ConfigError::InvalidCalibration),
+        };
+
+        let a = Matrix3::new(
+            v[0], v[3], v[4],
+            v[3], v[1], v[5],
+            v[4], v[5], v[2],
+        );
+        let linear = Vector3::new(v[6], v[7], v[8]);
+
+        // Ellipsoid center, and the shape matrix M such that (p - center)' M (p - center) = 1
+        let center = match a.try_inverse() {
+            Some(inv) => -(inv * linear),
+            None => return Err(ConfigError::InvalidCalibration),
+        };
+        let shape = a / (one + center.dot(&(a * center)));
+
+        let eigen = SymmetricEigen::new(shape);
+        if !eigen.eigenvalues.iter().all(|l| *l > N::zero()) {
+            return Err(ConfigError::InvalidCalibration);
+        }
+
+        // Square root of the shape matrix, scaled so the corrected sphere keeps the mean radius
+        let radius = eigen.eigenvalues.iter().fold(one, |acc, l| acc * *l).powf(na::convert(-1.0 / 6.0));
+        let sqrt_shape = eigen.eigenvectors
+            * Matrix3::from_diagonal(&eigen.eigenvalues.map(|l| l.sqrt()))
+            * eigen.eigenvectors.transpose();
+
+        let hard_iron = mean + center * scale;
+        let soft_iron = sqrt_shape * radius;
+        let field_strength = radius * scale;
+
+        // Relative root mean square distance of the corrected samples from the sphere
+        let residual = samples.iter().fold(N::zero(), |acc, s| {
+            let r = (soft_iron * (s - hard_iron)).norm() - field_strength;
+            acc + r * r
+        });
+        let fit_error = (residual / count).sqrt() / field_strength;
+
+        Ok(MagCalibration {
+            hard_iron,
+            soft_iron,
+            field_strength,
+            fit_error,
+        })
+    }
+
+    /// Corrects a raw `magnetometer` measurement.
+    pub fn apply(&self, magnetometer: &Vector3<N>) -> Vector3<N> {
+        self.soft_iron * (magnetometer - self.hard_iron)
+    }
+
+    /// Hard-iron offset, in sensor units.
+    pub fn hard_iron(&self) -> Vector3<N> {
+        self.hard_iron
+    }
+
+    /// Soft-iron correction matrix.
+    pub fn soft_iron(&self) -> Matrix3<N> {
+        self.soft_iron
+    }
+
+    /// Strength of the corrected field, in sensor units. Calibrations created with `new` report
+    /// one.
+    pub fn field_strength(&self) -> N {
+        self.field_strength
+    }
+
+    /// Root mean square distance of the corrected samples from a sphere, relative to its radius.
+    /// Calibrations created with `new` report zero.
+    pub fn fit_error(&self) -> N {
+        self.fit_error
+    }
+}
+
+/// Accelerometer calibration correcting bias, scale and axis misalignment.
+///
+/// The raw measurement is modelled as `sensitivity * a + bias` for the true acceleration `a`, so a
+/// raw measurement `m` is corrected as `sensitivity^-1 * (m - bias)`.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AccelCalibration<N: RealField> {
+    /// Zero offset, in sensor units.
+    bias: Vector3<N>,
+    /// Scale factors on the diagonal and axis misalignment off the diagonal.
+    sensitivity: Matrix3<N>,
+    /// Inverse of the sensitivity matrix.
+    correction: Matrix3<N>,
+    /// Magnitude of gravity, in sensor units.
+    gravity: N,
+    /// Root mean square residual of the corrected samples, relative to gravity.
+    fit_error: N,
+}
+
+impl<N: RealField> AccelCalibration<N> {
+    /// Creates a new `AccelCalibration` from known parameters.
+    ///
+    /// Returns an `Err(ConfigError::InvalidCalibration)` if `sensitivity` is singular.
+    ///
+    /// # Arguments
+    ///
+    /// * `bias` - Zero offset, in sensor units.
+    /// * `sensitivity` - Scale factors on the diagonal and axis misalignment off the diagonal.
+    /// * `gravity` - Magnitude of gravity in sensor units, `1` for g or `9.80665` for m/s².
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::{Matrix3, Vector3};
+    /// use lobster_rust::ahrs::calibration::AccelCalibration;
+    ///
+    /// fn main() {
+    ///     let cal = AccelCalibration::new(Vector3::new(0.01f64, 0.0, -0.02), Matrix3::identity(), 1.0).unwrap();
+    /// }
+    /// ```
+    pub fn new(bias: Vector3<N>, sensitivity: Matrix3<N>, gravity: N) -> Result<Self, ConfigError> {
+        let correction = match sensitivity.try_inverse() {
+            Some(inv) => inv,
+            None => return Err(ConfigError::InvalidCalibration),
+        };
+
+        Ok(AccelCalibration {
+            bias,
+            sensitivity,
+            correction,
+            gravity,
+            fit_error: N::zero(),
+        })
+    }
+
+    /// Fits a calibration to static accelerometer `samples` captured with each sensor axis in turn
+    /// pointing up and down, as in the six-position method.
+    ///
+    /// Every sample is matched to gravity along the sensor axis closest to vertical, so the sensor
+    /// only needs to be placed roughly aligned. Returns an `Err(ConfigError::InvalidCalibration)` if
+    /// the samples do not cover enough orientations to determine every parameter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Accelerometer samples taken while the sensor is stationary.
+    /// * `gravity` - Magnitude of gravity in sensor units, `1` for g or `9.80665` for m/s².
+    pub fn fit(samples: &[Vector3<N>], gravity: N) -> Result<Self, ConfigError> {
+        let one: N = N::one();
+
+        // Least squares fit of raw = sensitivity * reference + bias, solving all axes at once
+        let mut normal = Matrix4::<N>::zeros();
+        let mut rhs = Matrix4x3::<N>::zeros();
+
+        for s in samples {
+            let r = gravity_reference(s, gravity);
+            let row = Vector4::new(r[0], r[1], r[2], one);
+
+            normal += row * row.transpose();
+            rhs += row * s.transpose();
+        }
+
+        let x = match Cholesky::new(normal) {
+            Some(c) => c.solve(&rhs),
+            None => return Err(ConfigError::InvalidCalibration),
+        };
+
+        let sensitivity = x.fixed_rows::<U3>(0).transpose();
+        let bias = x.row(3).transpose();
+
+        let mut cal = AccelCalibration::new(bias, sensitivity, gravity)?;
+
+        // Relative root mean square residual
+        let count: N = na::convert(samples.len() as f64);
+        let residual = cal.residuals(samples).iter().fold(N::zero(), |acc, r| acc + *r * *r);
+        cal.fit_error = (residual / count).sqrt() / gravity;
+
+        Ok(cal)
+    }
+
+    /// Corrects a raw `accelerometer` measurement.
+    pub fn apply(&self, accelerometer: &Vector3<N>) -> Vector3<N> {
+        self.correction * (accelerometer - self.bias)
+    }
+
+    /// Distance of every corrected static sample from gravity along the sensor axis closest to
+    /// vertical, in sensor units.
+    pub fn residuals(&self, samples: &[Vector3<N>]) -> Vec<N> {
+        samples.iter()
+            .map(|s| (self.apply(s) - gravity_reference(s, self.gravity)).norm())
+            .collect()
+    }
+
+    /// Zero offset, in sensor units.
+    pub fn bias(&self) -> Vector3<N> {
+        self.bias
+    }
+
+    /// Scale factors on the diagonal and axis misalignment off the diagonal.
+    pub fn sensitivity(&self) -> Matrix3<N> {
+        self.sensitivity
+    }
+
+    /// Scale factor of every axis.
+    pub fn scale(&self) -> Vector3<N> {
+        self.sensitivity.diagonal()
+    }
+
+    /// Sensitivity matrix with every row divided by its scale factor, leaving the cross-axis
+    /// coupling.
+    pub fn misalignment(&self) -> Matrix3<N> {
+        Matrix3::from_diagonal(&self.scale().map(|s| N::one() / s)) * self.sensitivity
+    }
+
+    /// Matrix applied to bias-corrected measurements, the inverse of the sensitivity matrix.
+    pub fn correction(&self) -> Matrix3<N> {
+        self.correction
+    }
+
+    /// Magnitude of gravity, in sensor units.
+    pub fn gravity(&self) -> N {
+        self.gravity
+    }
+
+    /// Root mean square residual of the corrected samples, relative to gravity. Calibrations
+    /// created with `new` report zero.
+    pub fn fit_error(&self) -> N {
+        self.fit_error
+    }
+}
+
+/// Gravity along the sensor axis with the largest component of `sample`, with its sign.
+fn gravity_reference<N: RealField>(sample: &Vector3<N>, gravity: N) -> Vector3<N> {
+    let axis = sample.iamax();
+
+    let mut reference = Vector3::zeros();
+    reference[axis] = if sample[axis] < N::zero() { -gravity } else { gravity };
+
+    reference
+}
+
+/// Gyroscope calibration correcting a constant bias.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct GyroCalibration<N: RealField> {
+    /// Zero-rate offset, in sensor units.
+    bias: Vector3<N>,
+    /// Standard deviation of every axis while stationary, in sensor units.
+    noise: Vector3<N>,
+}
+
+impl<N: RealField> GyroCalibration<N> {
+    /// Creates a new `GyroCalibration` from a known zero-rate `bias`, in sensor units.
+    pub fn new(bias: Vector3<N>) -> Self {
+        GyroCalibration {
+            bias,
+            noise: Vector3::zeros(),
+        }
+    }
+
+    /// Estimates the bias from gyroscope `samples` taken while the sensor is stationary.
+    ///
+    /// Returns an `Err(ConfigError::InvalidCalibration)` if `samples` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Vector3;
+    /// use lobster_rust::ahrs::calibration::GyroCalibration;
+    ///
+    /// fn main() {
+    ///     let cal = GyroCalibration::fit(&[Vector3::new(0.01f64, -0.02, 0.0), Vector3::new(0.01, -0.01, 0.0)]);
+    /// }
+    /// ```
+    pub fn fit(samples: &[Vector3<N>]) -> Result<Self, ConfigError> {
+        let bias = init::mean(samples).map_err(|_| ConfigError::InvalidCalibration)?;
+
+        let count: N = na::convert(samples.len() as f64);
+        let variance = samples.iter()
+            .fold(Vector3::zeros(), |acc, s| acc + (s - bias).component_mul(&(s - bias)))
+            / count;
+
+        Ok(GyroCalibration {
+            bias,
+            noise: variance.map(|v| v.sqrt()),
+        })
+    }
+
+    /// Corrects a raw `gyroscope` measurement.
+    pub fn apply(&self, gyroscope: &Vector3<N>) -> Vector3<N> {
+        gyroscope - self.bias
+    }
+
+    /// Magnitude of every corrected stationary sample, in sensor units.
+    pub fn residuals(&self, samples: &[Vector3<N>]) -> Vec<N> {
+        samples.iter().map(|s| self.apply(s).norm()).collect()
+    }
+
+    /// Zero-rate offset, in sensor units.
+    pub fn bias(&self) -> Vector3<N> {
+        self.bias
+    }
+
+    /// Standard deviation of every axis while stationary, in sensor units. Calibrations created
+    /// with `new` report zero.
+    pub fn noise(&self) -> Vector3<N> {
+        self.noise
+    }
+}
+
+/// AHRS filter correcting every raw measurement with the attached calibrations before passing it
+/// on, so any filter can be fed uncalibrated sensors.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Calibrated<N: RealField, A> {
+    /// Orientation filter receiving the corrected measurements.
+    ahrs: A,
+    /// Gyroscope calibration, or `None` to pass measurements through.
+    gyro_calibration: Option<GyroCalibration<N>>,
+    /// Accelerometer calibration, or `None` to pass measurements through.
+    accel_calibration: Option<AccelCalibration<N>>,
+    /// Magnetometer calibration, or `None` to pass measurements through.
+    mag_calibration: Option<MagCalibration<N>>,
+}
+
+impl<N: RealField, A: Ahrs<N>> Calibrated<N, A> {
+    /// Creates a new `Calibrated` filter without calibrations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::calibration::Calibrated;
+    /// use lobster_rust::ahrs::mahony_filter::Mahony;
+    ///
+    /// fn main() {
+    ///     let ahrs = Calibrated::new(Mahony::new(0.01f64, 0.5, 0.0));
+    /// }
+    /// ```
+    pub fn new(ahrs: A) -> Self {
+        Calibrated {
+            ahrs,
+            gyro_calibration: None,
+            accel_calibration: None,
+            mag_calibration: None,
+        }
+    }
+
+    /// Orientation filter.
+    pub fn ahrs(&self) -> &A {
+        &self.ahrs
+    }
+
+    /// Mutable reference to the orientation filter.
+    pub fn ahrs_mut(&mut self) -> &mut A {
+        &mut self.ahrs
+    }
+
+    /// Gyroscope calibration applied before every update.
+    pub fn gyro_calibration(&self) -> Option<GyroCalibration<N>> {
+        self.gyro_calibration
+    }
+
+    /// Sets the gyroscope calibration applied to every measurement passed to `update` and
+    /// `update_imu`, or removes it with `None`.
+    pub fn set_gyro_calibration(&mut self, calibration: Option<GyroCalibration<N>>) {
+        self.gyro_calibration = calibration;
+    }
+
+    /// Accelerometer calibration applied before every update.
+    pub fn accel_calibration(&self) -> Option<AccelCalibration<N>> {
+        self.accel_calibration
+    }
+
+    /// Sets the accelerometer calibration applied to every measurement passed to `update` and
+    /// `update_imu`, or removes it with `None`.
+    pub fn set_accel_calibration(&mut self, calibration: Option<AccelCalibration<N>>) {
+        self.accel_calibration = calibration;
+    }
+
+    /// Magnetometer calibration applied before every update.
+    pub fn mag_calibration(&self) -> Option<MagCalibration<N>> {
+        self.mag_calibration
+    }
+
+    /// Sets the magnetometer calibration applied to every measurement passed to `update`, or
+    /// removes it with `None`.
+    pub fn set_mag_calibration(&mut self, calibration: Option<MagCalibration<N>>) {
+        self.mag_calibration = calibration;
+    }
+
+    /// Corrects a raw `gyroscope` measurement with the attached calibration.
+    fn gyroscope(&self, gyroscope: &Vector3<N>) -> Vector3<N> {
+        self.gyro_calibration.map_or(*gyroscope, |cal| cal.apply(gyroscope))
+    }
+
+    /// Corrects a raw `accelerometer` measurement with the attached calibration.
+    fn accelerometer(&self, accelerometer: &Vector3<N>) -> Vector3<N> {
+        self.accel_calibration.map_or(*accelerometer, |cal| cal.apply(accelerometer))
+    }
+
+    /// Corrects a raw `magnetometer` measurement with the attached calibration.
+    fn magnetometer(&self, magnetometer: &Vector3<N>) -> Vector3<N> {
+        self.mag_calibration.map_or(*magnetometer, |cal| cal.apply(magnetometer))
+    }
+}
+
+impl<N: RealField, A: Ahrs<N>> Ahrs<N> for Calibrated<N, A> {
+    fn update(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, magnetometer: &Vector3<N>) -> Result<&Quaternion<N>, AhrsError> {
+        let (gyroscope, accelerometer) = (self.gyroscope(gyroscope), self.accelerometer(accelerometer));
+        let magnetometer = self.magnetometer(magnetometer);
+        self.ahrs.update(&gyroscope, &accelerometer, &magnetometer)
+    }
+
+    fn update_imu(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>) -> Result<&Quaternion<N>, AhrsError> {
+        let (gyroscope, accelerometer) = (self.gyroscope(gyroscope), self.accelerometer(accelerometer));
+        self.ahrs.update_imu(&gyroscope, &accelerometer)
+    }
+
+    fn update_dt(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, magnetometer: &Vector3<N>, dt: N) -> Result<&Quaternion<N>, AhrsError> {
+        let (gyroscope, accelerometer) = (self.gyroscope(gyroscope), self.accelerometer(accelerometer));
+        let magnetometer = self.magnetometer(magnetometer);
+        self.ahrs.update_dt(&gyroscope, &accelerometer, &magnetometer, dt)
+    }
+
+    fn update_imu_dt(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, dt: N) -> Result<&Quaternion<N>, AhrsError> {
+        let (gyroscope, accelerometer) = (self.gyroscope(gyroscope), self.accelerometer(accelerometer));
+        self.ahrs.update_imu_dt(&gyroscope, &accelerometer, dt)
+    }
+
+    fn earth_frame(&self) -> EarthFrame {
+        self.ahrs.earth_frame()
+    }
+
+    fn correct_accelerometer(&self, accelerometer: &Vector3<N>) -> Vector3<N> {
+        self.ahrs.correct_accelerometer(&self.accelerometer(accelerometer))
+    }
+}
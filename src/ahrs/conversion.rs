@@ -0,0 +1,168 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Matrix3, Quaternion, UnitQuaternion, Vector3};
+use std::str::FromStr;
+use crate::ahrs::error::ConfigError;
+
+/// Axis order of an intrinsic Euler angle sequence.
+///
+/// A sequence `[a, b, c]` with angles `[α, β, γ]` describes the rotation `R_a(α) R_b(β) R_c(γ)`,
+/// so `ZYX` yields `[yaw, pitch, roll]`.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+pub enum EulerSequence {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+    XYX,
+    XZX,
+    YXY,
+    YZY,
+    ZXZ,
+    ZYZ,
+}
+
+impl EulerSequence {
+    /// Indices of the rotation axes, with `0`, `1` and `2` denoting x, y and z.
+    pub fn axes(&self) -> [usize; 3] {
+        match self {
+            EulerSequence::XYZ => [0, 1, 2],
+            EulerSequence::XZY => [0, 2, 1],
+            EulerSequence::YXZ => [1, 0, 2],
+            EulerSequence::YZX => [1, 2, 0],
+            EulerSequence::ZXY => [2, 0, 1],
+            EulerSequence::ZYX => [2, 1, 0],
+            EulerSequence::XYX => [0, 1, 0],
+            EulerSequence::XZX => [0, 2, 0],
+            EulerSequence::YXY => [1, 0, 1],
+            EulerSequence::YZY => [1, 2, 1],
+            EulerSequence::ZXZ => [2, 0, 2],
+            EulerSequence::ZYZ => [2, 1, 2],
+        }
+    }
+}
+
+impl FromStr for EulerSequence {
+    type Err = ConfigError;
+
+    /// Parses a sequence such as `"zyx"`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "xyz" => Ok(EulerSequence::XYZ),
+            "xzy" => Ok(EulerSequence::XZY),
+            "yxz" => Ok(EulerSequence::YXZ),
+            "yzx" => Ok(EulerSequence::YZX),
+            "zxy" => Ok(EulerSequence::ZXY),
+            "zyx" => Ok(EulerSequence::ZYX),
+            "xyx" => Ok(EulerSequence::XYX),
+            "xzx" => Ok(EulerSequence::XZX),
+            "yxy" => Ok(EulerSequence::YXY),
+            "yzy" => Ok(EulerSequence::YZY),
+            "zxz" => Ok(EulerSequence::ZXZ),
+            "zyz" => Ok(EulerSequence::ZYZ),
+            _ => Err(ConfigError::InvalidEulerSequence),
+        }
+    }
+}
+
+/// Converts `quat` to Euler angles in radians for the intrinsic rotation `sequence`.
+///
+/// The first and last angles lie in `[-π, π]`. The middle angle lies in `[-π/2, π/2]` for
+/// Tait–Bryan sequences and in `[0, π]` for proper Euler sequences. In gimbal lock the last angle
+/// is set to zero.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::Quaternion;
+/// use lobster_rust::ahrs::conversion::{euler_angles, EulerSequence};
+///
+/// fn main() {
+///     let ypr = euler_angles(&Quaternion::new(1.0f64, 0.0, 0.0, 0.0), EulerSequence::ZYX);
+/// }
+/// ```
+pub fn euler_angles<N: RealField>(quat: &Quaternion<N>, sequence: EulerSequence) -> Vector3<N> {
+    let zero: N = na::zero();
+    let two: N = na::convert(2.0);
+    let eps: N = na::convert(1.0e-7);
+
+    // Direct quaternion method of Bernardes and Viollet, formulated for the equivalent extrinsic
+    // sequence, which has the axes in reverse order
+    let axes = sequence.axes();
+    let (i, j) = (axes[2], axes[1]);
+    let proper = axes[0] == i;
+    let k = 3 - i - j;
+
+    // Parity of the permutation (i, j, k)
+    let sign: N = if (j + 3 - i) % 3 == 1 { N::one() } else { -N::one() };
+
+    let q = quat.normalize().coords;
+    let (a, b, c, d) = if proper {
+        (q[3], q[i], q[j], q[k] * sign)
+    } else {
+        (q[3] - q[j], q[i] + q[k] * sign, q[j] + q[3], q[k] * sign - q[i])
+    };
+
+    let mut second = two * c.hypot(d).atan2(a.hypot(b));
+
+    let half_sum = b.atan2(a);
+    let half_diff = d.atan2(c);
+
+    // In gimbal lock only the sum or difference is defined, so the last intrinsic angle is zero
+    let (mut first, mut third) = if second.abs() <= eps {
+        (zero, two * half_sum)
+    } else if (second - N::pi()).abs() <= eps {
+        (zero, two * half_diff)
+    } else {
+        (half_sum - half_diff, half_sum + half_diff)
+    };
+
+    if !proper {
+        third *= sign;
+        second -= N::frac_pi_2();
+    }
+
+    first = wrap_angle(first);
+    third = wrap_angle(third);
+
+    // Reverse back to the intrinsic order
+    Vector3::new(third, second, first)
+}
+
+/// Wraps `angle` to `[-π, π]`.
+fn wrap_angle<N: RealField>(angle: N) -> N {
+    if angle > N::pi() {
+        angle - N::two_pi()
+    } else if angle < -N::pi() {
+        angle + N::two_pi()
+    } else {
+        angle
+    }
+}
+
+/// Converts `quat` to the rotation matrix that maps vectors from the sensor frame to the Earth
+/// frame.
+pub fn rotation_matrix<N: RealField>(quat: &Quaternion<N>) -> Matrix3<N> {
+    UnitQuaternion::from_quaternion(*quat).to_rotation_matrix().into_inner()
+}
+
+/// Converts `quat` to a unit rotation axis and an angle in radians within `[0, π]`.
+///
+/// Returns the x axis with a zero angle when `quat` describes no rotation.
+pub fn axis_angle<N: RealField>(quat: &Quaternion<N>) -> (Vector3<N>, N) {
+    match UnitQuaternion::from_quaternion(*quat).axis_angle() {
+        Some((axis, angle)) => (axis.into_inner(), angle),
+        None => (Vector3::x(), N::zero()),
+    }
+}
+
+/// Converts `quat` to a rotation vector, the rotation axis scaled by the angle in radians.
+pub fn rotation_vector<N: RealField>(quat: &Quaternion<N>) -> Vector3<N> {
+    UnitQuaternion::from_quaternion(*quat).scaled_axis()
+}
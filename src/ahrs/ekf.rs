@@ -0,0 +1,430 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{
+    DimName, Matrix3, Matrix4, MatrixMN, MatrixN, Quaternion, Vector2, Vector3, Vector4,
+    Vector6, VectorN, U3, U4, U6, U7,
+};
+use crate::ahrs::ahrs::{validate_dt, Ahrs};
+use crate::ahrs::error::AhrsError;
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Extended Kalman filter AHRS implementation.
+///
+/// The filter state is made up of the orientation quaternion and the gyroscope bias, in the order
+/// `[w, i, j, k, bx, by, bz]`. The covariance matrix uses the same ordering.
+#[derive(PartialEq, Clone, Debug, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Ekf<N: RealField> {
+    /// Expected sampling period, in seconds.
+    sample_period: N,
+    /// Gyroscope noise density, in rad/s/√Hz.
+    gyro_noise: N,
+    /// Gyroscope bias random walk density, in rad/s²/√Hz.
+    gyro_bias_noise: N,
+    /// Noise density of the normalized accelerometer measurement, in 1/√Hz.
+    accel_noise: N,
+    /// Noise density of the normalized magnetometer measurement, in 1/√Hz.
+    mag_noise: N,
+    /// Estimated gyroscope bias, in rad/s.
+    gyro_bias: Vector3<N>,
+    /// State covariance matrix.
+    covariance: MatrixN<N, U7>,
+    /// Filter state quaternion.
+    pub quat: Quaternion<N>,
+}
+
+impl Default for Ekf<f64> {
+    /// Creates a new `Ekf` instance with default filter parameters:
+    ///
+    /// ```rust,ignore
+    /// Ekf {
+    ///     sample_period: 1.0f64/256.0,
+    ///     gyro_noise: 0.005f64,
+    ///     gyro_bias_noise: 0.0001f64,
+    ///     accel_noise: 0.01f64,
+    ///     mag_noise: 0.01f64,
+    ///     gyro_bias: Vector3 { x: 0.0f64, y: 0.0, z: 0.0 },
+    ///     covariance: diag(0.1, 0.1, 0.1, 0.1, 0.01, 0.01, 0.01),
+    ///     quat: Quaternion { w: 1.0f64, i: 0.0, j: 0.0, k: 0.0 }
+    /// }
+    /// ```
+    fn default() -> Ekf<f64> {
+        Ekf::new((1.0f64) / (256.0), 0.005, 0.0001, 0.01, 0.01)
+    }
+}
+
+impl<N: RealField> Ekf<N> {
+    /// Creates a new `Ekf` AHRS instance with identity quaternion and zero gyroscope bias.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `gyro_noise` - Gyroscope noise density, in rad/s/√Hz.
+    /// * `gyro_bias_noise` - Gyroscope bias random walk density, in rad/s²/√Hz.
+    /// * `accel_noise` - Noise density of the normalized accelerometer measurement.
+    /// * `mag_noise` - Noise density of the normalized magnetometer measurement.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate lobster_rust;
+    ///
+    /// use lobster_rust::ahrs::ekf::Ekf;
+    ///
+    /// fn main() {
+    ///     let ahrs = Ekf::new(0.002390625f64, 0.005, 0.0001, 0.01, 0.01);
+    /// }
+    /// ```
+    pub fn new(sample_period: N, gyro_noise: N, gyro_bias_noise: N, accel_noise: N, mag_noise: N) -> Self {
+        Ekf::new_with_quat(
+            sample_period,
+            gyro_noise,
+            gyro_bias_noise,
+            accel_noise,
+            mag_noise,
+            Quaternion::new(N::one(), N::zero(), N::zero(), N::zero()),
+        )
+    }
+
+    /// Creates a new `Ekf` AHRS instance with given quaternion and zero gyroscope bias.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `gyro_noise` - Gyroscope noise density, in rad/s/√Hz.
+    /// * `gyro_bias_noise` - Gyroscope bias random walk density, in rad/s²/√Hz.
+    /// * `accel_noise` - Noise density of the normalized accelerometer measurement.
+    /// * `mag_noise` - Noise density of the normalized magnetometer measurement.
+    /// * `quat` - Existing filter state quaternion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Quaternion;
+    /// use lobster_rust::ahrs::ekf::Ekf;
+    ///
+    /// fn main() {
+    ///     let ahrs = Ekf::new_with_quat(
+    ///         0.002390625f64,
+    ///         0.005,
+    ///         0.0001,
+    ///         0.01,
+    ///         0.01,
+    ///         Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    ///     );
+    /// }
+    /// ```
+    pub fn new_with_quat(
+        sample_period: N,
+        gyro_noise: N,
+        gyro_bias_noise: N,
+        accel_noise: N,
+        mag_noise: N,
+        quat: Quaternion<N>,
+    ) -> Self {
+        let quat_var: N = na::convert(0.1);
+        let bias_var: N = na::convert(0.01);
+
+        Ekf {
+            sample_period,
+            gyro_noise,
+            gyro_bias_noise,
+            accel_noise,
+            mag_noise,
+            gyro_bias: Vector3::zeros(),
+            covariance: MatrixN::<N, U7>::from_diagonal(&VectorN::<N, U7>::from_column_slice(&[
+                quat_var, quat_var, quat_var, quat_var, bias_var, bias_var, bias_var,
+            ])),
+            quat,
+        }
+    }
+
+    /// Estimated gyroscope bias, in rad/s.
+    pub fn gyro_bias(&self) -> Vector3<N> {
+        self.gyro_bias
+    }
+
+    /// State covariance matrix, ordered as `[w, i, j, k, bx, by, bz]`.
+    pub fn covariance(&self) -> MatrixN<N, U7> {
+        self.covariance
+    }
+
+    /// Propagates the state and covariance over time step `dt` using the bias-corrected
+    /// `gyroscope` measurement and returns the predicted quaternion and covariance.
+    fn predict(&self, gyroscope: &Vector3<N>, dt: N) -> (Quaternion<N>, MatrixN<N, U7>) {
+        let q = self.quat;
+
+        let zero: N = na::zero();
+        let half: N = na::convert(0.5);
+        let quarter: N = na::convert(0.25);
+
+        let w = gyroscope - self.gyro_bias;
+
+        // Integrate bias-corrected rate of change to yield quaternion
+        let q_dot = q * Quaternion::from_parts(zero, w) * half;
+        let quat = (q + q_dot * dt).normalize();
+
+        // Jacobian of q * (0, w) with respect to q
+        let omega = Matrix4::new(
+            zero, -w[0], -w[1], -w[2],
+            w[0], zero, w[2], -w[1],
+            w[1], -w[2], zero, w[0],
+            w[2], w[1], -w[0], zero,
+        );
+
+        // Jacobian of q * (0, w) with respect to w
+        let xi = quat_rate_jacobian(&q);
+
+        let mut f = MatrixN::<N, U7>::identity();
+        f.fixed_slice_mut::<U4, U4>(0, 0).copy_from(&(Matrix4::identity() + omega * (half * dt)));
+        f.fixed_slice_mut::<U4, U3>(0, 4).copy_from(&(xi * (-half * dt)));
+
+        // Gyroscope noise enters through the quaternion, bias noise is a random walk
+        let mut q_noise = MatrixN::<N, U7>::zeros();
+        q_noise.fixed_slice_mut::<U4, U4>(0, 0).copy_from(
+            &(xi * xi.transpose() * (quarter * self.gyro_noise * self.gyro_noise * dt)),
+        );
+        q_noise.fixed_slice_mut::<U3, U3>(4, 4).copy_from(
+            &(Matrix3::identity() * (self.gyro_bias_noise * self.gyro_bias_noise * dt)),
+        );
+
+        (quat, f * self.covariance * f.transpose() + q_noise)
+    }
+
+    /// Corrects the predicted quaternion `quat` and covariance `p` using measurement `z`, its
+    /// prediction `h`, measurement Jacobian `jac` and per-component measurement variance `var`,
+    /// and stores the corrected state. The state is left unchanged on error.
+    fn correct<M: DimName>(
+        &mut self,
+        quat: Quaternion<N>,
+        p: MatrixN<N, U7>,
+        z: &VectorN<N, M>,
+        h: &VectorN<N, M>,
+        jac: &MatrixMN<N, M, U7>,
+        var: &VectorN<N, M>,
+    ) -> Result<(), AhrsError>
+    where
+        na::DefaultAllocator: na::allocator::Allocator<N, M>
+            + na::allocator::Allocator<N, M, M>
+            + na::allocator::Allocator<N, M, U7>
+            + na::allocator::Allocator<N, U7, M>,
+    {
+        let half: N = na::convert(0.5);
+
+        let s = jac * p * jac.transpose() + MatrixN::<N, M>::from_diagonal(var);
+
+        let s_inv = match s.try_inverse() {
+            Some(n) => n,
+            None => return Err(AhrsError::SingularCovariance),
+        };
+
+        let k = p * jac.transpose() * s_inv;
+        let dx = &k * (z - h);
+
+        let q = quat.coords;
+        let q = Vector4::new(q[3], q[0], q[1], q[2]) + dx.fixed_rows::<U4>(0);
+        self.quat = Quaternion::new(q[0], q[1], q[2], q[3]).normalize();
+        self.gyro_bias += dx.fixed_rows::<U3>(4);
+
+        let p = (MatrixN::<N, U7>::identity() - k * jac) * p;
+        self.covariance = (p + p.transpose()) * half;
+
+        Ok(())
+    }
+}
+
+/// Jacobian of `q * (0, w)` with respect to `w`, with rows ordered as `[w, i, j, k]`.
+fn quat_rate_jacobian<N: RealField>(q: &Quaternion<N>) -> MatrixMN<N, U4, U3> {
+    MatrixMN::<N, U4, U3>::new(
+        -q.i, -q.j, -q.k,
+        q.w, -q.k, q.j,
+        q.k, q.w, -q.i,
+        -q.j, q.i, q.w,
+    )
+}
+
+/// Predicted direction of gravity in the sensor frame and its Jacobian with respect to the
+/// quaternion, with columns ordered as `[w, i, j, k]`.
+fn gravity_model<N: RealField>(q: &Quaternion<N>) -> (Vector3<N>, MatrixMN<N, U3, U4>) {
+    let two: N = na::convert(2.0);
+    let (w, x, y, z) = (q.w, q.i, q.j, q.k);
+
+    let h = Vector3::new(
+        two * (x * z - w * y),
+        two * (y * z + w * x),
+        w * w - x * x - y * y + z * z,
+    );
+
+    let jac = MatrixMN::<N, U3, U4>::new(
+        -two * y, two * z, -two * w, two * x,
+        two * x, two * w, two * z, two * y,
+        two * w, -two * x, -two * y, two * z,
+    );
+
+    (h, jac)
+}
+
+/// Predicted direction of the earth x axis in the sensor frame and its Jacobian with respect to
+/// the quaternion, with columns ordered as `[w, i, j, k]`.
+fn north_model<N: RealField>(q: &Quaternion<N>) -> (Vector3<N>, MatrixMN<N, U3, U4>) {
+    let two: N = na::convert(2.0);
+    let (w, x, y, z) = (q.w, q.i, q.j, q.k);
+
+    let h = Vector3::new(
+        w * w + x * x - y * y - z * z,
+        two * (x * y - w * z),
+        two * (x * z + w * y),
+    );
+
+    let jac = MatrixMN::<N, U3, U4>::new(
+        two * w, two * x, -two * y, -two * z,
+        -two * z, two * y, two * x, -two * w,
+        two * y, two * z, two * w, two * x,
+    );
+
+    (h, jac)
+}
+
+#[cfg(feature = "field_access")]
+impl<N: RealField> Ekf<N> {
+    /// Expected sampling period, in seconds.
+    pub fn sample_period(&self) -> N {
+        self.sample_period
+    }
+
+    /// Mutable reference to expected sampling period, in seconds.
+    pub fn sample_period_mut(&mut self) -> &mut N {
+        &mut self.sample_period
+    }
+
+    /// Mutable reference to state covariance matrix.
+    pub fn covariance_mut(&mut self) -> &mut MatrixN<N, U7> {
+        &mut self.covariance
+    }
+
+    /// Filter state quaternion.
+    pub fn quat(&self) -> Quaternion<N> {
+        self.quat
+    }
+
+    /// Mutable reference to filter state quaternion.
+    pub fn quat_mut(&mut self) -> &mut Quaternion<N> {
+        &mut self.quat
+    }
+}
+
+impl<N: RealField> Ahrs<N> for Ekf<N> {
+    fn update(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        magnetometer: &Vector3<N>,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = self.sample_period;
+        self.update_dt(gyroscope, accelerometer, magnetometer, dt)
+    }
+
+    fn update_imu(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = self.sample_period;
+        self.update_imu_dt(gyroscope, accelerometer, dt)
+    }
+
+    fn update_dt(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        magnetometer: &Vector3<N>,
+        dt: N,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+
+        let zero: N = na::zero();
+
+        // Normalize accelerometer measurement
+        let accel = match accelerometer.try_normalize(zero) {
+            Some(n) => n,
+            None => return Err(AhrsError::AccelerometerNormZero),
+        };
+
+        // Normalize magnetometer measurement
+        let mag = match magnetometer.try_normalize(zero) {
+            Some(n) => n,
+            None => {
+                return Err(AhrsError::MagnetometerNormZero);
+            }
+        };
+
+        let (q, p) = self.predict(gyroscope, dt);
+
+        // Reference direction of Earth's magnetic field
+        let h = q * (Quaternion::from_parts(zero, mag) * q.conjugate());
+        let b = Vector2::new(Vector2::new(h[0], h[1]).norm(), h[2]);
+
+        // Predicted measurements and their Jacobians
+        let (g_h, g_jac) = gravity_model(&q);
+        let (n_h, n_jac) = north_model(&q);
+
+        let m_h = n_h * b[0] + g_h * b[1];
+        let m_jac = n_jac * b[0] + g_jac * b[1];
+
+        let mut jac = MatrixMN::<N, U6, U7>::zeros();
+        jac.fixed_slice_mut::<U3, U4>(0, 0).copy_from(&g_jac);
+        jac.fixed_slice_mut::<U3, U4>(3, 0).copy_from(&m_jac);
+
+        let a_var = self.accel_noise * self.accel_noise / dt;
+        let m_var = self.mag_noise * self.mag_noise / dt;
+
+        self.correct(
+            q,
+            p,
+            &Vector6::new(accel[0], accel[1], accel[2], mag[0], mag[1], mag[2]),
+            &Vector6::new(g_h[0], g_h[1], g_h[2], m_h[0], m_h[1], m_h[2]),
+            &jac,
+            &Vector6::new(a_var, a_var, a_var, m_var, m_var, m_var),
+        )?;
+
+        Ok(&self.quat)
+    }
+
+    fn update_imu_dt(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        dt: N,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+
+        let zero: N = na::zero();
+
+        // Normalize accelerometer measurement
+        let accel = match accelerometer.try_normalize(zero) {
+            Some(n) => n,
+            None => {
+                return Err(AhrsError::AccelerometerNormZero);
+            }
+        };
+
+        let (q, p) = self.predict(gyroscope, dt);
+
+        // Predicted measurement and its Jacobian
+        let (g_h, g_jac) = gravity_model(&q);
+
+        let mut jac = MatrixMN::<N, U3, U7>::zeros();
+        jac.fixed_slice_mut::<U3, U4>(0, 0).copy_from(&g_jac);
+
+        let a_var = self.accel_noise * self.accel_noise / dt;
+
+        self.correct(q, p, &accel, &g_h, &jac, &Vector3::new(a_var, a_var, a_var))?;
+
+        Ok(&self.quat)
+    }
+}
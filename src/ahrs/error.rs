@@ -0,0 +1,77 @@
+use std::error::Error;
+use std::fmt;
+
+/// Reasons an AHRS filter update or orientation estimate can fail.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+pub enum AhrsError {
+    /// The accelerometer measurement has zero norm.
+    AccelerometerNormZero,
+    /// The magnetometer measurement has zero norm.
+    MagnetometerNormZero,
+    /// A sensor measurement contains a NaN or infinite component.
+    NonFiniteInput,
+    /// The corrective gradient vanished or the update produced a non-finite state.
+    DegenerateGradient,
+    /// The filter state was already non-finite before the update.
+    NonFiniteState,
+    /// The time step is not positive and finite.
+    InvalidTimeStep,
+    /// The innovation covariance of a Kalman filter update could not be inverted.
+    SingularCovariance,
+    /// The accelerometer and magnetometer measurements are parallel, so heading is undefined.
+    ParallelVectors,
+    /// No samples were given to average.
+    NoSamples,
+}
+
+impl fmt::Display for AhrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            AhrsError::AccelerometerNormZero => "Accelerometer norm divided by zero.",
+            AhrsError::MagnetometerNormZero => "Magnetometer norm divided by zero.",
+            AhrsError::NonFiniteInput => "Sensor measurement is not finite.",
+            AhrsError::DegenerateGradient => "Filter update is degenerate.",
+            AhrsError::NonFiniteState => "Filter state is not finite.",
+            AhrsError::InvalidTimeStep => "Time step must be positive and finite.",
+            AhrsError::SingularCovariance => "Innovation covariance is singular.",
+            AhrsError::ParallelVectors => "Accelerometer and magnetometer are parallel.",
+            AhrsError::NoSamples => "No samples to average.",
+        };
+
+        f.write_str(msg)
+    }
+}
+
+impl Error for AhrsError {}
+
+/// Reasons a setting or calibration is rejected before any filter runs.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+pub enum ConfigError {
+    /// The string does not name one of the twelve Euler angle sequences.
+    InvalidEulerSequence,
+    /// The calibration parameters are singular, or the calibration samples are too few or too
+    /// degenerate to determine a fit.
+    InvalidCalibration,
+    /// The string does not name a supported unit.
+    InvalidUnit,
+    /// The string does not name a supported Earth frame.
+    InvalidFrame,
+    /// The axis mapping does not name every sensor axis exactly once.
+    InvalidAxisMapping,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ConfigError::InvalidEulerSequence => "Unknown Euler angle sequence.",
+            ConfigError::InvalidCalibration => "Calibration does not determine a valid correction.",
+            ConfigError::InvalidUnit => "Unknown unit.",
+            ConfigError::InvalidFrame => "Unknown Earth frame.",
+            ConfigError::InvalidAxisMapping => "Axis mapping must name every sensor axis once.",
+        };
+
+        f.write_str(msg)
+    }
+}
+
+impl Error for ConfigError {}
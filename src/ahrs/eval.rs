@@ -0,0 +1,235 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Quaternion, Vector3};
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+use crate::ahrs::ahrs::Ahrs;
+use crate::ahrs::conversion::{euler_angles, EulerSequence};
+use crate::ahrs::error::AhrsError;
+use crate::ahrs::sim::Simulation;
+use std::error::Error;
+use std::fmt;
+
+/// Accuracy of estimated orientations against ground truth.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Accuracy<N: RealField> {
+    /// Root mean square angular error, in radians.
+    pub rmse: N,
+    /// Root mean square roll, pitch and yaw errors, in radians.
+    pub euler_rmse: Vector3<N>,
+    /// Largest angular error, in radians.
+    pub max_error: N,
+    /// Time after which the angular error stays below the convergence threshold, or `None` if it
+    /// ends above it.
+    pub convergence_time: Option<N>,
+    /// Rate at which the heading error grows, in rad/s.
+    pub heading_drift_rate: N,
+}
+
+/// Reasons evaluating estimated orientations against ground truth can fail.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+pub enum EvalError {
+    /// Sequences that must correspond sample by sample have different lengths.
+    LengthMismatch,
+    /// The sequences are empty.
+    NoSamples,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            EvalError::LengthMismatch => "Sequences must have the same length.",
+            EvalError::NoSamples => "No samples to evaluate.",
+        };
+
+        f.write_str(msg)
+    }
+}
+
+impl Error for EvalError {}
+
+/// Angle of the rotation between an `estimate` and the `truth`, in radians within `[0, π]`.
+///
+/// Both `q` and `-q` describe the same orientation, so the sign of either quaternion does not
+/// change the error.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::Quaternion;
+/// use lobster_rust::ahrs::eval::angular_error;
+///
+/// fn main() {
+///     let truth = Quaternion::new(1.0f64, 0.0, 0.0, 0.0);
+///
+///     assert_eq!(angular_error(&-truth, &truth), 0.0);
+/// }
+/// ```
+pub fn angular_error<N: RealField>(estimate: &Quaternion<N>, truth: &Quaternion<N>) -> N {
+    let difference = truth.normalize().conjugate() * estimate.normalize();
+
+    na::convert::<f64, N>(2.0) * difference.imag().norm().atan2(difference.w.abs())
+}
+
+/// Roll, pitch and yaw of an `estimate` minus those of the `truth`, in radians within `[-π, π]`.
+///
+/// The angles are those of the intrinsic z-y-x sequence, so roll and yaw errors are ill-defined
+/// near a pitch of ±90°.
+pub fn euler_error<N: RealField>(estimate: &Quaternion<N>, truth: &Quaternion<N>) -> Vector3<N> {
+    let estimate = euler_angles(estimate, EulerSequence::ZYX);
+    let truth = euler_angles(truth, EulerSequence::ZYX);
+
+    Vector3::new(
+        wrap_angle(estimate[2] - truth[2]),
+        wrap_angle(estimate[1] - truth[1]),
+        wrap_angle(estimate[0] - truth[0]),
+    )
+}
+
+/// Angular error of every estimate against the ground truth sample at the same index.
+///
+/// Returns an `Err(EvalError)` if the lengths differ.
+pub fn angular_errors<N: RealField>(estimates: &[Quaternion<N>], truth: &[Quaternion<N>]) -> Result<Vec<N>, EvalError> {
+    if estimates.len() != truth.len() {
+        return Err(EvalError::LengthMismatch);
+    }
+
+    Ok(estimates.iter().zip(truth.iter()).map(|(e, t)| angular_error(e, t)).collect())
+}
+
+/// Time after which every angular error in `errors` stays below `threshold`, or `None` if the
+/// last error is not below it.
+pub fn convergence_time<N: RealField>(errors: &[N], times: &[N], threshold: N) -> Option<N> {
+    match errors.iter().rposition(|e| *e >= threshold || !e.is_finite()) {
+        Some(last) if last + 1 < errors.len() => Some(times[last + 1]),
+        Some(_) => None,
+        None => times.first().cloned(),
+    }
+}
+
+/// Rate at which the heading error grows, in rad/s, from a least squares line through the
+/// unwrapped yaw error at `times`.
+///
+/// Returns an `Err(EvalError)` if the lengths differ or there are no samples.
+pub fn heading_drift_rate<N: RealField>(estimates: &[Quaternion<N>], truth: &[Quaternion<N>], times: &[N]) -> Result<N, EvalError> {
+    if estimates.len() != truth.len() || times.len() != truth.len() {
+        return Err(EvalError::LengthMismatch);
+    }
+
+    if times.is_empty() {
+        return Err(EvalError::NoSamples);
+    }
+
+    // Unwrap the yaw error so a drift through ±π keeps growing
+    let mut yaw = Vec::with_capacity(times.len());
+    for (e, t) in estimates.iter().zip(truth.iter()) {
+        let error = euler_error(e, t)[2];
+        let unwrapped = match yaw.last() {
+            Some(last) => *last + wrap_angle(error - *last),
+            None => error,
+        };
+        yaw.push(unwrapped);
+    }
+
+    let count: N = na::convert(times.len() as f64);
+    let mean_t = times.iter().fold(N::zero(), |sum, t| sum + *t) / count;
+    let mean_yaw = yaw.iter().fold(N::zero(), |sum, y| sum + *y) / count;
+
+    let (covariance, variance) = times.iter().zip(yaw.iter()).fold((N::zero(), N::zero()), |(c, v), (t, y)| {
+        (c + (*t - mean_t) * (*y - mean_yaw), v + (*t - mean_t) * (*t - mean_t))
+    });
+
+    if variance > N::zero() {
+        Ok(covariance / variance)
+    } else {
+        Ok(N::zero())
+    }
+}
+
+/// Evaluates estimated orientations against ground truth.
+///
+/// Returns an `Err(EvalError)` if the lengths differ or there are no samples.
+///
+/// # Arguments
+///
+/// * `estimates` - Estimated orientations, relative to the same Earth frame as the truth.
+/// * `truth` - Ground truth orientations.
+/// * `times` - Time of every sample, in seconds.
+/// * `threshold` - Angular error below which the estimate has converged, in radians.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::{UnitQuaternion, Vector3};
+/// use lobster_rust::ahrs::eval::{evaluate, track};
+/// use lobster_rust::ahrs::madgwick_filter::Madgwick;
+/// use lobster_rust::ahrs::sim::{simulate, ImuModel, Segment, Trajectory};
+///
+/// fn main() {
+///     let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+///     trajectory.push(Segment::constant(5.0, Vector3::new(0.0, 0.0, 0.5), Vector3::zeros()));
+///
+///     let sim = simulate(&trajectory, &ImuModel::default(), 0);
+///     let estimates = track(&mut Madgwick::new(0.01, 0.1), &sim, true).unwrap();
+///
+///     let truth: Vec<_> = sim.truth.iter().map(|t| t.quat).collect();
+///     let times: Vec<_> = sim.truth.iter().map(|t| t.time).collect();
+///
+///     let accuracy = evaluate(&estimates, &truth, &times, 0.01).unwrap();
+/// }
+/// ```
+pub fn evaluate<N: RealField>(estimates: &[Quaternion<N>], truth: &[Quaternion<N>], times: &[N], threshold: N) -> Result<Accuracy<N>, EvalError> {
+    let heading_drift_rate = heading_drift_rate(estimates, truth, times)?;
+    let errors = angular_errors(estimates, truth)?;
+
+    let count: N = na::convert(errors.len() as f64);
+
+    let rmse = (errors.iter().fold(N::zero(), |sum, e| sum + *e * *e) / count).sqrt();
+    let max_error = errors.iter().fold(N::zero(), |max, e| max.max(*e));
+
+    let euler_rmse = (estimates.iter().zip(truth.iter()).fold(Vector3::zeros(), |sum, (e, t)| {
+        let error = euler_error(e, t);
+        sum + error.component_mul(&error)
+    }) / count).map(|x| x.sqrt());
+
+    Ok(Accuracy {
+        rmse,
+        euler_rmse,
+        max_error,
+        convergence_time: convergence_time(&errors, times, threshold),
+        heading_drift_rate,
+    })
+}
+
+/// Runs an `ahrs` over the measurements of a simulation and returns the estimated orientation
+/// after every sample, expressed in north-west-up like the ground truth.
+///
+/// The filter should have the sampling period of the simulated trajectory. The magnetometer is
+/// ignored unless `magnetometer` is true. Returns an `Err(AhrsError)` if any update fails.
+pub fn track<N: RealField, A: Ahrs<N>>(ahrs: &mut A, simulation: &Simulation<N>, magnetometer: bool) -> Result<Vec<Quaternion<N>>, AhrsError> {
+    let mut estimates = Vec::with_capacity(simulation.truth.len());
+
+    for n in 0..simulation.truth.len() {
+        let quat = if magnetometer {
+            *ahrs.update(&simulation.gyroscope[n], &simulation.accelerometer[n], &simulation.magnetometer[n])?
+        } else {
+            *ahrs.update_imu(&simulation.gyroscope[n], &simulation.accelerometer[n])?
+        };
+
+        estimates.push(ahrs.earth_frame().quat_to_nwu(&quat));
+    }
+
+    Ok(estimates)
+}
+
+/// Wraps `angle` to `[-π, π]`.
+fn wrap_angle<N: RealField>(angle: N) -> N {
+    angle - N::two_pi() * ((angle + N::pi()) / N::two_pi()).floor()
+}
@@ -0,0 +1,212 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Matrix3, Quaternion, UnitQuaternion, Vector3};
+use std::str::FromStr;
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+use crate::ahrs::error::ConfigError;
+
+/// Earth frame convention of the filter state quaternion.
+///
+/// Filters fuse in the north-west-up frame, with the magnetic field pointing north along the x
+/// axis and gravity along -z, and express their state in the selected frame.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum EarthFrame {
+    /// North, west, up.
+    Nwu,
+    /// North, east, down.
+    Ned,
+    /// East, north, up.
+    Enu,
+}
+
+impl EarthFrame {
+    /// Rotation taking north-west-up coordinates to coordinates of this frame.
+    pub fn rotation_from_nwu<N: RealField>(&self) -> UnitQuaternion<N> {
+        let half: N = na::convert(0.5);
+
+        match self {
+            EarthFrame::Nwu => UnitQuaternion::identity(),
+            EarthFrame::Ned => UnitQuaternion::from_quaternion(Quaternion::new(N::zero(), N::one(), N::zero(), N::zero())),
+            EarthFrame::Enu => UnitQuaternion::from_quaternion(Quaternion::new(half.sqrt(), N::zero(), N::zero(), half.sqrt())),
+        }
+    }
+
+    /// Expresses an orientation `quat` relative to north-west-up in this frame.
+    pub fn quat_from_nwu<N: RealField>(&self, quat: &Quaternion<N>) -> Quaternion<N> {
+        match self {
+            EarthFrame::Nwu => *quat,
+            _ => self.rotation_from_nwu().into_inner() * quat,
+        }
+    }
+
+    /// Expresses an orientation `quat` relative to this frame in north-west-up.
+    pub fn quat_to_nwu<N: RealField>(&self, quat: &Quaternion<N>) -> Quaternion<N> {
+        match self {
+            EarthFrame::Nwu => *quat,
+            _ => self.rotation_from_nwu().inverse().into_inner() * quat,
+        }
+    }
+
+    /// Unit vector pointing up, against gravity, in this frame.
+    pub fn up<N: RealField>(&self) -> Vector3<N> {
+        match self {
+            EarthFrame::Ned => -Vector3::z(),
+            _ => Vector3::z(),
+        }
+    }
+}
+
+impl Default for EarthFrame {
+    /// North-west-up, the frame the filters fuse in.
+    fn default() -> Self {
+        EarthFrame::Nwu
+    }
+}
+
+impl FromStr for EarthFrame {
+    type Err = ConfigError;
+
+    /// Parses `"nwu"`, `"ned"` or `"enu"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nwu" => Ok(EarthFrame::Nwu),
+            "ned" => Ok(EarthFrame::Ned),
+            "enu" => Ok(EarthFrame::Enu),
+            _ => Err(ConfigError::InvalidFrame),
+        }
+    }
+}
+
+/// Linear map from the axes of a sensor to the body frame of the filter, covering axis
+/// permutations, sign flips of left-handed sensors and mounting rotations.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AxisMapping<N: RealField> {
+    /// Matrix taking sensor coordinates to body coordinates.
+    matrix: Matrix3<N>,
+}
+
+impl<N: RealField> AxisMapping<N> {
+    /// Creates a new `AxisMapping` from the `matrix` taking sensor coordinates to body coordinates.
+    pub fn new(matrix: Matrix3<N>) -> Self {
+        AxisMapping { matrix }
+    }
+
+    /// Mapping leaving the sensor axes unchanged.
+    pub fn identity() -> Self {
+        AxisMapping::new(Matrix3::identity())
+    }
+
+    /// Creates a new `AxisMapping` from the sensor axis along every body axis.
+    ///
+    /// `axes` lists the signed sensor axes along the body x, y and z axes, separated by commas, so
+    /// `"y,x,-z"` swaps x and y and flips z. Returns an `Err(ConfigError)` unless every sensor axis
+    /// appears exactly once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Vector3;
+    /// use lobster_rust::ahrs::frame::AxisMapping;
+    ///
+    /// fn main() {
+    ///     let mapping = AxisMapping::from_axes("y,x,-z").unwrap();
+    ///
+    ///     assert_eq!(mapping.apply(&Vector3::new(1.0f64, 2.0, 3.0)), Vector3::new(2.0, 1.0, -3.0));
+    /// }
+    /// ```
+    pub fn from_axes(axes: &str) -> Result<Self, ConfigError> {
+        let mut matrix = Matrix3::zeros();
+        let mut used = [false; 3];
+
+        let tokens: Vec<&str> = axes.split(',').map(str::trim).collect();
+        if tokens.len() != 3 {
+            return Err(ConfigError::InvalidAxisMapping);
+        }
+
+        for (row, token) in tokens.iter().enumerate() {
+            let axis = token.trim_start_matches(&['+', '-'][..]);
+            let sign = match &token[..token.len() - axis.len()] {
+                "" | "+" => N::one(),
+                "-" => -N::one(),
+                _ => return Err(ConfigError::InvalidAxisMapping),
+            };
+
+            let col = match axis.to_ascii_lowercase().as_str() {
+                "x" => 0,
+                "y" => 1,
+                "z" => 2,
+                _ => return Err(ConfigError::InvalidAxisMapping),
+            };
+
+            if used[col] {
+                return Err(ConfigError::InvalidAxisMapping);
+            }
+            used[col] = true;
+
+            matrix[(row, col)] = sign;
+        }
+
+        Ok(AxisMapping::new(matrix))
+    }
+
+    /// Creates a new `AxisMapping` for a sensor mounted with orientation `rotation` relative to the
+    /// body.
+    pub fn from_rotation(rotation: &UnitQuaternion<N>) -> Self {
+        AxisMapping::new(rotation.to_rotation_matrix().into_inner())
+    }
+
+    /// Mapping applying this mapping followed by the mounting `rotation`.
+    pub fn rotated(&self, rotation: &UnitQuaternion<N>) -> Self {
+        AxisMapping::new(rotation.to_rotation_matrix().into_inner() * self.matrix)
+    }
+
+    /// Expresses a sensor measurement in the body frame.
+    pub fn apply(&self, measurement: &Vector3<N>) -> Vector3<N> {
+        self.matrix * measurement
+    }
+
+    /// Matrix taking sensor coordinates to body coordinates.
+    pub fn matrix(&self) -> Matrix3<N> {
+        self.matrix
+    }
+}
+
+/// Earth frame convention and sensor axis mappings of a filter.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Frames<N: RealField> {
+    /// Earth frame of the filter state quaternion.
+    pub earth: EarthFrame,
+    /// Mapping of the gyroscope axes to the body frame.
+    pub gyroscope: AxisMapping<N>,
+    /// Mapping of the accelerometer axes to the body frame.
+    pub accelerometer: AxisMapping<N>,
+    /// Mapping of the magnetometer axes to the body frame.
+    pub magnetometer: AxisMapping<N>,
+}
+
+impl<N: RealField> Frames<N> {
+    /// Creates new `Frames` in the `earth` frame with every sensor aligned with the body.
+    pub fn new(earth: EarthFrame) -> Self {
+        Frames {
+            earth,
+            gyroscope: AxisMapping::identity(),
+            accelerometer: AxisMapping::identity(),
+            magnetometer: AxisMapping::identity(),
+        }
+    }
+}
+
+impl<N: RealField> Default for Frames<N> {
+    /// North-west-up with every sensor aligned with the body.
+    fn default() -> Self {
+        Frames::new(EarthFrame::default())
+    }
+}
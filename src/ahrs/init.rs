@@ -0,0 +1,177 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Matrix3, Matrix4, Quaternion, SymmetricEigen, UnitQuaternion, Vector3};
+use crate::ahrs::error::AhrsError;
+
+/// Averages a collection of static sensor samples.
+///
+/// Returns the mean vector on success, or an `Err(AhrsError)` if `samples` is empty.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::Vector3;
+/// use lobster_rust::ahrs::init::mean;
+///
+/// fn main() {
+///     let accel = mean(&[Vector3::new(0.0f64, 0.1, 1.0), Vector3::new(0.0, -0.1, 1.0)]);
+/// }
+/// ```
+pub fn mean<N: RealField>(samples: &[Vector3<N>]) -> Result<Vector3<N>, AhrsError> {
+    if samples.is_empty() {
+        return Err(AhrsError::NoSamples);
+    }
+
+    let count: N = na::convert(samples.len() as f64);
+    let sum = samples.iter().fold(Vector3::zeros(), |acc, s| acc + s);
+
+    Ok(sum / count)
+}
+
+/// Computes the orientation of the sensor from a static `accelerometer` and `magnetometer`
+/// sample using the TRIAD algorithm.
+///
+/// Gravity is trusted fully and the magnetometer is only used to determine heading, so the
+/// magnetic dip angle does not need to be known. The returned quaternion uses the same convention
+/// as the filter state quaternions.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::Vector3;
+/// use lobster_rust::ahrs::init::triad;
+///
+/// fn main() {
+///     let quat = triad(&Vector3::new(0.0f64, 0.0, 1.0), &Vector3::new(0.6, 0.0, -0.8));
+/// }
+/// ```
+pub fn triad<N: RealField>(
+    accelerometer: &Vector3<N>,
+    magnetometer: &Vector3<N>,
+) -> Result<Quaternion<N>, AhrsError> {
+    let zero: N = na::zero();
+
+    // Normalize accelerometer measurement
+    let accel = match accelerometer.try_normalize(zero) {
+        Some(n) => n,
+        None => return Err(AhrsError::AccelerometerNormZero),
+    };
+
+    // Normalize magnetometer measurement
+    if magnetometer.try_normalize(zero).is_none() {
+        return Err(AhrsError::MagnetometerNormZero);
+    }
+
+    // Sensor frame triad, with the second axis pointing west
+    let west = match accel.cross(magnetometer).try_normalize(zero) {
+        Some(n) => n,
+        None => return Err(AhrsError::ParallelVectors),
+    };
+    let north = west.cross(&accel);
+
+    // Rows are the Earth frame axes expressed in the sensor frame
+    let rot = Matrix3::from_rows(&[north.transpose(), west.transpose(), accel.transpose()]);
+
+    Ok(UnitQuaternion::from_matrix(&rot).into_inner())
+}
+
+/// Computes the orientation of the sensor from a static `accelerometer` and `magnetometer`
+/// sample using Davenport's q-method, the exact solution of Wahba's problem that QUEST
+/// approximates.
+///
+/// The magnetic dip angle is taken from the measured angle between both vectors, and
+/// `accel_weight` and `mag_weight` set the relative trust in each sensor. The returned quaternion
+/// uses the same convention as the filter state quaternions.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::Vector3;
+/// use lobster_rust::ahrs::init::davenport;
+///
+/// fn main() {
+///     let quat = davenport(&Vector3::new(0.0f64, 0.0, 1.0), &Vector3::new(0.6, 0.0, -0.8), 1.0, 1.0);
+/// }
+/// ```
+pub fn davenport<N: RealField>(
+    accelerometer: &Vector3<N>,
+    magnetometer: &Vector3<N>,
+    accel_weight: N,
+    mag_weight: N,
+) -> Result<Quaternion<N>, AhrsError> {
+    let zero: N = na::zero();
+    let one: N = na::one();
+    let half: N = na::convert(0.5);
+
+    // Normalize accelerometer measurement
+    let accel = match accelerometer.try_normalize(zero) {
+        Some(n) => n,
+        None => return Err(AhrsError::AccelerometerNormZero),
+    };
+
+    // Normalize magnetometer measurement
+    let mag = match magnetometer.try_normalize(zero) {
+        Some(n) => n,
+        None => return Err(AhrsError::MagnetometerNormZero),
+    };
+
+    // Reference directions in the Earth frame, with the dip angle taken from the measurement
+    let dip = accel.dot(&mag);
+    if one - dip.abs() <= N::default_epsilon() {
+        return Err(AhrsError::ParallelVectors);
+    }
+
+    let gravity_ref = Vector3::new(zero, zero, one);
+    let mag_ref = Vector3::new((one - dip * dip).sqrt(), zero, dip);
+
+    // Attitude profile matrix, maximized by the eigenvector of the largest eigenvalue
+    let k = attitude_profile(&gravity_ref, &accel) * accel_weight
+        + attitude_profile(&mag_ref, &mag) * mag_weight;
+    let k = (k + k.transpose()) * half;
+
+    let eigen = SymmetricEigen::new(k);
+    let best = eigen.eigenvalues.imax();
+    let v = eigen.eigenvectors.column(best);
+
+    let quat = Quaternion::new(v[0], v[1], v[2], v[3]).normalize();
+
+    // Both signs describe the same rotation, prefer a non-negative scalar part
+    if quat.w < zero {
+        Ok(-quat)
+    } else {
+        Ok(quat)
+    }
+}
+
+/// Matrix `M` such that `q' M q = reference . (q * observation * q^-1)`, with quaternions ordered
+/// as `[w, i, j, k]`.
+fn attitude_profile<N: RealField>(reference: &Vector3<N>, observation: &Vector3<N>) -> Matrix4<N> {
+    let zero: N = na::zero();
+    let (r, b) = (reference, observation);
+
+    // Left multiplication by the reference
+    let left = Matrix4::new(
+        zero, -r[0], -r[1], -r[2],
+        r[0], zero, -r[2], r[1],
+        r[1], r[2], zero, -r[0],
+        r[2], -r[1], r[0], zero,
+    );
+
+    // Right multiplication by the observation
+    let right = Matrix4::new(
+        zero, -b[0], -b[1], -b[2],
+        b[0], zero, b[2], -b[1],
+        b[1], -b[2], zero, b[0],
+        b[2], b[1], -b[0], zero,
+    );
+
+    left.transpose() * right
+}
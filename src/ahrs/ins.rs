@@ -0,0 +1,198 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::Vector3;
+use crate::ahrs::acceleration::{self, AccelUnit};
+use crate::ahrs::ahrs::{validate_dt, Ahrs};
+use crate::ahrs::error::AhrsError;
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Strapdown inertial navigation on top of an AHRS filter.
+///
+/// Every update advances the orientation filter, removes gravity from the accelerometer measurement
+/// in the Earth frame and integrates the remaining linear acceleration to velocity and position.
+/// Velocity is in m/s and position in m, whatever the accelerometer unit, and both are expressed in
+/// the Earth frame of the filter.
+///
+/// Integration drift is unbounded on its own, so velocity should be zeroed with
+/// `zero_velocity_update` whenever the sensor is known to be stationary, and position corrected
+/// with `correct_position` when an external fix is available.
+///
+/// The accelerometer measurement is corrected like the filter corrects it before fusion, so an
+/// attached calibration or axis mapping also applies to navigation.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Ins<N: RealField, A> {
+    /// Orientation filter.
+    ahrs: A,
+    /// Magnitude of gravity, in `unit`.
+    gravity: N,
+    /// Unit of the accelerometer measurements and `gravity`.
+    unit: AccelUnit,
+    /// Velocity in the Earth frame, in m/s.
+    velocity: Vector3<N>,
+    /// Position in the Earth frame, in m.
+    position: Vector3<N>,
+    /// Linear acceleration in the Earth frame of the last update, in m/s².
+    acceleration: Vector3<N>,
+}
+
+impl<N: RealField, A: Ahrs<N>> Ins<N, A> {
+    /// Creates a new `Ins` at rest at the origin.
+    ///
+    /// # Arguments
+    ///
+    /// * `ahrs` - Orientation filter, updated by every navigation update.
+    /// * `gravity` - Magnitude of gravity, in `unit`.
+    /// * `unit` - Unit of the accelerometer measurements.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::acceleration::AccelUnit;
+    /// use lobster_rust::ahrs::ins::Ins;
+    /// use lobster_rust::ahrs::madgwick_filter::Madgwick;
+    ///
+    /// fn main() {
+    ///     let ins = Ins::new(Madgwick::default(), 1.0, AccelUnit::G);
+    /// }
+    /// ```
+    pub fn new(ahrs: A, gravity: N, unit: AccelUnit) -> Self {
+        Ins {
+            ahrs,
+            gravity,
+            unit,
+            velocity: Vector3::zeros(),
+            position: Vector3::zeros(),
+            acceleration: Vector3::zeros(),
+        }
+    }
+
+    /// Updates the orientation with 9dof IMU values sampled `dt` seconds after the previous update,
+    /// then integrates velocity and position.
+    ///
+    /// Returns a reference to the updated position on success, or in the case of failure, an
+    /// `Err(AhrsError)` containing the reason, leaving velocity and position unchanged.
+    pub fn update(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        magnetometer: &Vector3<N>,
+        dt: N,
+    ) -> Result<&Vector3<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+        let quat = *self.ahrs.update_dt(gyroscope, accelerometer, magnetometer, dt)?;
+
+        let earth = acceleration::earth_acceleration(&quat, &self.ahrs.correct_accelerometer(accelerometer));
+        Ok(self.integrate(&earth, dt))
+    }
+
+    /// Updates the orientation with 6dof IMU values sampled `dt` seconds after the previous update,
+    /// then integrates velocity and position.
+    ///
+    /// Returns a reference to the updated position on success, or in the case of failure, an
+    /// `Err(AhrsError)` containing the reason, leaving velocity and position unchanged.
+    pub fn update_imu(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        dt: N,
+    ) -> Result<&Vector3<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+        let quat = *self.ahrs.update_imu_dt(gyroscope, accelerometer, dt)?;
+
+        let earth = acceleration::earth_acceleration(&quat, &self.ahrs.correct_accelerometer(accelerometer));
+        Ok(self.integrate(&earth, dt))
+    }
+
+    /// Removes gravity from the Earth frame acceleration `earth` and integrates it over `dt`.
+    ///
+    /// Acceleration is held constant over the step, so position advances by the mean of the old
+    /// and new velocity.
+    fn integrate(&mut self, earth: &Vector3<N>, dt: N) -> &Vector3<N> {
+        let half: N = na::convert(0.5);
+
+        let linear = earth - self.ahrs.earth_frame().up() * self.gravity;
+        let linear = linear.map(|x| self.unit.convert(x, AccelUnit::MetersPerSecondSquared));
+
+        let velocity = self.velocity + linear * dt;
+        self.position += (self.velocity + velocity) * (half * dt);
+        self.velocity = velocity;
+        self.acceleration = linear;
+
+        &self.position
+    }
+
+    /// Applies a zero-velocity update, for when the sensor is known to be stationary.
+    ///
+    /// Returns the velocity that was removed, in m/s, which is the drift accumulated since the
+    /// last zero-velocity update.
+    pub fn zero_velocity_update(&mut self) -> Vector3<N> {
+        let drift = self.velocity;
+        self.velocity = Vector3::zeros();
+        drift
+    }
+
+    /// Corrects the position towards an external position `fix`, in m.
+    ///
+    /// `weight` between `0` and `1` sets how far the position moves towards `fix`, with `1`
+    /// replacing it.
+    pub fn correct_position(&mut self, fix: &Vector3<N>, weight: N) {
+        self.position += (fix - self.position) * weight;
+    }
+
+    /// Resets velocity and acceleration to zero and moves the position back to the origin. The
+    /// orientation filter is left untouched.
+    pub fn reset(&mut self) {
+        self.velocity = Vector3::zeros();
+        self.position = Vector3::zeros();
+        self.acceleration = Vector3::zeros();
+    }
+
+    /// Orientation filter.
+    pub fn ahrs(&self) -> &A {
+        &self.ahrs
+    }
+
+    /// Mutable reference to the orientation filter.
+    pub fn ahrs_mut(&mut self) -> &mut A {
+        &mut self.ahrs
+    }
+
+    /// Magnitude of gravity, in the accelerometer unit.
+    pub fn gravity(&self) -> N {
+        self.gravity
+    }
+
+    /// Unit of the accelerometer measurements.
+    pub fn unit(&self) -> AccelUnit {
+        self.unit
+    }
+
+    /// Velocity in the Earth frame, in m/s.
+    pub fn velocity(&self) -> Vector3<N> {
+        self.velocity
+    }
+
+    /// Sets the velocity in the Earth frame, in m/s.
+    pub fn set_velocity(&mut self, velocity: Vector3<N>) {
+        self.velocity = velocity;
+    }
+
+    /// Position in the Earth frame, in m.
+    pub fn position(&self) -> Vector3<N> {
+        self.position
+    }
+
+    /// Sets the position in the Earth frame, in m.
+    pub fn set_position(&mut self, position: Vector3<N>) {
+        self.position = position;
+    }
+
+    /// Linear acceleration in the Earth frame of the last update, in m/s².
+    pub fn acceleration(&self) -> Vector3<N> {
+        self.acceleration
+    }
+}
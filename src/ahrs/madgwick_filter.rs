@@ -2,16 +2,213 @@ extern crate nalgebra as na;
 extern crate alga;
 
 use alga::general::RealField;
-use na::{Matrix4, Matrix6, Quaternion, Vector2, Vector3, Vector4, Vector6};
-use crate::ahrs::ahrs::Ahrs;
+use na::{Matrix3, Matrix4, Matrix6, Quaternion, Vector2, Vector3, Vector4, Vector6};
+use crate::ahrs::acceleration::{self, AccelUnit, LinearAcceleration};
+use crate::ahrs::ahrs::{validate_dt, validate_finite, Ahrs};
+use crate::ahrs::conversion::{self, EulerSequence};
+use crate::ahrs::error::AhrsError;
+use crate::ahrs::frame::{EarthFrame, Frames};
+use crate::ahrs::init;
+use crate::ahrs::stationary::StationaryDetector;
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Lowers the Madgwick filter gain while the accelerometer measures dynamic acceleration on top of
+/// gravity.
+///
+/// With `e` the deviation of the accelerometer norm from `gravity`, relative to `gravity`, the gain
+/// is kept for `e <= threshold`, falls linearly to zero between `threshold` and `cutoff`, and is
+/// zero beyond `cutoff`.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AccelAdaptation<N: RealField> {
+    /// Magnitude of gravity, in accelerometer units.
+    gravity: N,
+    /// Relative deviation up to which the full gain is used.
+    threshold: N,
+    /// Relative deviation from which the gain is zero.
+    cutoff: N,
+}
+
+impl<N: RealField> AccelAdaptation<N> {
+    /// Creates a new `AccelAdaptation`.
+    ///
+    /// # Arguments
+    ///
+    /// * `gravity` - Magnitude of gravity in accelerometer units, `1` for g or `9.80665` for m/s².
+    /// * `threshold` - Relative deviation from gravity up to which the full gain is used.
+    /// * `cutoff` - Relative deviation from gravity from which the gain is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::madgwick_filter::AccelAdaptation;
+    ///
+    /// fn main() {
+    ///     let adaptation = AccelAdaptation::new(1.0f64, 0.1, 0.2);
+    /// }
+    /// ```
+    pub fn new(gravity: N, threshold: N, cutoff: N) -> Self {
+        AccelAdaptation { gravity, threshold, cutoff }
+    }
+
+    /// Factor in `[0, 1]` applied to the filter gain for an `accelerometer` measurement.
+    pub fn factor(&self, accelerometer: &Vector3<N>) -> N {
+        let deviation = (accelerometer.norm() - self.gravity).abs() / self.gravity;
+
+        if deviation <= self.threshold {
+            N::one()
+        } else if deviation >= self.cutoff {
+            N::zero()
+        } else {
+            (self.cutoff - deviation) / (self.cutoff - self.threshold)
+        }
+    }
+}
+
+/// Detects magnetic disturbances by comparing the strength and inclination of every magnetometer
+/// measurement against a reference field, which is either configured or learned from accepted
+/// measurements.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct MagRejection<N: RealField> {
+    /// Reference field strength in magnetometer units and inclination in radians, if known.
+    reference: Option<(N, N)>,
+    /// Relative deviation of the field strength up to which a measurement is accepted.
+    norm_tolerance: N,
+    /// Deviation of the inclination up to which a measurement is accepted, in radians.
+    dip_tolerance: N,
+    /// Weight of the magnetometer terms for rejected measurements, `0` falls back to an IMU update.
+    weight: N,
+    /// Rate at which accepted measurements update the reference, `0` keeps it fixed.
+    learning_rate: N,
+}
+
+impl<N: RealField> MagRejection<N> {
+    /// Creates a new `MagRejection` that learns the reference field, starting from the first
+    /// measurement.
+    ///
+    /// # Arguments
+    ///
+    /// * `norm_tolerance` - Relative deviation of the field strength up to which a measurement is
+    ///   accepted.
+    /// * `dip_tolerance` - Deviation of the inclination up to which a measurement is accepted, in
+    ///   radians.
+    /// * `weight` - Weight of the magnetometer terms for rejected measurements, `0` falls back to
+    ///   an IMU update.
+    /// * `learning_rate` - Rate in `[0, 1]` at which accepted measurements update the reference.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::madgwick_filter::MagRejection;
+    ///
+    /// fn main() {
+    ///     let rejection = MagRejection::learned(0.1f64, 0.1, 0.0, 0.01);
+    /// }
+    /// ```
+    pub fn learned(norm_tolerance: N, dip_tolerance: N, weight: N, learning_rate: N) -> Self {
+        MagRejection {
+            reference: None,
+            norm_tolerance,
+            dip_tolerance,
+            weight,
+            learning_rate,
+        }
+    }
+
+    /// Creates a new `MagRejection` with a fixed reference field of strength `norm` in magnetometer
+    /// units and inclination `dip` in radians, positive when the field points down.
+    pub fn with_reference(norm: N, dip: N, norm_tolerance: N, dip_tolerance: N, weight: N) -> Self {
+        MagRejection {
+            reference: Some((norm, dip)),
+            norm_tolerance,
+            dip_tolerance,
+            weight,
+            learning_rate: N::zero(),
+        }
+    }
+
+    /// Reference field strength in magnetometer units and inclination in radians, or `None` before
+    /// a learned reference has seen a measurement.
+    pub fn reference(&self) -> Option<(N, N)> {
+        self.reference
+    }
+
+    /// Weight of the magnetometer terms for rejected measurements.
+    pub fn weight(&self) -> N {
+        self.weight
+    }
+
+    /// Checks a `magnetometer` measurement against the reference field, using the
+    /// `accelerometer` measurement as the vertical, and learns from it if accepted.
+    ///
+    /// Returns whether the measurement is accepted.
+    pub fn accept(&mut self, accelerometer: &Vector3<N>, magnetometer: &Vector3<N>) -> bool {
+        let norm = magnetometer.norm();
+        let cos_up = accelerometer.dot(magnetometer) / (accelerometer.norm() * norm);
+        let dip = -cos_up.max(-N::one()).min(N::one()).asin();
+
+        let (ref_norm, ref_dip) = match self.reference {
+            Some(r) => r,
+            None => {
+                self.reference = Some((norm, dip));
+                return true;
+            }
+        };
+
+        let accepted = (norm - ref_norm).abs() <= self.norm_tolerance * ref_norm
+            && (dip - ref_dip).abs() <= self.dip_tolerance;
+
+        if accepted {
+            self.reference = Some((
+                ref_norm + (norm - ref_norm) * self.learning_rate,
+                ref_dip + (dip - ref_dip) * self.learning_rate,
+            ));
+        }
+
+        accepted
+    }
+}
 
 /// Madgwick AHRS implementation.
 #[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub struct Madgwick<N: RealField> {
     /// Expected sampling period, in seconds.
     sample_period: N,
     /// Filter gain.
     beta: N,
+    /// Filter gain at startup, decaying to `beta` over `gain_duration`.
+    initial_beta: N,
+    /// Time over which the gain decays from `initial_beta` to `beta`, in seconds.
+    gain_duration: N,
+    /// Time integrated by successful updates since the gain schedule started, in seconds.
+    elapsed: N,
+    /// Gain reduction during dynamic acceleration.
+    accel_adaptation: Option<AccelAdaptation<N>>,
+    /// Gyroscope drift compensation gain.
+    zeta: N,
+    /// Estimated gyroscope bias, in rad/s.
+    gyro_bias: Vector3<N>,
+    /// Whether a non-finite state is restored to the last good state on the next update.
+    auto_reset: bool,
+    /// Filter state quaternion after the last successful update.
+    last_quat: Quaternion<N>,
+    /// Estimated gyroscope bias after the last successful update, in rad/s.
+    last_gyro_bias: Vector3<N>,
+    /// Magnetic disturbance detection.
+    mag_rejection: Option<MagRejection<N>>,
+    /// Whether the magnetometer measurement of the last update was rejected.
+    mag_rejected: bool,
+    /// Stationary detection re-estimating the gyroscope bias.
+    stationary_detector: Option<StationaryDetector<N>>,
+    /// Time constant of the gyroscope bias re-estimation while stationary, in seconds.
+    bias_time_constant: N,
+    /// Whether the sensor was stationary at the last update.
+    stationary: bool,
+    /// Earth frame of the state quaternion and sensor axis mappings.
+    frames: Frames<N>,
     /// Filter state quaternion.
     pub quat: Quaternion<N>,
 }
@@ -23,6 +220,21 @@ impl Default for Madgwick<f64> {
     /// Madgwick {
     ///     sample_period: 1.0f64/256.0,
     ///     beta: 0.1f64,
+    ///     initial_beta: 0.1f64,
+    ///     gain_duration: 0.0f64,
+    ///     elapsed: 0.0f64,
+    ///     accel_adaptation: None,
+    ///     zeta: 0.0f64,
+    ///     gyro_bias: Vector3 { x: 0.0f64, y: 0.0, z: 0.0 },
+    ///     auto_reset: false,
+    ///     last_quat: Quaternion { w: 1.0f64, i: 0.0, j: 0.0, k: 0.0 },
+    ///     last_gyro_bias: Vector3 { x: 0.0f64, y: 0.0, z: 0.0 },
+    ///     mag_rejection: None,
+    ///     mag_rejected: false,
+    ///     stationary_detector: None,
+    ///     bias_time_constant: 1.0f64,
+    ///     stationary: false,
+    ///     frames: Frames::new(EarthFrame::Nwu),
     ///     quat: Quaternion { w: 1.0f64, i: 0.0, j: 0.0, k: 0.0 }
     /// }
     /// ```
@@ -30,6 +242,21 @@ impl Default for Madgwick<f64> {
         Madgwick {
             sample_period: (1.0f64) / (256.0),
             beta: 0.1f64,
+            initial_beta: 0.1f64,
+            gain_duration: 0.0f64,
+            elapsed: 0.0f64,
+            accel_adaptation: None,
+            zeta: 0.0f64,
+            gyro_bias: Vector3::new(0.0f64, 0.0, 0.0),
+            auto_reset: false,
+            last_quat: Quaternion::new(1.0f64, 0.0, 0.0, 0.0),
+            last_gyro_bias: Vector3::new(0.0f64, 0.0, 0.0),
+            mag_rejection: None,
+            mag_rejected: false,
+            stationary_detector: None,
+            bias_time_constant: 1.0f64,
+            stationary: false,
+            frames: Frames::default(),
             quat: Quaternion::new(1.0f64, 0.0, 0.0, 0.0),
         }
     }
@@ -87,11 +314,355 @@ impl<N: RealField> Madgwick<N> {
     /// }
     /// ```
     pub fn new_with_quat(sample_period: N, beta: N, quat: Quaternion<N>) -> Self {
+        Madgwick::new_with_zeta(sample_period, beta, N::zero(), quat)
+    }
+
+    /// Creates a new `Madgwick` AHRS instance with given quaternion and gyroscope drift
+    /// compensation.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `beta` - Filter gain.
+    /// * `zeta` - Gyroscope drift compensation gain, `0` disables bias estimation.
+    /// * `quat` - Existing filter state quaternion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Quaternion;
+    /// use lobster_rust::ahrs::madgwick_filter::Madgwick;
+    ///
+    /// fn main() {
+    ///     let ahrs = Madgwick::new_with_zeta(
+    ///         0.002390625f64,
+    ///         0.1,
+    ///         0.015,
+    ///         Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    ///     );
+    /// }
+    /// ```
+    pub fn new_with_zeta(sample_period: N, beta: N, zeta: N, quat: Quaternion<N>) -> Self {
         Madgwick {
-            sample_period: sample_period,
-            beta: beta,
-            quat: quat,
+            sample_period,
+            beta,
+            initial_beta: beta,
+            gain_duration: N::zero(),
+            elapsed: N::zero(),
+            accel_adaptation: None,
+            zeta,
+            gyro_bias: Vector3::zeros(),
+            auto_reset: false,
+            last_quat: quat,
+            last_gyro_bias: Vector3::zeros(),
+            mag_rejection: None,
+            mag_rejected: false,
+            stationary_detector: None,
+            bias_time_constant: N::one(),
+            stationary: false,
+            frames: Frames::default(),
+            quat,
+        }
+    }
+
+    /// Creates a new `Madgwick` AHRS instance expressing its state in the Earth frame of `frames`,
+    /// with identity quaternion in that frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `beta` - Filter gain.
+    /// * `frames` - Earth frame convention and mapping of every sensor to the body frame.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::frame::{AxisMapping, EarthFrame, Frames};
+    /// use lobster_rust::ahrs::madgwick_filter::Madgwick;
+    ///
+    /// fn main() {
+    ///     let mut frames = Frames::new(EarthFrame::Ned);
+    ///     frames.magnetometer = AxisMapping::from_axes("y,x,-z").unwrap();
+    ///
+    ///     let ahrs = Madgwick::new_with_frames(0.002390625f64, 0.1, frames);
+    /// }
+    /// ```
+    pub fn new_with_frames(sample_period: N, beta: N, frames: Frames<N>) -> Self {
+        let mut ahrs = Madgwick::new(sample_period, beta);
+        ahrs.frames = frames;
+        ahrs
+    }
+
+    /// Creates a new `Madgwick` AHRS instance aligned with the orientation measured by a
+    /// collection of static `accelerometer` and `magnetometer` samples.
+    ///
+    /// The samples are averaged and the starting quaternion is found with Davenport's q-method, so
+    /// the filter does not need to converge from the identity quaternion.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `beta` - Filter gain.
+    /// * `accelerometer` - Accelerometer samples taken while the sensor is stationary.
+    /// * `magnetometer` - Magnetometer samples taken while the sensor is stationary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Vector3;
+    /// use lobster_rust::ahrs::madgwick_filter::Madgwick;
+    ///
+    /// fn main() {
+    ///     let ahrs = Madgwick::from_static_samples(
+    ///         0.002390625f64,
+    ///         0.1,
+    ///         &[Vector3::new(0.0, 0.0, 1.0)],
+    ///         &[Vector3::new(0.6, 0.0, -0.8)]
+    ///     ).unwrap();
+    /// }
+    /// ```
+    pub fn from_static_samples(
+        sample_period: N,
+        beta: N,
+        accelerometer: &[Vector3<N>],
+        magnetometer: &[Vector3<N>],
+    ) -> Result<Self, AhrsError> {
+        let accel = init::mean(accelerometer)?;
+        let mag = init::mean(magnetometer)?;
+
+        let quat = init::davenport(&accel, &mag, N::one(), N::one())?;
+
+        Ok(Madgwick::new_with_quat(sample_period, beta, quat))
+    }
+
+    /// Estimated gyroscope bias, in rad/s.
+    pub fn gyro_bias(&self) -> Vector3<N> {
+        self.gyro_bias
+    }
+
+    /// Filter state as Euler angles in radians for the intrinsic rotation `sequence`.
+    pub fn euler_angles(&self, sequence: EulerSequence) -> Vector3<N> {
+        conversion::euler_angles(&self.quat, sequence)
+    }
+
+    /// Filter state as a rotation matrix from the sensor frame to the Earth frame.
+    pub fn rotation_matrix(&self) -> Matrix3<N> {
+        conversion::rotation_matrix(&self.quat)
+    }
+
+    /// Filter state as a unit rotation axis and an angle in radians.
+    pub fn axis_angle(&self) -> (Vector3<N>, N) {
+        conversion::axis_angle(&self.quat)
+    }
+
+    /// Filter state as a rotation vector, the rotation axis scaled by the angle in radians.
+    pub fn rotation_vector(&self) -> Vector3<N> {
+        conversion::rotation_vector(&self.quat)
+    }
+
+    /// Magnetic disturbance detection.
+    pub fn mag_rejection(&self) -> Option<MagRejection<N>> {
+        self.mag_rejection
+    }
+
+    /// Sets the magnetic disturbance detection applied by `update`, or removes it with `None`.
+    pub fn set_mag_rejection(&mut self, rejection: Option<MagRejection<N>>) {
+        self.mag_rejection = rejection;
+    }
+
+    /// Whether the magnetometer measurement of the last successful update was rejected as
+    /// disturbed. Always `false` after `update_imu`.
+    pub fn mag_rejected(&self) -> bool {
+        self.mag_rejected
+    }
+
+    /// Starts a gain schedule that uses `initial_beta` at first and decays linearly to the
+    /// steady-state gain `beta` over `duration` seconds of updates. A zero `duration` disables the
+    /// schedule.
+    pub fn set_gain_schedule(&mut self, initial_beta: N, duration: N) {
+        self.initial_beta = initial_beta;
+        self.gain_duration = duration;
+        self.elapsed = N::zero();
+    }
+
+    /// Filter gain of the gain schedule at the current time, before any accelerometer adaptation.
+    pub fn current_beta(&self) -> N {
+        if self.elapsed >= self.gain_duration {
+            return self.beta;
+        }
+
+        let remaining = N::one() - self.elapsed / self.gain_duration;
+        self.beta + (self.initial_beta - self.beta) * remaining
+    }
+
+    /// Gain reduction during dynamic acceleration.
+    pub fn accel_adaptation(&self) -> Option<AccelAdaptation<N>> {
+        self.accel_adaptation
+    }
+
+    /// Sets the gain reduction applied while the accelerometer norm deviates from gravity, or
+    /// removes it with `None`.
+    pub fn set_accel_adaptation(&mut self, adaptation: Option<AccelAdaptation<N>>) {
+        self.accel_adaptation = adaptation;
+    }
+
+    /// Filter gain for an update with the calibrated `accelerometer` measurement.
+    fn gain(&self, accelerometer: &Vector3<N>) -> N {
+        match self.accel_adaptation {
+            Some(adaptation) => self.current_beta() * adaptation.factor(accelerometer),
+            None => self.current_beta(),
+        }
+    }
+
+    /// Linear acceleration for an `accelerometer` measurement at the current orientation, after
+    /// applying the accelerometer axis mapping. The Earth frame linear
+    /// acceleration is expressed in the configured Earth frame.
+    ///
+    /// `accelerometer` and `gravity` are given in `unit` and the result is returned in `output`.
+    pub fn linear_acceleration(
+        &self,
+        accelerometer: &Vector3<N>,
+        gravity: N,
+        unit: AccelUnit,
+        output: AccelUnit,
+    ) -> LinearAcceleration<N> {
+        let accelerometer = self.correct_accelerometer(accelerometer);
+        let earth = self.frames.earth;
+
+        let linear = acceleration::linear_acceleration(&earth.quat_to_nwu(&self.quat), &accelerometer, gravity, unit, output);
+
+        LinearAcceleration {
+            body: linear.body,
+            earth: earth.rotation_from_nwu() * linear.earth,
+        }
+    }
+
+    /// Earth frame convention and sensor axis mappings.
+    pub fn frames(&self) -> Frames<N> {
+        self.frames
+    }
+
+    /// Sets the Earth frame convention and sensor axis mappings, expressing the current state in
+    /// the new Earth frame.
+    pub fn set_frames(&mut self, frames: Frames<N>) {
+        let old = self.frames.earth;
+
+        self.quat = frames.earth.quat_from_nwu(&old.quat_to_nwu(&self.quat));
+        self.last_quat = frames.earth.quat_from_nwu(&old.quat_to_nwu(&self.last_quat));
+        self.frames = frames;
+    }
+
+    /// Stationary detection re-estimating the gyroscope bias.
+    pub fn stationary_detector(&self) -> Option<&StationaryDetector<N>> {
+        self.stationary_detector.as_ref()
+    }
+
+    /// Sets the stationary detection fed with every finite calibrated measurement, or disables it
+    /// with `None`.
+    ///
+    /// While the sensor is stationary the gyroscope measures its bias alone, so the estimated
+    /// gyroscope bias moves towards the measurement with time constant `time_constant` seconds,
+    /// independently of `zeta`.
+    pub fn set_stationary_detector(&mut self, detector: Option<StationaryDetector<N>>, time_constant: N) {
+        self.stationary_detector = detector;
+        self.bias_time_constant = time_constant;
+        self.stationary = false;
+    }
+
+    /// Whether the sensor was stationary at the last update, always `false` without stationary
+    /// detection.
+    pub fn is_stationary(&self) -> bool {
+        self.stationary
+    }
+
+    /// Whether a non-finite state is restored to the last good state on the next update.
+    pub fn auto_reset(&self) -> bool {
+        self.auto_reset
+    }
+
+    /// Enables or disables restoring a non-finite state to the last good state on the next update.
+    /// When disabled, updates on a non-finite state fail with `AhrsError::NonFiniteState`.
+    pub fn set_auto_reset(&mut self, auto_reset: bool) {
+        self.auto_reset = auto_reset;
+    }
+
+    /// Sets the state quaternion, which also becomes the last good state restored by `reset`.
+    pub fn set_quat(&mut self, quat: Quaternion<N>) {
+        self.quat = quat;
+        self.last_quat = quat;
+    }
+
+    /// Restores the state quaternion and gyroscope bias to their values after the last successful
+    /// update.
+    pub fn reset(&mut self) {
+        self.quat = self.last_quat;
+        self.gyro_bias = self.last_gyro_bias;
+    }
+
+    /// Checks that the filter state is finite before an update, restoring the last good state if
+    /// `auto_reset` is enabled.
+    fn check_state(&mut self) -> Result<(), AhrsError> {
+        if self.quat.coords.iter().all(|x| x.is_finite()) && self.gyro_bias.iter().all(|x| x.is_finite()) {
+            return Ok(());
+        }
+
+        if self.auto_reset {
+            self.reset();
+            Ok(())
+        } else {
+            Err(AhrsError::NonFiniteState)
+        }
+    }
+
+    /// Computes the gyroscope bias estimate from the normalized gradient `step` at the north-west-up
+    /// orientation `q` over time step `dt` and returns it together with the bias-compensated
+    /// `gyroscope` measurement.
+    fn compensate_gyro(
+        &self,
+        q: &Quaternion<N>,
+        gyroscope: &Vector3<N>,
+        step: &Quaternion<N>,
+        dt: N,
+        stationary: bool,
+    ) -> (Vector3<N>, Vector3<N>) {
+        let two: N = na::convert(2.0);
+
+        // Gyroscope error is the direction of the gradient expressed as an angular rate
+        let gyro_error = (q.conjugate() * step).vector() * two;
+
+        // Integrate error to yield gyroscope bias
+        let mut gyro_bias = self.gyro_bias + gyro_error * (self.zeta * dt);
+
+        // A stationary gyroscope measures its bias alone
+        if stationary {
+            let rate = (dt / self.bias_time_constant).min(N::one());
+            gyro_bias += (gyroscope - gyro_bias) * rate;
+        }
+
+        (gyro_bias, gyroscope - gyro_bias)
+    }
+
+    /// Stores the updated `quat` and `gyro_bias` as the new filter state after a time step `dt`,
+    /// refusing non-finite values so a failed update leaves the previous state untouched.
+    fn commit(&mut self, quat: Quaternion<N>, gyro_bias: Vector3<N>, dt: N) -> Result<&Quaternion<N>, AhrsError> {
+        if !quat.coords.iter().all(|x| x.is_finite()) || !gyro_bias.iter().all(|x| x.is_finite()) {
+            return Err(AhrsError::DegenerateGradient);
+        }
+
+        self.quat = quat;
+        self.gyro_bias = gyro_bias;
+        self.last_quat = quat;
+        self.last_gyro_bias = gyro_bias;
+
+        if self.elapsed < self.gain_duration {
+            self.elapsed += dt;
         }
+
+        Ok(&self.quat)
     }
 }
 
@@ -117,6 +688,16 @@ impl<N: RealField> Madgwick<N> {
         &mut self.beta
     }
 
+    /// Gyroscope drift compensation gain.
+    pub fn zeta(&self) -> N {
+        self.zeta
+    }
+
+    /// Mutable reference to gyroscope drift compensation gain.
+    pub fn zeta_mut(&mut self) -> &mut N {
+        &mut self.zeta
+    }
+
     /// Filter state quaternion.
     pub fn quat(&self) -> Quaternion<N> {
         self.quat
@@ -129,13 +710,59 @@ impl<N: RealField> Madgwick<N> {
 }
 
 impl<N: RealField> Ahrs<N> for Madgwick<N> {
+    fn earth_frame(&self) -> EarthFrame {
+        self.frames.earth
+    }
+
+    fn correct_accelerometer(&self, accelerometer: &Vector3<N>) -> Vector3<N> {
+        self.frames.accelerometer.apply(accelerometer)
+    }
+
     fn update(
         &mut self,
         gyroscope: &Vector3<N>,
         accelerometer: &Vector3<N>,
         magnetometer: &Vector3<N>,
-    ) -> Result<&Quaternion<N>, &'static str> {
-        let q = self.quat;
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = self.sample_period;
+        self.update_dt(gyroscope, accelerometer, magnetometer, dt)
+    }
+
+    fn update_imu(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = self.sample_period;
+        self.update_imu_dt(gyroscope, accelerometer, dt)
+    }
+
+    fn update_dt(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        magnetometer: &Vector3<N>,
+        dt: N,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+
+        // Reject non-finite measurements and state
+        validate_finite(gyroscope)?;
+        validate_finite(accelerometer)?;
+        validate_finite(magnetometer)?;
+        self.check_state()?;
+
+        // Express measurements in the body frame
+        let gyroscope = &self.frames.gyroscope.apply(gyroscope);
+        let accelerometer = &self.frames.accelerometer.apply(accelerometer);
+        let magnetometer = &self.frames.magnetometer.apply(magnetometer);
+
+        // Detect stationary intervals, adding the sample to the detector only on success
+        let detection = self.stationary_detector.as_ref().map(|d| d.test(gyroscope, accelerometer));
+        let stationary = matches!(detection, Some((_, true)));
+
+        // Fuse in north-west-up
+        let q = self.frames.earth.quat_to_nwu(&self.quat);
 
         let zero: N = na::zero();
         let two: N = na::convert(2.0);
@@ -145,19 +772,27 @@ impl<N: RealField> Ahrs<N> for Madgwick<N> {
         // Normalize accelerometer measurement
         let accel = match accelerometer.try_normalize(zero) {
             Some(n) => n,
-            None => return Err("Accelerometer norm divided by zero."),
+            None => return Err(AhrsError::AccelerometerNormZero),
         };
 
         // Normalize magnetometer measurement
         let mag = match magnetometer.try_normalize(zero) {
             Some(n) => n,
             None => {
-                return Err("Magnetometer norm divided by zero.");
+                return Err(AhrsError::MagnetometerNormZero);
             }
         };
 
+        // Check for magnetic disturbances, committing the learned reference only on success
+        let mut rejection = self.mag_rejection;
+        let mag_rejected = match rejection.as_mut() {
+            Some(r) => !r.accept(accelerometer, magnetometer),
+            None => false,
+        };
+        let mag_weight = rejection.map_or(N::one(), |r| r.weight());
+
         // Reference direction of Earth's magnetic field (Quaternion should still be conj of q)
-        let h = q * (Quaternion::from_parts(zero, mag.clone()) * q.conjugate());
+        let h = q * (Quaternion::from_parts(zero, mag) * q.conjugate());
         let b = Quaternion::new(zero, Vector2::new(h[0], h[1]).norm(), zero, h[2]);
 
         // Gradient descent algorithm corrective step
@@ -179,24 +814,63 @@ impl<N: RealField> Ahrs<N> for Madgwick<N> {
             zero, zero, zero, zero, zero, zero,
         );
 
-        let step = (j_t * f).normalize();
+        // A vanishing gradient means the estimate already matches, so no correction is applied
+        let step = (j_t * f).try_normalize(zero).unwrap_or_else(Vector6::zeros);
+
+        // Down-weight a disturbed magnetometer by blending with the accelerometer-only step
+        let step = if mag_rejected {
+            let accel_f = Vector6::new(f[0], f[1], f[2], zero, zero, zero);
+            let accel_step = (j_t * accel_f).try_normalize(zero).unwrap_or_else(Vector6::zeros);
+            accel_step + (step - accel_step) * mag_weight
+        } else {
+            step
+        };
+        let step = Quaternion::new(step[0], step[1], step[2], step[3]);
+
+        // Compensate for gyroscope drift
+        let (gyro_bias, gyro) = self.compensate_gyro(&q, gyroscope, &step, dt, stationary);
 
         // Compute rate of change for quaternion
-        let q_dot = q * Quaternion::from_parts(zero, (*gyroscope).clone()) * half
-            - Quaternion::new(step[0], step[1], step[2], step[3]) * self.beta;
+        let q_dot = q * Quaternion::from_parts(zero, gyro) * half
+            - step * self.gain(accelerometer);
 
         // Integrate to yield quaternion
-        self.quat = (q + q_dot * self.sample_period).normalize();
+        let quat = (q + q_dot * dt).normalize();
+
+        self.commit(self.frames.earth.quat_from_nwu(&quat), gyro_bias, dt)?;
+        self.mag_rejection = rejection;
+        self.mag_rejected = mag_rejected;
+        if let (Some(d), Some((statistic, _))) = (self.stationary_detector.as_mut(), detection) {
+            d.push(gyroscope, accelerometer, statistic);
+        }
+        self.stationary = stationary;
 
         Ok(&self.quat)
     }
 
-    fn update_imu(
+    fn update_imu_dt(
         &mut self,
         gyroscope: &Vector3<N>,
         accelerometer: &Vector3<N>,
-    ) -> Result<&Quaternion<N>, &'static str> {
-        let q = self.quat;
+        dt: N,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+
+        // Reject non-finite measurements and state
+        validate_finite(gyroscope)?;
+        validate_finite(accelerometer)?;
+        self.check_state()?;
+
+        // Express measurements in the body frame
+        let gyroscope = &self.frames.gyroscope.apply(gyroscope);
+        let accelerometer = &self.frames.accelerometer.apply(accelerometer);
+
+        // Detect stationary intervals, adding the sample to the detector only on success
+        let detection = self.stationary_detector.as_ref().map(|d| d.test(gyroscope, accelerometer));
+        let stationary = matches!(detection, Some((_, true)));
+
+        // Fuse in north-west-up
+        let q = self.frames.earth.quat_to_nwu(&self.quat);
 
         let zero: N = na::zero();
         let two: N = na::convert(2.0);
@@ -207,7 +881,7 @@ impl<N: RealField> Ahrs<N> for Madgwick<N> {
         let accel = match accelerometer.try_normalize(zero) {
             Some(n) => n,
             None => {
-                return Err("Accelerator norm divided by zero.");
+                return Err(AhrsError::AccelerometerNormZero);
             }
         };
 
@@ -226,14 +900,26 @@ impl<N: RealField> Ahrs<N> for Madgwick<N> {
             two * q[0], two * q[1], zero, zero,
         );
 
-        let step = (j_t * f).normalize();
+        // A vanishing gradient means the estimate already matches, so no correction is applied
+        let step = (j_t * f).try_normalize(zero).unwrap_or_else(Vector4::zeros);
+        let step = Quaternion::new(step[0], step[1], step[2], step[3]);
+
+        // Compensate for gyroscope drift
+        let (gyro_bias, gyro) = self.compensate_gyro(&q, gyroscope, &step, dt, stationary);
 
         // Compute rate of change of quaternion
-        let q_dot = (q * Quaternion::from_parts(zero, (*gyroscope).clone())) * half
-            - Quaternion::new(step[0], step[1], step[2], step[3]) * self.beta;
+        let q_dot = (q * Quaternion::from_parts(zero, gyro)) * half
+            - step * self.gain(accelerometer);
 
         // Integrate to yield quaternion
-        self.quat = (q + q_dot * self.sample_period).normalize();
+        let quat = (q + q_dot * dt).normalize();
+
+        self.commit(self.frames.earth.quat_from_nwu(&quat), gyro_bias, dt)?;
+        self.mag_rejected = false;
+        if let (Some(d), Some((statistic, _))) = (self.stationary_detector.as_mut(), detection) {
+            d.push(gyroscope, accelerometer, statistic);
+        }
+        self.stationary = stationary;
 
         Ok(&self.quat)
     }
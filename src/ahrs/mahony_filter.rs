@@ -0,0 +1,279 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{Quaternion, Vector2, Vector3};
+use crate::ahrs::ahrs::{validate_dt, Ahrs};
+use crate::ahrs::error::AhrsError;
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Mahony AHRS implementation.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Mahony<N: RealField> {
+    /// Expected sampling period, in seconds.
+    sample_period: N,
+    /// Proportional filter gain.
+    kp: N,
+    /// Integral filter gain.
+    ki: N,
+    /// Integral error feedback, in rad/s.
+    integral_fb: Vector3<N>,
+    /// Filter state quaternion.
+    pub quat: Quaternion<N>,
+}
+
+impl Default for Mahony<f64> {
+    /// Creates a new `Mahony` instance with default filter parameters:
+    ///
+    /// ```rust,ignore
+    /// Mahony {
+    ///     sample_period: 1.0f64/256.0,
+    ///     kp: 0.5f64,
+    ///     ki: 0.0f64,
+    ///     integral_fb: Vector3 { x: 0.0f64, y: 0.0, z: 0.0 },
+    ///     quat: Quaternion { w: 1.0f64, i: 0.0, j: 0.0, k: 0.0 }
+    /// }
+    /// ```
+    fn default() -> Mahony<f64> {
+        Mahony {
+            sample_period: (1.0f64) / (256.0),
+            kp: 0.5f64,
+            ki: 0.0f64,
+            integral_fb: Vector3::new(0.0f64, 0.0, 0.0),
+            quat: Quaternion::new(1.0f64, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl<N: RealField> Mahony<N> {
+    /// Creates a new `Mahony` AHRS instance with identity quaternion.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `kp` - Proportional filter gain.
+    /// * `ki` - Integral filter gain.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate lobster_rust;
+    ///
+    /// use lobster_rust::ahrs::mahony_filter::Mahony;
+    ///
+    /// fn main() {
+    ///     let ahrs = Mahony::new(0.002390625f64, 0.5, 0.0);
+    /// }
+    /// ```
+    pub fn new(sample_period: N, kp: N, ki: N) -> Self {
+        Mahony::new_with_quat(
+            sample_period,
+            kp,
+            ki,
+            Quaternion::new(N::one(), N::zero(), N::zero(), N::zero()),
+        )
+    }
+
+    /// Creates a new `Mahony` AHRS instance with given quaternion.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `kp` - Proportional filter gain.
+    /// * `ki` - Integral filter gain.
+    /// * `quat` - Existing filter state quaternion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Quaternion;
+    /// use lobster_rust::ahrs::mahony_filter::Mahony;
+    ///
+    /// fn main() {
+    ///     let ahrs = Mahony::new_with_quat(
+    ///         0.002390625f64,
+    ///         0.5,
+    ///         0.0,
+    ///         Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    ///     );
+    /// }
+    /// ```
+    pub fn new_with_quat(sample_period: N, kp: N, ki: N, quat: Quaternion<N>) -> Self {
+        Mahony {
+            sample_period,
+            kp,
+            ki,
+            integral_fb: Vector3::zeros(),
+            quat,
+        }
+    }
+
+    /// Integral error feedback accumulated so far, in rad/s.
+    pub fn integral_feedback(&self) -> Vector3<N> {
+        self.integral_fb
+    }
+
+    /// Applies the PI correction for the error `e` to `gyroscope` and integrates the result over
+    /// time step `dt` into the state quaternion.
+    fn correct_and_integrate(&mut self, gyroscope: &Vector3<N>, e: Vector3<N>, dt: N) -> &Quaternion<N> {
+        let zero: N = na::zero();
+        let half: N = na::convert(0.5);
+
+        // Integral feedback, only accumulated when the integral gain is enabled
+        if self.ki > zero {
+            self.integral_fb += e * (self.ki * dt);
+        } else {
+            self.integral_fb = Vector3::zeros();
+        }
+
+        // Apply proportional and integral feedback
+        let gyro = gyroscope + self.integral_fb + e * self.kp;
+
+        // Compute rate of change for quaternion
+        let q = self.quat;
+        let q_dot = q * Quaternion::from_parts(zero, gyro) * half;
+
+        // Integrate to yield quaternion
+        self.quat = (q + q_dot * dt).normalize();
+
+        &self.quat
+    }
+}
+
+#[cfg(feature = "field_access")]
+impl<N: RealField> Mahony<N> {
+    /// Expected sampling period, in seconds.
+    pub fn sample_period(&self) -> N {
+        self.sample_period
+    }
+
+    /// Mutable reference to expected sampling period, in seconds.
+    pub fn sample_period_mut(&mut self) -> &mut N {
+        &mut self.sample_period
+    }
+
+    /// Proportional filter gain.
+    pub fn kp(&self) -> N {
+        self.kp
+    }
+
+    /// Mutable reference to proportional filter gain.
+    pub fn kp_mut(&mut self) -> &mut N {
+        &mut self.kp
+    }
+
+    /// Integral filter gain.
+    pub fn ki(&self) -> N {
+        self.ki
+    }
+
+    /// Mutable reference to integral filter gain.
+    pub fn ki_mut(&mut self) -> &mut N {
+        &mut self.ki
+    }
+
+    /// Filter state quaternion.
+    pub fn quat(&self) -> Quaternion<N> {
+        self.quat
+    }
+
+    /// Mutable reference to filter state quaternion.
+    pub fn quat_mut(&mut self) -> &mut Quaternion<N> {
+        &mut self.quat
+    }
+}
+
+impl<N: RealField> Ahrs<N> for Mahony<N> {
+    fn update(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        magnetometer: &Vector3<N>,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = self.sample_period;
+        self.update_dt(gyroscope, accelerometer, magnetometer, dt)
+    }
+
+    fn update_imu(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = self.sample_period;
+        self.update_imu_dt(gyroscope, accelerometer, dt)
+    }
+
+    fn update_dt(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        magnetometer: &Vector3<N>,
+        dt: N,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+
+        let q = self.quat;
+
+        let zero: N = na::zero();
+
+        // Normalize accelerometer measurement
+        let accel = match accelerometer.try_normalize(zero) {
+            Some(n) => n,
+            None => return Err(AhrsError::AccelerometerNormZero),
+        };
+
+        // Normalize magnetometer measurement
+        let mag = match magnetometer.try_normalize(zero) {
+            Some(n) => n,
+            None => {
+                return Err(AhrsError::MagnetometerNormZero);
+            }
+        };
+
+        // Reference direction of Earth's magnetic field
+        let h = q * (Quaternion::from_parts(zero, mag) * q.conjugate());
+        let b = Quaternion::new(zero, Vector2::new(h[0], h[1]).norm(), zero, h[2]);
+
+        // Estimated direction of gravity and magnetic field in the sensor frame
+        let v = (q.conjugate() * Quaternion::new(zero, zero, zero, N::one()) * q).vector().into_owned();
+        let w = (q.conjugate() * b * q).vector().into_owned();
+
+        // Error is the sum of cross products between measured and estimated directions
+        let e = accel.cross(&v) + mag.cross(&w);
+
+        Ok(self.correct_and_integrate(gyroscope, e, dt))
+    }
+
+    fn update_imu_dt(
+        &mut self,
+        gyroscope: &Vector3<N>,
+        accelerometer: &Vector3<N>,
+        dt: N,
+    ) -> Result<&Quaternion<N>, AhrsError> {
+        let dt = validate_dt(dt)?;
+
+        let q = self.quat;
+
+        let zero: N = na::zero();
+
+        // Normalize accelerometer measurement
+        let accel = match accelerometer.try_normalize(zero) {
+            Some(n) => n,
+            None => {
+                return Err(AhrsError::AccelerometerNormZero);
+            }
+        };
+
+        // Estimated direction of gravity in the sensor frame
+        let v = (q.conjugate() * Quaternion::new(zero, zero, zero, N::one()) * q).vector().into_owned();
+
+        // Error is the cross product between measured and estimated direction of gravity
+        let e = accel.cross(&v);
+
+        Ok(self.correct_and_integrate(gyroscope, e, dt))
+    }
+}
@@ -1,3 +1,19 @@
+pub mod acceleration;
+#[allow(clippy::module_inception)]
 pub mod ahrs;
+pub mod allan;
+pub mod calibration;
+pub mod conversion;
+pub mod ekf;
+pub mod error;
+pub mod eval;
+pub mod frame;
+pub mod init;
+pub mod ins;
 pub mod madgwick_filter;
-pub mod python_interface;
\ No newline at end of file
+pub mod mahony_filter;
+#[cfg(feature = "python")]
+pub mod python_interface;
+pub mod replay;
+pub mod sim;
+pub mod stationary;
\ No newline at end of file
@@ -1,24 +1,203 @@
 use pyo3::prelude::*;
-use crate::ahrs::madgwick_filter::Madgwick;
-use nalgebra::{Quaternion, Vector3};
+use pyo3::exceptions::ValueError;
+use pyo3::types::PyDict;
+use pyo3::{wrap_pyfunction, PyNumberProtocol, PyObjectProtocol};
+use crate::ahrs::ekf::Ekf;
+use crate::ahrs::madgwick_filter::{AccelAdaptation, Madgwick, MagRejection};
+use crate::ahrs::mahony_filter::Mahony;
+use nalgebra::{Matrix3, Quaternion, UnitQuaternion, Vector3};
+use ndarray::{Array1, Array2, ArrayView2};
+use numpy::{IntoPyArray, PyArray1, PyArray2};
+use crate::ahrs::acceleration::AccelUnit;
 use crate::ahrs::ahrs::Ahrs;
+use crate::ahrs::allan;
+use crate::ahrs::calibration::{AccelCalibration, Calibrated, GyroCalibration, MagCalibration};
+use crate::ahrs::conversion::EulerSequence;
+use crate::ahrs::error::{AhrsError, ConfigError};
+use crate::ahrs::eval::{self, EvalError};
+use crate::ahrs::frame::{AxisMapping, EarthFrame, Frames};
+use crate::ahrs::ins::Ins;
+use crate::ahrs::replay::{self, Columns, LogError, LogFormat};
+use crate::ahrs::sim::{self, ImuModel, Segment, SensorModel, Trajectory};
+use crate::ahrs::stationary::{self, StationaryDetector};
+use std::str::FromStr;
 
 use pyo3::create_exception;
 create_exception!(module, MadgwickError, pyo3::exceptions::Exception);
+create_exception!(module, AccelerometerNormError, MadgwickError);
+create_exception!(module, MagnetometerNormError, MadgwickError);
+create_exception!(module, NonFiniteInputError, MadgwickError);
+create_exception!(module, DegenerateGradientError, MadgwickError);
+create_exception!(module, NonFiniteStateError, MadgwickError);
+create_exception!(module, InvalidTimeStepError, MadgwickError);
+create_exception!(module, SingularCovarianceError, MadgwickError);
+create_exception!(module, ParallelVectorsError, MadgwickError);
+create_exception!(module, NoSamplesError, MadgwickError);
+create_exception!(module, CalibrationError, MadgwickError);
+
+/// Component order of a quaternion stored in a list or array.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+enum QuatOrder {
+    /// Scalar first, `[w, x, y, z]`.
+    Wxyz,
+    /// Scalar last, `[x, y, z, w]`, the storage order of nalgebra.
+    Xyzw,
+}
+
+impl QuatOrder {
+    fn components(&self, quat: &Quaternion<f64>) -> [f64; 4] {
+        match self {
+            QuatOrder::Wxyz => [quat.w, quat.i, quat.j, quat.k],
+            QuatOrder::Xyzw => [quat.i, quat.j, quat.k, quat.w],
+        }
+    }
+
+    fn quaternion(&self, c: &[f64]) -> Quaternion<f64> {
+        match self {
+            QuatOrder::Wxyz => Quaternion::new(c[0], c[1], c[2], c[3]),
+            QuatOrder::Xyzw => Quaternion::new(c[3], c[0], c[1], c[2]),
+        }
+    }
+}
+
+impl FromStr for QuatOrder {
+    type Err = PyErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "wxyz" => Ok(QuatOrder::Wxyz),
+            "xyzw" => Ok(QuatOrder::Xyzw),
+            _ => Err(PyErr::new::<ValueError, _>(
+                format!("Unknown quaternion order '{}', expected 'wxyz' or 'xyzw'.", s)
+            )),
+        }
+    }
+}
+
+/// Quaternion with named components, used for every filter state passed to or returned from
+/// Python so the component order is never ambiguous.
+#[pyclass(name = Quaternion)]
+#[derive(Clone)]
+pub struct QuaternionP {
+    q: Quaternion<f64>,
+}
+
+impl From<Quaternion<f64>> for QuaternionP {
+    fn from(q: Quaternion<f64>) -> Self {
+        QuaternionP { q }
+    }
+}
+
+#[pymethods]
+impl QuaternionP {
+    /// Creates a quaternion from its named components, defaulting to identity.
+    #[new]
+    #[args(w = "1.0", x = "0.0", y = "0.0", z = "0.0")]
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        QuaternionP { q: Quaternion::new(w, x, y, z) }
+    }
+
+    /// Components `(w, x, y, z)` passed to `Quaternion` when unpickling.
+    fn __getnewargs__(&self) -> (f64, f64, f64, f64) {
+        (self.q.w, self.q.i, self.q.j, self.q.k)
+    }
+
+    /// Creates a quaternion from four `values` with components in `order`, either `"wxyz"` or
+    /// `"xyzw"`.
+    #[staticmethod]
+    #[args(order = "\"wxyz\"")]
+    pub fn from_list(values: Vec<f64>, order: &str) -> PyResult<Self> {
+        let order: QuatOrder = order.parse()?;
+
+        if values.len() != 4 {
+            return Err(PyErr::new::<ValueError, _>(
+                format!("Expected 4 quaternion components, got {}.", values.len())
+            ));
+        }
+
+        Ok(QuaternionP { q: order.quaternion(&values) })
+    }
+
+    #[getter]
+    fn w(&self) -> f64 {
+        self.q.w
+    }
+
+    #[getter]
+    fn x(&self) -> f64 {
+        self.q.i
+    }
+
+    #[getter]
+    fn y(&self) -> f64 {
+        self.q.j
+    }
+
+    #[getter]
+    fn z(&self) -> f64 {
+        self.q.k
+    }
+
+    /// Components as a list in `order`, either `"wxyz"` or `"xyzw"`.
+    #[args(order = "\"wxyz\"")]
+    fn to_list(&self, order: &str) -> PyResult<Vec<f64>> {
+        let order: QuatOrder = order.parse()?;
+        Ok(order.components(&self.q).to_vec())
+    }
+
+    fn conjugate(&self) -> QuaternionP {
+        QuaternionP { q: self.q.conjugate() }
+    }
+
+    fn norm(&self) -> f64 {
+        self.q.norm()
+    }
+
+    /// Unit quaternion with the same direction, or a `ValueError` for a zero quaternion.
+    fn normalize(&self) -> PyResult<QuaternionP> {
+        match self.q.try_normalize(0.0) {
+            Some(q) => Ok(QuaternionP { q }),
+            None => Err(PyErr::new::<ValueError, _>("Cannot normalize a zero quaternion.")),
+        }
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for QuaternionP {
+    /// Hamilton product, so `a * b` applies `b` first when rotating vectors.
+    fn __mul__(lhs: QuaternionP, rhs: QuaternionP) -> PyResult<QuaternionP> {
+        Ok(QuaternionP { q: lhs.q * rhs.q })
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for QuaternionP {
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("Quaternion(w={}, x={}, y={}, z={})", self.q.w, self.q.i, self.q.j, self.q.k))
+    }
+}
 
 #[pyclass]
 pub struct MadgwickP {
-    mw: Madgwick<f64>
+    mw: Calibrated<f64, Madgwick<f64>>,
+    last_timestamp: Option<f64>,
 }
 
 #[pymethods]
 impl MadgwickP {
-    /// Creates a new `Madgwick` AHRS instance with identity quaternion.
+    /// Creates a new `Madgwick` AHRS instance, with identity quaternion unless `quat` is given.
     ///
     /// # Arguments
     ///
     /// * `sample_period` - The expected sensor sampling period in seconds.
     /// * `beta` - Filter gain.
+    /// * `quat` - Existing filter state quaternion, relative to `earth_frame`.
+    /// * `zeta` - Gyroscope drift compensation gain, `0` disables bias estimation.
+    /// * `earth_frame` - Earth frame of the quaternion, `"nwu"`, `"ned"` or `"enu"`.
+    /// * `gyro_axes`, `accel_axes`, `mag_axes` - Signed sensor axes along the body x, y and z axes,
+    ///   such as `"y,x,-z"`, for sensors not aligned with the body.
+    /// * `mounting` - Orientation of the sensor board relative to the body, applied to every sensor
+    ///   after its axis mapping.
     ///
     /// # Example
     ///
@@ -30,8 +209,30 @@ impl MadgwickP {
     /// }
     /// ```
     #[new]
-    pub fn new(sample_period: f64, beta: f64) -> Self {
-        MadgwickP { mw: Madgwick::new(sample_period, beta) }
+    #[args(
+        quat = "None",
+        zeta = "0.0",
+        earth_frame = "\"nwu\"",
+        gyro_axes = "None",
+        accel_axes = "None",
+        mag_axes = "None",
+        mounting = "None"
+    )]
+    pub fn new(
+        sample_period: f64,
+        beta: f64,
+        quat: Option<QuaternionP>,
+        zeta: f64,
+        earth_frame: &str,
+        gyro_axes: Option<&str>,
+        accel_axes: Option<&str>,
+        mag_axes: Option<&str>,
+        mounting: Option<QuaternionP>,
+    ) -> PyResult<Self> {
+        let frames = to_frames(earth_frame, gyro_axes, accel_axes, mag_axes, mounting)?;
+        let quat = quat.map_or_else(Quaternion::identity, |q| q.q);
+
+        PyResult::Ok(MadgwickP::with_frames(sample_period, beta, zeta, quat, frames))
     }
 
     /// Creates a new `Madgwick` AHRS instance with given quaternion.
@@ -40,7 +241,9 @@ impl MadgwickP {
     ///
     /// * `sample_period` - The expected sensor sampling period in seconds.
     /// * `beta` - Filter gain.
-    /// * `quat` - Existing filter state quaternion.
+    /// * `quat` - Existing filter state quaternion, relative to `earth_frame`.
+    /// * `earth_frame`, `gyro_axes`, `accel_axes`, `mag_axes`, `mounting` - Frame settings, as for
+    ///   `MadgwickP(...)`.
     ///
     /// # Example
     ///
@@ -58,21 +261,128 @@ impl MadgwickP {
     ///     );
     /// }
     /// ```
-    #[new]
-    pub fn new_with_quat(sample_period: f64, beta: f64, quat: Vec<f64>) -> Self {
-        assert_eq!(quat.len(), 4);
-        MadgwickP { mw: Madgwick::new_with_quat(sample_period, beta, Quaternion::new(quat[0], quat[1], quat[2], quat[3])) }
+    #[staticmethod]
+    #[args(
+        earth_frame = "\"nwu\"",
+        gyro_axes = "None",
+        accel_axes = "None",
+        mag_axes = "None",
+        mounting = "None"
+    )]
+    pub fn new_with_quat(
+        sample_period: f64,
+        beta: f64,
+        quat: QuaternionP,
+        earth_frame: &str,
+        gyro_axes: Option<&str>,
+        accel_axes: Option<&str>,
+        mag_axes: Option<&str>,
+        mounting: Option<QuaternionP>,
+    ) -> PyResult<Self> {
+        let frames = to_frames(earth_frame, gyro_axes, accel_axes, mag_axes, mounting)?;
+        PyResult::Ok(MadgwickP::with_frames(sample_period, beta, 0.0, quat.q, frames))
+    }
+
+    /// Creates a new `Madgwick` AHRS instance with given quaternion and gyroscope drift
+    /// compensation.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `beta` - Filter gain.
+    /// * `zeta` - Gyroscope drift compensation gain, `0` disables bias estimation.
+    /// * `quat` - Existing filter state quaternion, relative to `earth_frame`.
+    /// * `earth_frame`, `gyro_axes`, `accel_axes`, `mag_axes`, `mounting` - Frame settings, as for
+    ///   `MadgwickP(...)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Quaternion;
+    /// use lobster_rust::ahrs::madgwick_filter::Madgwick;
+    ///
+    /// fn main() {
+    ///     let ahrs = Madgwick::new_with_zeta(
+    ///         0.002390625f64,
+    ///         0.1,
+    ///         0.015,
+    ///         Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    ///     );
+    /// }
+    /// ```
+    #[staticmethod]
+    #[args(
+        earth_frame = "\"nwu\"",
+        gyro_axes = "None",
+        accel_axes = "None",
+        mag_axes = "None",
+        mounting = "None"
+    )]
+    pub fn new_with_zeta(
+        sample_period: f64,
+        beta: f64,
+        zeta: f64,
+        quat: QuaternionP,
+        earth_frame: &str,
+        gyro_axes: Option<&str>,
+        accel_axes: Option<&str>,
+        mag_axes: Option<&str>,
+        mounting: Option<QuaternionP>,
+    ) -> PyResult<Self> {
+        let frames = to_frames(earth_frame, gyro_axes, accel_axes, mag_axes, mounting)?;
+        PyResult::Ok(MadgwickP::with_frames(sample_period, beta, zeta, quat.q, frames))
+    }
+
+    /// Creates a new `Madgwick` AHRS instance aligned with the orientation measured by a
+    /// collection of static accelerometer and magnetometer samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `beta` - Filter gain.
+    /// * `accelerometer` - Accelerometer samples taken while the sensor is stationary.
+    /// * `magnetometer` - Magnetometer samples taken while the sensor is stationary.
+    #[staticmethod]
+    pub fn from_static_samples(
+        sample_period: f64,
+        beta: f64,
+        accelerometer: Vec<Vec<f64>>,
+        magnetometer: Vec<Vec<f64>>,
+    ) -> PyResult<Self> {
+        let accel = to_vectors(accelerometer);
+        let mag = to_vectors(magnetometer);
+
+        match Madgwick::from_static_samples(sample_period, beta, &accel, &mag) {
+            Ok(mw) => PyResult::Ok(MadgwickP { mw: Calibrated::new(mw), last_timestamp: None }),
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
     }
 }
 
 #[pymethods]
 impl MadgwickP {
+    /// Updates the filter and returns the quaternion.
+    ///
+    /// With `linear_acceleration` set, returns a tuple of the quaternion and the `[x, y, z]`
+    /// linear acceleration in the sensor and Earth frames, in `output_unit`. The accelerometer and
+    /// `gravity` are given in `unit`, either `"g"` or `"m/s^2"`.
+    #[args(linear_acceleration = "false", gravity = "1.0", unit = "\"g\"", output_unit = "\"g\"")]
     fn update(
         &mut self,
+        py: Python,
         gyroscope: Vec<f64>,
         accelerometer: Vec<f64>,
         magnetometer: Vec<f64>,
-    ) -> PyResult<Vec<f64>> {
+        linear_acceleration: bool,
+        gravity: f64,
+        unit: &str,
+        output_unit: &str,
+    ) -> PyResult<PyObject> {
+        let unit: AccelUnit = unit.parse().map_err(to_config_pyerr)?;
+        let output_unit: AccelUnit = output_unit.parse().map_err(to_config_pyerr)?;
+
         //Convert gyro, accel, magneto to nalgebra vectors
         assert_eq!(gyroscope.len(), 3);
         let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
@@ -84,17 +394,53 @@ impl MadgwickP {
         let m3: Vector3<f64> = Vector3::new(magnetometer[0], magnetometer[1], magnetometer[2]);
 
         //Update madgwick
-        match self.mw.update(&g3, &a3, &m3) {
-            Ok(quat) => PyResult::Ok(vec!(quat.i, quat.j, quat.k, quat.w)),
-            Err(e) => PyResult::Err(PyErr::new::<MadgwickError, _>(e))
+        if let Err(e) = self.mw.update(&g3, &a3, &m3) {
+            return PyResult::Err(to_pyerr(e));
         }
+
+        PyResult::Ok(self.output(py, &a3, linear_acceleration, gravity, unit, output_unit))
     }
 
+    /// Updates the filter without magnetometer and returns the quaternion, with the same optional
+    /// linear acceleration output as `update`.
+    #[args(linear_acceleration = "false", gravity = "1.0", unit = "\"g\"", output_unit = "\"g\"")]
     fn update_imu(
         &mut self,
+        py: Python,
         gyroscope: Vec<f64>,
-        accelerometer: Vec<f64>
-    ) -> PyResult<Vec<f64>> {
+        accelerometer: Vec<f64>,
+        linear_acceleration: bool,
+        gravity: f64,
+        unit: &str,
+        output_unit: &str,
+    ) -> PyResult<PyObject> {
+        let unit: AccelUnit = unit.parse().map_err(to_config_pyerr)?;
+        let output_unit: AccelUnit = output_unit.parse().map_err(to_config_pyerr)?;
+
+        //Convert gyro, accel, magneto to nalgebra vectors
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
+        //Update madgwick
+        if let Err(e) = self.mw.update_imu(&g3, &a3) {
+            return PyResult::Err(to_pyerr(e));
+        }
+
+        PyResult::Ok(self.output(py, &a3, linear_acceleration, gravity, unit, output_unit))
+    }
+
+    /// Updates the filter with a sample taken at `timestamp` seconds, integrating over the time
+    /// elapsed since the previous timestamped sample. The first sample uses the sampling period.
+    fn update_timestamp(
+        &mut self,
+        timestamp: f64,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>,
+        magnetometer: Vec<f64>,
+    ) -> PyResult<QuaternionP> {
         //Convert gyro, accel, magneto to nalgebra vectors
         assert_eq!(gyroscope.len(), 3);
         let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
@@ -102,17 +448,1612 @@ impl MadgwickP {
         assert_eq!(accelerometer.len(), 3);
         let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
 
+        assert_eq!(magnetometer.len(), 3);
+        let m3: Vector3<f64> = Vector3::new(magnetometer[0], magnetometer[1], magnetometer[2]);
+
+        //Update madgwick
+        let res = match self.elapsed(timestamp)? {
+            Some(dt) => self.mw.update_dt(&g3, &a3, &m3, dt),
+            None => self.mw.update(&g3, &a3, &m3),
+        };
+
+        match res {
+            Ok(quat) => {
+                self.last_timestamp = Some(timestamp);
+                PyResult::Ok(QuaternionP::from(*quat))
+            },
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    /// Updates the filter without magnetometer with a sample taken at `timestamp` seconds,
+    /// integrating over the time elapsed since the previous timestamped sample. The first sample
+    /// uses the sampling period.
+    fn update_imu_timestamp(
+        &mut self,
+        timestamp: f64,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>
+    ) -> PyResult<QuaternionP> {
+        //Convert gyro, accel to nalgebra vectors
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
         //Update madgwick
-        match self.mw.update_imu(&g3, &a3) {
-            Ok(quat) => PyResult::Ok(vec!(quat.i, quat.j, quat.k, quat.w)),
-            Err(e) => PyResult::Err(PyErr::new::<MadgwickError, _>(e))
+        let res = match self.elapsed(timestamp)? {
+            Some(dt) => self.mw.update_imu_dt(&g3, &a3, dt),
+            None => self.mw.update_imu(&g3, &a3),
+        };
+
+        match res {
+            Ok(quat) => {
+                self.last_timestamp = Some(timestamp);
+                PyResult::Ok(QuaternionP::from(*quat))
+            },
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    /// Updates the filter with every row of the Nx3 `gyroscope`, `accelerometer` and
+    /// `magnetometer` arrays, with the GIL released.
+    ///
+    /// Returns an Nx4 array of quaternions with components in `order`, either `"wxyz"` or
+    /// `"xyzw"`, and a length N mask that is `True` for rows whose update failed. Failed rows
+    /// repeat the previous quaternion.
+    #[args(order = "\"wxyz\"")]
+    fn update_batch(
+        &mut self,
+        py: Python,
+        gyroscope: &PyArray2<f64>,
+        accelerometer: &PyArray2<f64>,
+        magnetometer: &PyArray2<f64>,
+        order: &str,
+    ) -> PyResult<(Py<PyArray2<f64>>, Py<PyArray1<bool>>)> {
+        let g = gyroscope.as_array();
+        let a = accelerometer.as_array();
+        let m = magnetometer.as_array();
+
+        check_batch_shape(&g, g.nrows())?;
+        check_batch_shape(&a, g.nrows())?;
+        check_batch_shape(&m, g.nrows())?;
+
+        let order: QuatOrder = order.parse()?;
+
+        //Update madgwick for every row
+        let mw = &mut self.mw;
+        let (quats, failed) = py.allow_threads(move || {
+            let mut quats = Array2::<f64>::zeros((g.nrows(), 4));
+            let mut failed = Array1::<bool>::from_elem(g.nrows(), false);
+
+            for n in 0..g.nrows() {
+                let g3: Vector3<f64> = Vector3::new(g[[n, 0]], g[[n, 1]], g[[n, 2]]);
+                let a3: Vector3<f64> = Vector3::new(a[[n, 0]], a[[n, 1]], a[[n, 2]]);
+                let m3: Vector3<f64> = Vector3::new(m[[n, 0]], m[[n, 1]], m[[n, 2]]);
+
+                failed[n] = mw.update(&g3, &a3, &m3).is_err();
+
+                quats.row_mut(n).assign(&Array1::from(order.components(&mw.ahrs().quat).to_vec()));
+            }
+
+            (quats, failed)
+        });
+
+        PyResult::Ok((quats.into_pyarray(py).to_owned(), failed.into_pyarray(py).to_owned()))
+    }
+
+    /// Updates the filter without magnetometer with every row of the Nx3 `gyroscope` and
+    /// `accelerometer` arrays, with the GIL released.
+    ///
+    /// Returns an Nx4 array of quaternions with components in `order`, either `"wxyz"` or
+    /// `"xyzw"`, and a length N mask that is `True` for rows whose update failed. Failed rows
+    /// repeat the previous quaternion.
+    #[args(order = "\"wxyz\"")]
+    fn update_imu_batch(
+        &mut self,
+        py: Python,
+        gyroscope: &PyArray2<f64>,
+        accelerometer: &PyArray2<f64>,
+        order: &str,
+    ) -> PyResult<(Py<PyArray2<f64>>, Py<PyArray1<bool>>)> {
+        let g = gyroscope.as_array();
+        let a = accelerometer.as_array();
+
+        check_batch_shape(&g, g.nrows())?;
+        check_batch_shape(&a, g.nrows())?;
+
+        let order: QuatOrder = order.parse()?;
+
+        //Update madgwick for every row
+        let mw = &mut self.mw;
+        let (quats, failed) = py.allow_threads(move || {
+            let mut quats = Array2::<f64>::zeros((g.nrows(), 4));
+            let mut failed = Array1::<bool>::from_elem(g.nrows(), false);
+
+            for n in 0..g.nrows() {
+                let g3: Vector3<f64> = Vector3::new(g[[n, 0]], g[[n, 1]], g[[n, 2]]);
+                let a3: Vector3<f64> = Vector3::new(a[[n, 0]], a[[n, 1]], a[[n, 2]]);
+
+                failed[n] = mw.update_imu(&g3, &a3).is_err();
+
+                quats.row_mut(n).assign(&Array1::from(order.components(&mw.ahrs().quat).to_vec()));
+            }
+
+            (quats, failed)
+        });
+
+        PyResult::Ok((quats.into_pyarray(py).to_owned(), failed.into_pyarray(py).to_owned()))
+    }
+
+    /// Filter state as Euler angles for the intrinsic rotation `sequence`, such as `"zyx"` for
+    /// `[yaw, pitch, roll]`, in radians or in `degrees`.
+    #[args(sequence = "\"zyx\"", degrees = "false")]
+    fn euler(&self, sequence: &str, degrees: bool) -> PyResult<Vec<f64>> {
+        let sequence: EulerSequence = sequence.parse().map_err(to_config_pyerr)?;
+
+        let mut angles = self.mw.ahrs().euler_angles(sequence);
+        if degrees {
+            angles = angles.map(f64::to_degrees);
+        }
+
+        PyResult::Ok(vec!(angles[0], angles[1], angles[2]))
+    }
+
+    /// Filter state as a 3x3 rotation matrix from the sensor frame to the Earth frame, as a list of
+    /// rows.
+    fn rotation_matrix(&self) -> Vec<Vec<f64>> {
+        let r = self.mw.ahrs().rotation_matrix();
+        r.row_iter().map(|row| row.iter().cloned().collect()).collect()
+    }
+
+    /// Filter state as a unit rotation axis `[x, y, z]` and an angle in radians.
+    fn axis_angle(&self) -> (Vec<f64>, f64) {
+        let (axis, angle) = self.mw.ahrs().axis_angle();
+        (vec!(axis[0], axis[1], axis[2]), angle)
+    }
+
+    /// Filter state as a rotation vector `[x, y, z]`, the rotation axis scaled by the angle in
+    /// radians.
+    fn rotation_vector(&self) -> Vec<f64> {
+        let v = self.mw.ahrs().rotation_vector();
+        vec!(v[0], v[1], v[2])
+    }
+
+    /// Enables or disables restoring a non-finite state to the last good state on the next update.
+    fn set_auto_reset(&mut self, auto_reset: bool) {
+        self.mw.ahrs_mut().set_auto_reset(auto_reset);
+    }
+
+    /// Restores the filter state to its value after the last successful update.
+    fn reset(&mut self) {
+        self.mw.ahrs_mut().reset();
+    }
+
+    /// Estimated gyroscope bias as `[x, y, z]`, in rad/s.
+    fn gyro_bias(&self) -> Vec<f64> {
+        let b = self.mw.ahrs().gyro_bias();
+        vec!(b[0], b[1], b[2])
+    }
+
+    /// Sets the gyroscope calibration applied before every update, or removes it with `None`.
+    fn set_gyro_calibration(&mut self, calibration: Option<GyroCalibrationP>) {
+        self.mw.set_gyro_calibration(calibration.map(|c| c.cal));
+    }
+
+    /// Sets the accelerometer calibration applied before every update, or removes it with `None`.
+    fn set_accel_calibration(&mut self, calibration: Option<AccelCalibrationP>) {
+        self.mw.set_accel_calibration(calibration.map(|c| c.cal));
+    }
+
+    /// Sets the magnetometer calibration applied before every update, or removes it with `None`.
+    fn set_mag_calibration(&mut self, calibration: Option<MagCalibrationP>) {
+        self.mw.set_mag_calibration(calibration.map(|c| c.cal));
+    }
+
+    /// Starts with gain `initial_beta` and decays linearly to the steady-state gain over
+    /// `duration` seconds of updates.
+    fn set_gain_schedule(&mut self, initial_beta: f64, duration: f64) {
+        self.mw.ahrs_mut().set_gain_schedule(initial_beta, duration);
+    }
+
+    /// Filter gain of the gain schedule at the current time.
+    fn current_beta(&self) -> f64 {
+        self.mw.ahrs().current_beta()
+    }
+
+    /// Lowers the gain while the accelerometer norm deviates from `gravity` by more than the
+    /// relative `threshold`, down to zero at the relative `cutoff`.
+    #[args(gravity = "1.0")]
+    fn set_accel_adaptation(&mut self, threshold: f64, cutoff: f64, gravity: f64) {
+        self.mw.ahrs_mut().set_accel_adaptation(Some(AccelAdaptation::new(gravity, threshold, cutoff)));
+    }
+
+    /// Always uses the full gain, regardless of the accelerometer norm.
+    fn disable_accel_adaptation(&mut self) {
+        self.mw.ahrs_mut().set_accel_adaptation(None);
+    }
+
+    /// Rejects magnetometer measurements whose strength deviates from the reference by more than
+    /// the relative `norm_tolerance` or whose inclination deviates by more than `dip_tolerance`
+    /// radians. Rejected measurements are down-weighted by `weight`, with `0` falling back to an
+    /// IMU update.
+    ///
+    /// The reference is `reference_norm` and `reference_dip` if both are given, and is otherwise
+    /// learned from accepted measurements at `learning_rate`.
+    #[args(weight = "0.0", learning_rate = "0.01", reference_norm = "None", reference_dip = "None")]
+    fn set_mag_rejection(
+        &mut self,
+        norm_tolerance: f64,
+        dip_tolerance: f64,
+        weight: f64,
+        learning_rate: f64,
+        reference_norm: Option<f64>,
+        reference_dip: Option<f64>,
+    ) {
+        let rejection = match (reference_norm, reference_dip) {
+            (Some(norm), Some(dip)) => MagRejection::with_reference(norm, dip, norm_tolerance, dip_tolerance, weight),
+            _ => MagRejection::learned(norm_tolerance, dip_tolerance, weight, learning_rate),
+        };
+
+        self.mw.ahrs_mut().set_mag_rejection(Some(rejection));
+    }
+
+    /// Always trusts the magnetometer.
+    fn disable_mag_rejection(&mut self) {
+        self.mw.ahrs_mut().set_mag_rejection(None);
+    }
+
+    /// Whether the magnetometer measurement of the last update was rejected as disturbed.
+    fn mag_rejected(&self) -> bool {
+        self.mw.ahrs().mag_rejected()
+    }
+
+    /// Re-estimates the gyroscope bias with time constant `time_constant` seconds while `detector`
+    /// reports the sensor stationary. The detector is copied, so it keeps its own state.
+    #[args(time_constant = "1.0")]
+    fn set_stationary_detector(&mut self, detector: StationaryDetectorP, time_constant: f64) {
+        self.mw.ahrs_mut().set_stationary_detector(Some(detector.detector), time_constant);
+    }
+
+    /// Stops re-estimating the gyroscope bias while stationary.
+    fn disable_stationary_detector(&mut self) {
+        self.mw.ahrs_mut().set_stationary_detector(None, 1.0);
+    }
+
+    /// Whether the sensor was stationary at the last update.
+    fn is_stationary(&self) -> bool {
+        self.mw.ahrs().is_stationary()
+    }
+}
+
+#[cfg(feature = "field_access")]
+#[pymethods]
+impl MadgwickP {
+    /// Expected sampling period, in seconds.
+    #[getter]
+    fn sample_period(&self) -> f64 {
+        self.mw.ahrs().sample_period()
+    }
+
+    #[setter]
+    fn set_sample_period(&mut self, sample_period: f64) -> PyResult<()> {
+        if !(sample_period.is_finite() && sample_period > 0.0) {
+            return PyResult::Err(PyErr::new::<pyo3::exceptions::ValueError, _>("Sample period must be positive and finite."));
+        }
+
+        *self.mw.ahrs_mut().sample_period_mut() = sample_period;
+        Ok(())
+    }
+
+    /// Filter gain.
+    #[getter]
+    fn beta(&self) -> f64 {
+        self.mw.ahrs().beta()
+    }
+
+    #[setter]
+    fn set_beta(&mut self, beta: f64) -> PyResult<()> {
+        if !(beta.is_finite() && beta >= 0.0) {
+            return PyResult::Err(PyErr::new::<pyo3::exceptions::ValueError, _>("Beta must be non-negative and finite."));
+        }
+
+        *self.mw.ahrs_mut().beta_mut() = beta;
+        Ok(())
+    }
+
+    /// Filter state quaternion.
+    #[getter]
+    fn quaternion(&self) -> QuaternionP {
+        QuaternionP::from(self.mw.ahrs().quat())
+    }
+
+    #[setter]
+    fn set_quaternion(&mut self, quat: QuaternionP) -> PyResult<()> {
+        if !((quat.q.norm() - 1.0).abs() <= 1.0e-6) {
+            return PyResult::Err(PyErr::new::<pyo3::exceptions::ValueError, _>("Quaternion must have unit norm."));
         }
+
+        self.mw.ahrs_mut().set_quat(quat.q);
+        Ok(())
     }
 }
 
-#[pymodule]
-fn ahrs(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
-    m.add_class::<MadgwickP>()?;
+#[cfg(feature = "serde-serialize")]
+#[pymethods]
+impl MadgwickP {
+    /// Filter parameters, state and calibrations as a JSON string. The previous timestamp is not
+    /// included, so timestamped updates restart after restoring.
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.mw)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::ValueError, _>(e.to_string()))
+    }
+
+    /// Creates a filter from a JSON string produced by `to_json`.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<MadgwickP> {
+        match serde_json::from_str(json) {
+            Ok(mw) => PyResult::Ok(MadgwickP { mw, last_timestamp: None }),
+            Err(e) => PyResult::Err(PyErr::new::<pyo3::exceptions::ValueError, _>(e.to_string()))
+        }
+    }
+
+    /// Arguments `(sample_period, beta, quat, zeta, earth_frame)` of `MadgwickP`, which `pickle`
+    /// calls before `__setstate__` restores the axis mappings, calibrations and remaining state.
+    fn __getnewargs__(&self) -> (f64, f64, QuaternionP, f64, &'static str) {
+        let earth_frame = match self.mw.ahrs().frames().earth {
+            EarthFrame::Nwu => "nwu",
+            EarthFrame::Ned => "ned",
+            EarthFrame::Enu => "enu",
+        };
+
+        (self.mw.ahrs().sample_period(), self.mw.ahrs().beta(), QuaternionP::from(self.mw.ahrs().quat()), self.mw.ahrs().zeta(), earth_frame)
+    }
+
+    fn __getstate__(&self) -> PyResult<String> {
+        self.to_json()
+    }
+
+    fn __setstate__(&mut self, state: &str) -> PyResult<()> {
+        *self = MadgwickP::from_json(state)?;
+        Ok(())
+    }
+}
+
+/// Converts an `AhrsError` from any filter to the matching subclass of `MadgwickError`.
+fn to_pyerr(e: AhrsError) -> PyErr {
+    let msg = e.to_string();
+    match e {
+        AhrsError::AccelerometerNormZero => PyErr::new::<AccelerometerNormError, _>(msg),
+        AhrsError::MagnetometerNormZero => PyErr::new::<MagnetometerNormError, _>(msg),
+        AhrsError::NonFiniteInput => PyErr::new::<NonFiniteInputError, _>(msg),
+        AhrsError::DegenerateGradient => PyErr::new::<DegenerateGradientError, _>(msg),
+        AhrsError::NonFiniteState => PyErr::new::<NonFiniteStateError, _>(msg),
+        AhrsError::InvalidTimeStep => PyErr::new::<InvalidTimeStepError, _>(msg),
+        AhrsError::SingularCovariance => PyErr::new::<SingularCovarianceError, _>(msg),
+        AhrsError::ParallelVectors => PyErr::new::<ParallelVectorsError, _>(msg),
+        AhrsError::NoSamples => PyErr::new::<NoSamplesError, _>(msg),
+    }
+}
+
+/// Converts a `ConfigError` to a `CalibrationError` for calibrations, or a `ValueError` for
+/// other settings.
+fn to_config_pyerr(e: ConfigError) -> PyErr {
+    let msg = e.to_string();
+    match e {
+        ConfigError::InvalidCalibration => PyErr::new::<CalibrationError, _>(msg),
+        _ => PyErr::new::<pyo3::exceptions::ValueError, _>(msg),
+    }
+}
+
+/// Converts an `EvalError` to a `NoSamplesError` for empty inputs, or a `ValueError` otherwise.
+fn to_eval_pyerr(e: EvalError) -> PyErr {
+    let msg = e.to_string();
+    match e {
+        EvalError::NoSamples => PyErr::new::<NoSamplesError, _>(msg),
+        EvalError::LengthMismatch => PyErr::new::<pyo3::exceptions::ValueError, _>(msg),
+    }
+}
+
+/// Checks that a batch of sensor samples is an array with `rows` rows of `[x, y, z]` values.
+fn check_batch_shape(samples: &ArrayView2<f64>, rows: usize) -> PyResult<()> {
+    if samples.ncols() != 3 {
+        return PyResult::Err(PyErr::new::<pyo3::exceptions::ValueError, _>("Sensor arrays must have shape Nx3."));
+    }
+
+    if samples.nrows() != rows {
+        return PyResult::Err(PyErr::new::<pyo3::exceptions::ValueError, _>("Sensor arrays must have the same length."));
+    }
+
+    PyResult::Ok(())
+}
+
+/// Converts an Nx4 array of quaternions with components in `order` to nalgebra quaternions.
+fn to_quaternions(quats: &ArrayView2<f64>, order: QuatOrder) -> PyResult<Vec<Quaternion<f64>>> {
+    if quats.ncols() != 4 {
+        return PyResult::Err(PyErr::new::<pyo3::exceptions::ValueError, _>("Quaternion arrays must have shape Nx4."));
+    }
+
+    PyResult::Ok(quats.outer_iter().map(|row| order.quaternion(&row.to_vec())).collect())
+}
+
+/// Converts a list of `[x, y, z]` samples to nalgebra vectors.
+fn to_vectors(samples: Vec<Vec<f64>>) -> Vec<Vector3<f64>> {
+    samples.iter().map(|s| {
+        assert_eq!(s.len(), 3);
+        Vector3::new(s[0], s[1], s[2])
+    }).collect()
+}
+
+/// Converts an optional `[x, y, z]` list to a vector, zero if omitted.
+fn to_optional_vector(values: Option<Vec<f64>>, name: &str) -> PyResult<Vector3<f64>> {
+    match values {
+        Some(v) if v.len() != 3 => PyResult::Err(PyErr::new::<ValueError, _>(format!("{} must have 3 components.", name))),
+        Some(v) => PyResult::Ok(Vector3::new(v[0], v[1], v[2])),
+        None => PyResult::Ok(Vector3::zeros()),
+    }
+}
+
+/// Converts nalgebra vectors to an Nx3 array.
+fn to_array(vectors: &[Vector3<f64>]) -> Array2<f64> {
+    let mut array = Array2::<f64>::zeros((vectors.len(), 3));
+    for (n, v) in vectors.iter().enumerate() {
+        array.row_mut(n).assign(&Array1::from(vec!(v[0], v[1], v[2])));
+    }
+    array
+}
+
+/// Converts a 3x3 nested list of rows to a matrix.
+fn to_matrix(rows: Vec<Vec<f64>>) -> Matrix3<f64> {
+    assert_eq!(rows.len(), 3);
+    let rows = to_vectors(rows);
+    Matrix3::from_rows(&[rows[0].transpose(), rows[1].transpose(), rows[2].transpose()])
+}
+
+/// Converts a matrix to a nested list of rows.
+fn to_rows(m: &Matrix3<f64>) -> Vec<Vec<f64>> {
+    m.row_iter().map(|row| row.iter().cloned().collect()).collect()
+}
+
+/// Three column names from `names`, or `default` if none were given.
+fn to_columns(names: Option<Vec<String>>, default: [String; 3], name: &str) -> PyResult<[String; 3]> {
+    match names {
+        Some(n) if n.len() != 3 => PyResult::Err(PyErr::new::<ValueError, _>(format!("{} must name 3 columns.", name))),
+        Some(n) => PyResult::Ok([n[0].clone(), n[1].clone(), n[2].clone()]),
+        None => PyResult::Ok(default),
+    }
+}
+
+/// Converts a `LogError` to `IOError` for I/O failures and `ValueError` otherwise.
+fn to_log_pyerr(e: LogError) -> PyErr {
+    match e {
+        LogError::Io(e) => PyErr::new::<pyo3::exceptions::IOError, _>(e.to_string()),
+        e => PyErr::new::<ValueError, _>(e.to_string()),
+    }
+}
+
+/// Frames from an `earth_frame` name and the signed sensor axes, each rotated by `mounting`.
+fn to_frames(
+    earth_frame: &str,
+    gyro_axes: Option<&str>,
+    accel_axes: Option<&str>,
+    mag_axes: Option<&str>,
+    mounting: Option<QuaternionP>,
+) -> PyResult<Frames<f64>> {
+    let mounting = mounting.map(|q| UnitQuaternion::from_quaternion(q.q));
+    let mapping = |axes: Option<&str>| -> PyResult<AxisMapping<f64>> {
+        let mapping = AxisMapping::from_axes(axes.unwrap_or("x,y,z")).map_err(to_config_pyerr)?;
+        Ok(mounting.map_or(mapping, |r| mapping.rotated(&r)))
+    };
+
+    PyResult::Ok(Frames {
+        earth: earth_frame.parse::<EarthFrame>().map_err(to_config_pyerr)?,
+        gyroscope: mapping(gyro_axes)?,
+        accelerometer: mapping(accel_axes)?,
+        magnetometer: mapping(mag_axes)?,
+    })
+}
+
+impl MadgwickP {
+    /// Filter with the given parameters and `frames`, starting at `quat` relative to the earth
+    /// frame.
+    fn with_frames(sample_period: f64, beta: f64, zeta: f64, quat: Quaternion<f64>, frames: Frames<f64>) -> Self {
+        // The filter starts in north-west-up, so express the quaternion there before switching frames
+        let mut mw = Madgwick::new_with_zeta(sample_period, beta, zeta, frames.earth.quat_to_nwu(&quat));
+        mw.set_frames(frames);
+
+        MadgwickP { mw: Calibrated::new(mw), last_timestamp: None }
+    }
+
+    /// Result of an update, the quaternion optionally followed by the linear acceleration in the
+    /// sensor and Earth frames.
+    fn output(
+        &self,
+        py: Python,
+        accelerometer: &Vector3<f64>,
+        linear_acceleration: bool,
+        gravity: f64,
+        unit: AccelUnit,
+        output_unit: AccelUnit,
+    ) -> PyObject {
+        let quat = QuaternionP::from(self.mw.ahrs().quat());
+
+        if !linear_acceleration {
+            return quat.into_py(py);
+        }
+
+        let accelerometer = self.mw.accel_calibration().map_or(*accelerometer, |cal| cal.apply(accelerometer));
+        let linear = self.mw.ahrs().linear_acceleration(&accelerometer, gravity, unit, output_unit);
+        let body = vec!(linear.body[0], linear.body[1], linear.body[2]);
+        let earth = vec!(linear.earth[0], linear.earth[1], linear.earth[2]);
+
+        (quat, body, earth).into_py(py)
+    }
+
+    /// Seconds elapsed between the previous timestamped sample and `timestamp`, or `None` for the
+    /// first sample.
+    fn elapsed(&self, timestamp: f64) -> PyResult<Option<f64>> {
+        match self.last_timestamp {
+            Some(last) if !(timestamp > last) => PyResult::Err(PyErr::new::<InvalidTimeStepError, _>(
+                "Timestamps must be monotonically increasing."
+            )),
+            Some(last) => PyResult::Ok(Some(timestamp - last)),
+            None => PyResult::Ok(None),
+        }
+    }
+}
+
+#[pyclass]
+pub struct MahonyP {
+    mh: Calibrated<f64, Mahony<f64>>
+}
+
+#[pymethods]
+impl MahonyP {
+    /// Creates a new `Mahony` AHRS instance with identity quaternion.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `kp` - Proportional filter gain.
+    /// * `ki` - Integral filter gain.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::mahony_filter::Mahony;
+    ///
+    /// fn main() {
+    ///     let ahrs = Mahony::new(0.002390625f64, 0.5, 0.0);
+    /// }
+    /// ```
+    #[new]
+    pub fn new(sample_period: f64, kp: f64, ki: f64) -> Self {
+        MahonyP { mh: Calibrated::new(Mahony::new(sample_period, kp, ki)) }
+    }
+
+    /// Creates a new `Mahony` AHRS instance with given quaternion.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `kp` - Proportional filter gain.
+    /// * `ki` - Integral filter gain.
+    /// * `quat` - Existing filter state quaternion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::Quaternion;
+    /// use lobster_rust::ahrs::mahony_filter::Mahony;
+    ///
+    /// fn main() {
+    ///     let ahrs = Mahony::new_with_quat(
+    ///         0.002390625f64,
+    ///         0.5,
+    ///         0.0,
+    ///         Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    ///     );
+    /// }
+    /// ```
+    #[staticmethod]
+    pub fn new_with_quat(sample_period: f64, kp: f64, ki: f64, quat: QuaternionP) -> Self {
+        MahonyP { mh: Calibrated::new(Mahony::new_with_quat(sample_period, kp, ki, quat.q)) }
+    }
+}
+
+#[pymethods]
+impl MahonyP {
+    fn update(
+        &mut self,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>,
+        magnetometer: Vec<f64>,
+    ) -> PyResult<QuaternionP> {
+        //Convert gyro, accel, magneto to nalgebra vectors
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
+        assert_eq!(magnetometer.len(), 3);
+        let m3: Vector3<f64> = Vector3::new(magnetometer[0], magnetometer[1], magnetometer[2]);
+
+        //Update mahony
+        match self.mh.update(&g3, &a3, &m3) {
+            Ok(quat) => PyResult::Ok(QuaternionP::from(*quat)),
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    fn update_imu(
+        &mut self,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>
+    ) -> PyResult<QuaternionP> {
+        //Convert gyro, accel to nalgebra vectors
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
+        //Update mahony
+        match self.mh.update_imu(&g3, &a3) {
+            Ok(quat) => PyResult::Ok(QuaternionP::from(*quat)),
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    /// Sets the gyroscope calibration applied before every update, or removes it with `None`.
+    fn set_gyro_calibration(&mut self, calibration: Option<GyroCalibrationP>) {
+        self.mh.set_gyro_calibration(calibration.map(|c| c.cal));
+    }
+
+    /// Sets the accelerometer calibration applied before every update, or removes it with `None`.
+    fn set_accel_calibration(&mut self, calibration: Option<AccelCalibrationP>) {
+        self.mh.set_accel_calibration(calibration.map(|c| c.cal));
+    }
+
+    /// Sets the magnetometer calibration applied before every update, or removes it with `None`.
+    fn set_mag_calibration(&mut self, calibration: Option<MagCalibrationP>) {
+        self.mh.set_mag_calibration(calibration.map(|c| c.cal));
+    }
+}
+
+#[pyclass]
+pub struct EkfP {
+    ekf: Calibrated<f64, Ekf<f64>>
+}
+
+#[pymethods]
+impl EkfP {
+    /// Creates a new `Ekf` AHRS instance with identity quaternion and zero gyroscope bias.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `gyro_noise` - Gyroscope noise density, in rad/s/√Hz.
+    /// * `gyro_bias_noise` - Gyroscope bias random walk density, in rad/s²/√Hz.
+    /// * `accel_noise` - Noise density of the normalized accelerometer measurement.
+    /// * `mag_noise` - Noise density of the normalized magnetometer measurement.
+    #[new]
+    pub fn new(sample_period: f64, gyro_noise: f64, gyro_bias_noise: f64, accel_noise: f64, mag_noise: f64) -> Self {
+        EkfP { ekf: Calibrated::new(Ekf::new(sample_period, gyro_noise, gyro_bias_noise, accel_noise, mag_noise)) }
+    }
+
+    /// Creates a new `Ekf` AHRS instance with given quaternion and zero gyroscope bias.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_period` - The expected sensor sampling period in seconds.
+    /// * `gyro_noise` - Gyroscope noise density, in rad/s/√Hz.
+    /// * `gyro_bias_noise` - Gyroscope bias random walk density, in rad/s²/√Hz.
+    /// * `accel_noise` - Noise density of the normalized accelerometer measurement.
+    /// * `mag_noise` - Noise density of the normalized magnetometer measurement.
+    /// * `quat` - Existing filter state quaternion.
+    #[staticmethod]
+    pub fn new_with_quat(
+        sample_period: f64,
+        gyro_noise: f64,
+        gyro_bias_noise: f64,
+        accel_noise: f64,
+        mag_noise: f64,
+        quat: QuaternionP,
+    ) -> Self {
+        EkfP {
+            ekf: Calibrated::new(Ekf::new_with_quat(
+                sample_period,
+                gyro_noise,
+                gyro_bias_noise,
+                accel_noise,
+                mag_noise,
+                quat.q,
+            ))
+        }
+    }
+}
+
+#[pymethods]
+impl EkfP {
+    /// Updates the filter and returns the quaternion together with the 7x7 covariance matrix as a
+    /// nested list.
+    fn update(
+        &mut self,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>,
+        magnetometer: Vec<f64>,
+    ) -> PyResult<(QuaternionP, Vec<Vec<f64>>)> {
+        //Convert gyro, accel, magneto to nalgebra vectors
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
+        assert_eq!(magnetometer.len(), 3);
+        let m3: Vector3<f64> = Vector3::new(magnetometer[0], magnetometer[1], magnetometer[2]);
+
+        //Update ekf
+        match self.ekf.update(&g3, &a3, &m3) {
+            Ok(quat) => PyResult::Ok((QuaternionP::from(*quat), self.covariance())),
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    /// Updates the filter without magnetometer and returns the quaternion
+    /// together with the 7x7 covariance matrix as a nested list.
+    fn update_imu(
+        &mut self,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>
+    ) -> PyResult<(QuaternionP, Vec<Vec<f64>>)> {
+        //Convert gyro, accel to nalgebra vectors
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
+        //Update ekf
+        match self.ekf.update_imu(&g3, &a3) {
+            Ok(quat) => PyResult::Ok((QuaternionP::from(*quat), self.covariance())),
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    /// State covariance matrix as a nested list, ordered as `[w, i, j, k, bx, by, bz]`.
+    fn covariance(&self) -> Vec<Vec<f64>> {
+        let p = self.ekf.ahrs().covariance();
+        p.row_iter().map(|row| row.iter().cloned().collect()).collect()
+    }
+
+    /// Estimated gyroscope bias as `[x, y, z]`, in rad/s.
+    fn gyro_bias(&self) -> Vec<f64> {
+        let b = self.ekf.ahrs().gyro_bias();
+        vec!(b[0], b[1], b[2])
+    }
+
+    /// Sets the gyroscope calibration applied before every update, or removes it with `None`.
+    fn set_gyro_calibration(&mut self, calibration: Option<GyroCalibrationP>) {
+        self.ekf.set_gyro_calibration(calibration.map(|c| c.cal));
+    }
+
+    /// Sets the accelerometer calibration applied before every update, or removes it with `None`.
+    fn set_accel_calibration(&mut self, calibration: Option<AccelCalibrationP>) {
+        self.ekf.set_accel_calibration(calibration.map(|c| c.cal));
+    }
+
+    /// Sets the magnetometer calibration applied before every update, or removes it with `None`.
+    fn set_mag_calibration(&mut self, calibration: Option<MagCalibrationP>) {
+        self.ekf.set_mag_calibration(calibration.map(|c| c.cal));
+    }
+}
+
+/// Strapdown inertial navigation on top of a Madgwick filter.
+#[pyclass(name = Ins)]
+pub struct InsP {
+    ins: Ins<f64, Madgwick<f64>>,
+    sample_period: f64,
+}
+
+#[pymethods]
+impl InsP {
+    /// Creates a navigation instance at rest at the origin, with a Madgwick filter of the given
+    /// `sample_period` and `beta`. Accelerometer measurements and `gravity` are given in `unit`,
+    /// either `"g"` or `"m/s^2"`. Velocity and position are in m/s and m, in `earth_frame`, either
+    /// `"nwu"`, `"ned"` or `"enu"`.
+    #[new]
+    #[args(gravity = "1.0", unit = "\"g\"", earth_frame = "\"nwu\"")]
+    pub fn new(sample_period: f64, beta: f64, gravity: f64, unit: &str, earth_frame: &str) -> PyResult<Self> {
+        let unit: AccelUnit = unit.parse().map_err(to_config_pyerr)?;
+        let frames = Frames::new(earth_frame.parse().map_err(to_config_pyerr)?);
+
+        PyResult::Ok(InsP {
+            ins: Ins::new(Madgwick::new_with_frames(sample_period, beta, frames), gravity, unit),
+            sample_period,
+        })
+    }
+
+    /// Updates orientation, velocity and position with `[x, y, z]` measurements taken `dt` seconds
+    /// after the previous update, or the sampling period if omitted.
+    ///
+    /// Returns the updated `[x, y, z]` position.
+    #[args(dt = "None")]
+    fn update(
+        &mut self,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>,
+        magnetometer: Vec<f64>,
+        dt: Option<f64>,
+    ) -> PyResult<Vec<f64>> {
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
+        assert_eq!(magnetometer.len(), 3);
+        let m3: Vector3<f64> = Vector3::new(magnetometer[0], magnetometer[1], magnetometer[2]);
+
+        match self.ins.update(&g3, &a3, &m3, dt.unwrap_or(self.sample_period)) {
+            Ok(p) => PyResult::Ok(vec!(p[0], p[1], p[2])),
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    /// Updates orientation without magnetometer, velocity and position with `[x, y, z]`
+    /// measurements taken `dt` seconds after the previous update, or the sampling period if
+    /// omitted.
+    ///
+    /// Returns the updated `[x, y, z]` position.
+    #[args(dt = "None")]
+    fn update_imu(
+        &mut self,
+        gyroscope: Vec<f64>,
+        accelerometer: Vec<f64>,
+        dt: Option<f64>,
+    ) -> PyResult<Vec<f64>> {
+        assert_eq!(gyroscope.len(), 3);
+        let g3: Vector3<f64> = Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]);
+
+        assert_eq!(accelerometer.len(), 3);
+        let a3: Vector3<f64> = Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]);
+
+        match self.ins.update_imu(&g3, &a3, dt.unwrap_or(self.sample_period)) {
+            Ok(p) => PyResult::Ok(vec!(p[0], p[1], p[2])),
+            Err(e) => PyResult::Err(to_pyerr(e))
+        }
+    }
+
+    /// Zeroes the velocity while the sensor is stationary and returns the removed `[x, y, z]`
+    /// velocity.
+    fn zero_velocity_update(&mut self) -> Vec<f64> {
+        let v = self.ins.zero_velocity_update();
+        vec!(v[0], v[1], v[2])
+    }
+
+    /// Moves the position towards an external `[x, y, z]` position `fix` by `weight`, with `1`
+    /// replacing it.
+    #[args(weight = "1.0")]
+    fn correct_position(&mut self, fix: Vec<f64>, weight: f64) {
+        assert_eq!(fix.len(), 3);
+        self.ins.correct_position(&Vector3::new(fix[0], fix[1], fix[2]), weight);
+    }
+
+    /// Zeroes velocity and moves the position back to the origin, keeping the orientation.
+    fn reset(&mut self) {
+        self.ins.reset();
+    }
+
+    #[getter]
+    fn quaternion(&self) -> QuaternionP {
+        QuaternionP::from(self.ins.ahrs().quat)
+    }
+
+    #[getter]
+    fn velocity(&self) -> Vec<f64> {
+        let v = self.ins.velocity();
+        vec!(v[0], v[1], v[2])
+    }
+
+    #[setter]
+    fn set_velocity(&mut self, velocity: Vec<f64>) -> PyResult<()> {
+        if velocity.len() != 3 {
+            return Err(PyErr::new::<ValueError, _>("Velocity must have 3 components."));
+        }
+
+        self.ins.set_velocity(Vector3::new(velocity[0], velocity[1], velocity[2]));
+        Ok(())
+    }
+
+    #[getter]
+    fn position(&self) -> Vec<f64> {
+        let p = self.ins.position();
+        vec!(p[0], p[1], p[2])
+    }
+
+    #[setter]
+    fn set_position(&mut self, position: Vec<f64>) -> PyResult<()> {
+        if position.len() != 3 {
+            return Err(PyErr::new::<ValueError, _>("Position must have 3 components."));
+        }
+
+        self.ins.set_position(Vector3::new(position[0], position[1], position[2]));
+        Ok(())
+    }
+
+    #[getter]
+    fn acceleration(&self) -> Vec<f64> {
+        let a = self.ins.acceleration();
+        vec!(a[0], a[1], a[2])
+    }
+}
+
+#[pyclass(name = MagCalibration)]
+#[derive(Clone)]
+pub struct MagCalibrationP {
+    cal: MagCalibration<f64>,
+}
+
+#[pymethods]
+impl MagCalibrationP {
+    /// Creates a magnetometer calibration from a known `[x, y, z]` hard-iron offset and a 3x3
+    /// soft-iron matrix given as a nested list of rows.
+    #[new]
+    pub fn new(hard_iron: Vec<f64>, soft_iron: Vec<Vec<f64>>) -> Self {
+        assert_eq!(hard_iron.len(), 3);
+
+        MagCalibrationP {
+            cal: MagCalibration::new(
+                Vector3::new(hard_iron[0], hard_iron[1], hard_iron[2]),
+                to_matrix(soft_iron),
+            )
+        }
+    }
+
+    /// Fits a calibration to a list of raw `[x, y, z]` magnetometer samples.
+    #[staticmethod]
+    pub fn fit(samples: Vec<Vec<f64>>) -> PyResult<Self> {
+        match MagCalibration::fit(&to_vectors(samples)) {
+            Ok(cal) => PyResult::Ok(MagCalibrationP { cal }),
+            Err(e) => PyResult::Err(to_config_pyerr(e))
+        }
+    }
+
+    /// Corrects a raw `[x, y, z]` magnetometer measurement.
+    fn apply(&self, magnetometer: Vec<f64>) -> Vec<f64> {
+        assert_eq!(magnetometer.len(), 3);
+        let m = self.cal.apply(&Vector3::new(magnetometer[0], magnetometer[1], magnetometer[2]));
+        vec!(m[0], m[1], m[2])
+    }
+
+    #[getter]
+    fn hard_iron(&self) -> Vec<f64> {
+        let b = self.cal.hard_iron();
+        vec!(b[0], b[1], b[2])
+    }
+
+    #[getter]
+    fn soft_iron(&self) -> Vec<Vec<f64>> {
+        to_rows(&self.cal.soft_iron())
+    }
+
+    #[getter]
+    fn field_strength(&self) -> f64 {
+        self.cal.field_strength()
+    }
+
+    #[getter]
+    fn fit_error(&self) -> f64 {
+        self.cal.fit_error()
+    }
+}
+
+#[pyclass(name = StationaryDetector)]
+#[derive(Clone)]
+pub struct StationaryDetectorP {
+    detector: StationaryDetector<f64>,
+}
+
+#[pymethods]
+impl StationaryDetectorP {
+    /// Creates a stationary detector testing windows of `window` samples, with the accelerometer
+    /// noise and `gravity` in accelerometer units and the gyroscope noise in rad/s. The sensor is
+    /// stationary while the test statistic is below `threshold`. Windows hold at most 64 samples.
+    #[new]
+    #[args(gravity = "1.0", threshold = "30.0")]
+    pub fn new(window: usize, accel_noise: f64, gyro_noise: f64, gravity: f64, threshold: f64) -> PyResult<Self> {
+        if window > stationary::MAX_WINDOW {
+            return PyResult::Err(PyErr::new::<ValueError, _>(
+                format!("The window must hold at most {} samples.", stationary::MAX_WINDOW)
+            ));
+        }
+
+        PyResult::Ok(StationaryDetectorP { detector: StationaryDetector::new(window, accel_noise, gyro_noise, gravity, threshold) })
+    }
+
+    /// Adds an `[x, y, z]` measurement pair and returns whether the sensor is stationary.
+    fn update(&mut self, gyroscope: Vec<f64>, accelerometer: Vec<f64>) -> bool {
+        assert_eq!(gyroscope.len(), 3);
+        assert_eq!(accelerometer.len(), 3);
+
+        self.detector.update(
+            &Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]),
+            &Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]),
+        )
+    }
+
+    /// Labels every row of the Nx3 `gyroscope` and `accelerometer` arrays of a recording, with
+    /// the GIL released.
+    ///
+    /// Returns a length N mask that is `True` for stationary samples.
+    fn label(
+        &mut self,
+        py: Python,
+        gyroscope: &PyArray2<f64>,
+        accelerometer: &PyArray2<f64>,
+    ) -> PyResult<Py<PyArray1<bool>>> {
+        let g = gyroscope.as_array();
+        let a = accelerometer.as_array();
+
+        check_batch_shape(&g, g.nrows())?;
+        check_batch_shape(&a, g.nrows())?;
+
+        let detector = &mut self.detector;
+        let labels = py.allow_threads(move || {
+            (0..g.nrows()).map(|n| detector.update(
+                &Vector3::new(g[[n, 0]], g[[n, 1]], g[[n, 2]]),
+                &Vector3::new(a[[n, 0]], a[[n, 1]], a[[n, 2]]),
+            )).collect::<Array1<bool>>()
+        });
+
+        PyResult::Ok(labels.into_pyarray(py).to_owned())
+    }
+
+    /// Forgets every sample seen so far.
+    fn reset(&mut self) {
+        self.detector.reset();
+    }
+
+    #[getter]
+    fn stationary(&self) -> bool {
+        self.detector.is_stationary()
+    }
+
+    #[getter]
+    fn statistic(&self) -> f64 {
+        self.detector.statistic()
+    }
+}
+
+/// Converts per-sample stationary `labels` into a list of `(start, end)` sample ranges, with
+/// `end` exclusive.
+#[pyfunction]
+fn stationary_intervals(labels: Vec<bool>) -> Vec<(usize, usize)> {
+    stationary::intervals(&labels)
+}
+
+#[pyclass(name = SensorModel)]
+#[derive(Clone)]
+pub struct SensorModelP {
+    model: SensorModel<f64>,
+}
+
+#[pymethods]
+impl SensorModelP {
+    /// Creates a sensor error model, ideal unless errors are given. `bias` and `scale_error` are
+    /// `[x, y, z]` lists, the bias instability is a Gauss-Markov process with
+    /// `bias_correlation_time` in seconds, and the random walk is per √s. Zero `quantization` or
+    /// `saturation` disables them.
+    #[new]
+    #[args(
+        white_noise = "0.0",
+        bias = "None",
+        bias_instability = "0.0",
+        bias_correlation_time = "100.0",
+        random_walk = "0.0",
+        scale_error = "None",
+        quantization = "0.0",
+        saturation = "0.0"
+    )]
+    pub fn new(
+        white_noise: f64,
+        bias: Option<Vec<f64>>,
+        bias_instability: f64,
+        bias_correlation_time: f64,
+        random_walk: f64,
+        scale_error: Option<Vec<f64>>,
+        quantization: f64,
+        saturation: f64,
+    ) -> PyResult<Self> {
+        PyResult::Ok(SensorModelP {
+            model: SensorModel {
+                white_noise,
+                bias: to_optional_vector(bias, "Bias")?,
+                bias_instability,
+                bias_correlation_time,
+                random_walk,
+                scale_error: to_optional_vector(scale_error, "Scale error")?,
+                quantization,
+                saturation,
+            }
+        })
+    }
+}
+
+/// Orientation trajectory built from consecutive segments, for simulating IMU recordings.
+#[pyclass(name = Trajectory)]
+#[derive(Clone)]
+pub struct TrajectoryP {
+    trajectory: Trajectory<f64>,
+}
+
+#[pymethods]
+impl TrajectoryP {
+    /// Creates an empty trajectory sampled every `sample_period` seconds, starting at the
+    /// `initial` orientation relative to north-west-up, or identity if omitted. The magnetic
+    /// `field` is an `[x, y, z]` list in the Earth frame, pointing north with a dip of about 53°
+    /// if omitted.
+    #[new]
+    #[args(initial = "None", gravity = "1.0", field = "None")]
+    pub fn new(sample_period: f64, initial: Option<QuaternionP>, gravity: f64, field: Option<Vec<f64>>) -> PyResult<Self> {
+        let initial = match initial {
+            Some(quat) => UnitQuaternion::from_quaternion(quat.q),
+            None => UnitQuaternion::identity(),
+        };
+
+        let mut trajectory = Trajectory::new(initial, sample_period);
+        trajectory.gravity = gravity;
+
+        if let Some(field) = field {
+            trajectory.field = to_optional_vector(Some(field), "Field")?;
+        }
+
+        PyResult::Ok(TrajectoryP { trajectory })
+    }
+
+    /// Appends a segment holding the body still for `duration` seconds.
+    fn still(&mut self, duration: f64) {
+        self.trajectory.push(Segment::still(duration));
+    }
+
+    /// Appends a segment rotating the body at a constant `[x, y, z]` `rate`, in rad/s, while
+    /// accelerating it with an `[x, y, z]` Earth frame `acceleration`, in the unit of gravity.
+    #[args(acceleration = "None")]
+    fn constant(&mut self, duration: f64, rate: Vec<f64>, acceleration: Option<Vec<f64>>) -> PyResult<()> {
+        let rate = to_optional_vector(Some(rate), "Rate")?;
+        let acceleration = to_optional_vector(acceleration, "Acceleration")?;
+
+        self.trajectory.push(Segment::constant(duration, rate, acceleration));
+        PyResult::Ok(())
+    }
+
+    /// Appends a segment oscillating the `[x, y, z]` angular `rate` and Earth frame
+    /// `acceleration` amplitudes with `frequency`, in Hz.
+    #[args(acceleration = "None")]
+    fn sinusoidal(&mut self, duration: f64, rate: Vec<f64>, frequency: f64, acceleration: Option<Vec<f64>>) -> PyResult<()> {
+        let rate = to_optional_vector(Some(rate), "Rate")?;
+        let acceleration = to_optional_vector(acceleration, "Acceleration")?;
+
+        self.trajectory.push(Segment::sinusoidal(duration, rate, acceleration, frequency));
+        PyResult::Ok(())
+    }
+
+    /// Samples the trajectory and corrupts the ideal measurements with the given sensor models,
+    /// ideal if omitted, using noise seeded with `seed`, with the GIL released.
+    ///
+    /// Returns a length N array of sample times, an Nx4 array of true quaternions with
+    /// components in `order`, either `"wxyz"` or `"xyzw"`, and Nx3 gyroscope, accelerometer and
+    /// magnetometer arrays.
+    #[args(gyroscope = "None", accelerometer = "None", magnetometer = "None", seed = "0", order = "\"wxyz\"")]
+    fn simulate(
+        &self,
+        py: Python,
+        gyroscope: Option<SensorModelP>,
+        accelerometer: Option<SensorModelP>,
+        magnetometer: Option<SensorModelP>,
+        seed: u64,
+        order: &str,
+    ) -> PyResult<(Py<PyArray1<f64>>, Py<PyArray2<f64>>, Py<PyArray2<f64>>, Py<PyArray2<f64>>, Py<PyArray2<f64>>)> {
+        let order: QuatOrder = order.parse()?;
+
+        let model = ImuModel {
+            gyroscope: gyroscope.map(|s| s.model).unwrap_or_default(),
+            accelerometer: accelerometer.map(|s| s.model).unwrap_or_default(),
+            magnetometer: magnetometer.map(|s| s.model).unwrap_or_default(),
+        };
+
+        let trajectory = &self.trajectory;
+        let (time, quats, g, a, m) = py.allow_threads(move || {
+            let sim = sim::simulate(trajectory, &model, seed);
+
+            let time = sim.truth.iter().map(|t| t.time).collect::<Array1<f64>>();
+
+            let mut quats = Array2::<f64>::zeros((sim.truth.len(), 4));
+            for (n, truth) in sim.truth.iter().enumerate() {
+                quats.row_mut(n).assign(&Array1::from(order.components(&truth.quat).to_vec()));
+            }
+
+            (time, quats, to_array(&sim.gyroscope), to_array(&sim.accelerometer), to_array(&sim.magnetometer))
+        });
+
+        PyResult::Ok((
+            time.into_pyarray(py).to_owned(),
+            quats.into_pyarray(py).to_owned(),
+            g.into_pyarray(py).to_owned(),
+            a.into_pyarray(py).to_owned(),
+            m.into_pyarray(py).to_owned(),
+        ))
+    }
+
+    #[getter]
+    fn duration(&self) -> f64 {
+        self.trajectory.duration()
+    }
+}
+
+/// Fits a magnetometer calibration to a list of raw `[x, y, z]` samples.
+#[pyfunction]
+fn calibrate_magnetometer(samples: Vec<Vec<f64>>) -> PyResult<MagCalibrationP> {
+    MagCalibrationP::fit(samples)
+}
+
+#[pyclass(name = AccelCalibration)]
+#[derive(Clone)]
+pub struct AccelCalibrationP {
+    cal: AccelCalibration<f64>,
+}
+
+#[pymethods]
+impl AccelCalibrationP {
+    /// Creates an accelerometer calibration from a known `[x, y, z]` bias and a 3x3 sensitivity
+    /// matrix given as a nested list of rows, with `gravity` in sensor units.
+    #[new]
+    #[args(gravity = "1.0")]
+    pub fn new(bias: Vec<f64>, sensitivity: Vec<Vec<f64>>, gravity: f64) -> PyResult<Self> {
+        assert_eq!(bias.len(), 3);
+
+        match AccelCalibration::new(Vector3::new(bias[0], bias[1], bias[2]), to_matrix(sensitivity), gravity) {
+            Ok(cal) => PyResult::Ok(AccelCalibrationP { cal }),
+            Err(e) => PyResult::Err(to_config_pyerr(e))
+        }
+    }
+
+    /// Corrects a raw `[x, y, z]` accelerometer measurement.
+    fn apply(&self, accelerometer: Vec<f64>) -> Vec<f64> {
+        assert_eq!(accelerometer.len(), 3);
+        let a = self.cal.apply(&Vector3::new(accelerometer[0], accelerometer[1], accelerometer[2]));
+        vec!(a[0], a[1], a[2])
+    }
+
+    /// Residual of every corrected static `[x, y, z]` sample, in sensor units.
+    fn residuals(&self, samples: Vec<Vec<f64>>) -> Vec<f64> {
+        self.cal.residuals(&to_vectors(samples))
+    }
+
+    #[getter]
+    fn bias(&self) -> Vec<f64> {
+        let b = self.cal.bias();
+        vec!(b[0], b[1], b[2])
+    }
+
+    #[getter]
+    fn scale(&self) -> Vec<f64> {
+        let s = self.cal.scale();
+        vec!(s[0], s[1], s[2])
+    }
+
+    #[getter]
+    fn misalignment(&self) -> Vec<Vec<f64>> {
+        to_rows(&self.cal.misalignment())
+    }
+
+    #[getter]
+    fn sensitivity(&self) -> Vec<Vec<f64>> {
+        to_rows(&self.cal.sensitivity())
+    }
+
+    #[getter]
+    fn gravity(&self) -> f64 {
+        self.cal.gravity()
+    }
+
+    #[getter]
+    fn fit_error(&self) -> f64 {
+        self.cal.fit_error()
+    }
+}
+
+#[pyclass(name = GyroCalibration)]
+#[derive(Clone)]
+pub struct GyroCalibrationP {
+    cal: GyroCalibration<f64>,
+}
+
+#[pymethods]
+impl GyroCalibrationP {
+    /// Creates a gyroscope calibration from a known `[x, y, z]` bias.
+    #[new]
+    pub fn new(bias: Vec<f64>) -> Self {
+        assert_eq!(bias.len(), 3);
+        GyroCalibrationP { cal: GyroCalibration::new(Vector3::new(bias[0], bias[1], bias[2])) }
+    }
+
+    /// Corrects a raw `[x, y, z]` gyroscope measurement.
+    fn apply(&self, gyroscope: Vec<f64>) -> Vec<f64> {
+        assert_eq!(gyroscope.len(), 3);
+        let g = self.cal.apply(&Vector3::new(gyroscope[0], gyroscope[1], gyroscope[2]));
+        vec!(g[0], g[1], g[2])
+    }
+
+    /// Magnitude of every corrected stationary `[x, y, z]` sample, in sensor units.
+    fn residuals(&self, samples: Vec<Vec<f64>>) -> Vec<f64> {
+        self.cal.residuals(&to_vectors(samples))
+    }
+
+    #[getter]
+    fn bias(&self) -> Vec<f64> {
+        let b = self.cal.bias();
+        vec!(b[0], b[1], b[2])
+    }
+
+    #[getter]
+    fn noise(&self) -> Vec<f64> {
+        let n = self.cal.noise();
+        vec!(n[0], n[1], n[2])
+    }
+}
+
+/// Fits an accelerometer calibration to static `[x, y, z]` samples captured with every axis in
+/// turn pointing up and down, with `gravity` in sensor units.
+///
+/// Returns the calibration and the residual of every sample.
+#[pyfunction(gravity = "1.0")]
+fn calibrate_accelerometer(samples: Vec<Vec<f64>>, gravity: f64) -> PyResult<(AccelCalibrationP, Vec<f64>)> {
+    let samples = to_vectors(samples);
+
+    match AccelCalibration::fit(&samples, gravity) {
+        Ok(cal) => PyResult::Ok((AccelCalibrationP { cal }, cal.residuals(&samples))),
+        Err(e) => PyResult::Err(to_config_pyerr(e))
+    }
+}
+
+/// Estimates the gyroscope bias from `[x, y, z]` samples taken while stationary.
+///
+/// Returns the calibration and the magnitude of every corrected sample.
+#[pyfunction]
+fn calibrate_gyroscope(samples: Vec<Vec<f64>>) -> PyResult<(GyroCalibrationP, Vec<f64>)> {
+    let samples = to_vectors(samples);
+
+    match GyroCalibration::fit(&samples) {
+        Ok(cal) => PyResult::Ok((GyroCalibrationP { cal }, cal.residuals(&samples))),
+        Err(e) => PyResult::Err(to_config_pyerr(e))
+    }
+}
+
+/// Computes the overlapping Allan deviation of every column of an NxK array of sensor `samples`
+/// taken every `sample_period` seconds, with the GIL released. `clusters` lists the cluster sizes
+/// in samples, spaced logarithmically with `per_decade` sizes per decade if omitted.
+///
+/// Returns a length M array of cluster times, an MxK array of deviations and a Kx3 array of the
+/// fitted angle random walk, bias instability and rate random walk of every column.
+#[pyfunction(clusters = "None", per_decade = "10")]
+fn allan_deviation(
+    py: Python,
+    samples: &PyArray2<f64>,
+    sample_period: f64,
+    clusters: Option<Vec<usize>>,
+    per_decade: usize,
+) -> PyResult<(Py<PyArray1<f64>>, Py<PyArray2<f64>>, Py<PyArray2<f64>>)> {
+    let s = samples.as_array();
+    let clusters = clusters.unwrap_or_else(|| allan::cluster_sizes(s.nrows(), per_decade));
+
+    let result = py.allow_threads(move || -> Result<_, AhrsError> {
+        let mut tau = Array1::<f64>::zeros(0);
+        let mut deviation = Array2::<f64>::zeros((0, s.ncols()));
+        let mut parameters = Array2::<f64>::zeros((s.ncols(), 3));
+
+        for c in 0..s.ncols() {
+            let adev = allan::allan_deviation(&s.column(c).to_vec(), sample_period, &clusters)?;
+            let noise = adev.fit()?;
+
+            if c == 0 {
+                tau = Array1::from(adev.tau.clone());
+                deviation = Array2::<f64>::zeros((tau.len(), s.ncols()));
+            }
+
+            deviation.column_mut(c).assign(&Array1::from(adev.deviation));
+            parameters.row_mut(c).assign(&Array1::from(vec!(
+                noise.angle_random_walk,
+                noise.bias_instability,
+                noise.rate_random_walk,
+            )));
+        }
+
+        Ok((tau, deviation, parameters))
+    });
+
+    match result {
+        Ok((tau, deviation, parameters)) => PyResult::Ok((
+            tau.into_pyarray(py).to_owned(),
+            deviation.into_pyarray(py).to_owned(),
+            parameters.into_pyarray(py).to_owned(),
+        )),
+        Err(e) => PyResult::Err(to_pyerr(e))
+    }
+}
+
+/// Angular error, in radians, of every row of an Nx4 array of estimated quaternions against the
+/// ground truth row, with components in `order`, either `"wxyz"` or `"xyzw"`. The sign of either
+/// quaternion does not change the error.
+#[pyfunction(order = "\"wxyz\"")]
+fn angular_error(py: Python, estimates: &PyArray2<f64>, truth: &PyArray2<f64>, order: &str) -> PyResult<Py<PyArray1<f64>>> {
+    let order: QuatOrder = order.parse()?;
+
+    let estimates = to_quaternions(&estimates.as_array(), order)?;
+    let truth = to_quaternions(&truth.as_array(), order)?;
+
+    match eval::angular_errors(&estimates, &truth) {
+        Ok(errors) => PyResult::Ok(Array1::from(errors).into_pyarray(py).to_owned()),
+        Err(e) => PyResult::Err(to_eval_pyerr(e))
+    }
+}
+
+/// Evaluates an Nx4 array of estimated quaternions against ground truth quaternions sampled at
+/// `times`, in seconds, with components in `order`, either `"wxyz"` or `"xyzw"`.
+///
+/// Returns a dict with the angular `rmse`, the `euler_rmse` as `[roll, pitch, yaw]` and the
+/// `max_error`, all in radians, the `convergence_time` after which the angular error stays below
+/// `threshold`, or `None`, and the `heading_drift_rate` in rad/s.
+#[pyfunction(threshold = "0.05", order = "\"wxyz\"")]
+fn evaluate(
+    py: Python,
+    estimates: &PyArray2<f64>,
+    truth: &PyArray2<f64>,
+    times: Vec<f64>,
+    threshold: f64,
+    order: &str,
+) -> PyResult<PyObject> {
+    let order: QuatOrder = order.parse()?;
+
+    let estimates = to_quaternions(&estimates.as_array(), order)?;
+    let truth = to_quaternions(&truth.as_array(), order)?;
+
+    let accuracy = eval::evaluate(&estimates, &truth, &times, threshold).map_err(to_eval_pyerr)?;
+    let euler = accuracy.euler_rmse;
+
+    let result = PyDict::new(py);
+    result.set_item("rmse", accuracy.rmse)?;
+    result.set_item("euler_rmse", vec!(euler[0], euler[1], euler[2]))?;
+    result.set_item("max_error", accuracy.max_error)?;
+    result.set_item("convergence_time", accuracy.convergence_time)?;
+    result.set_item("heading_drift_rate", accuracy.heading_drift_rate)?;
+
+    PyResult::Ok(result.to_object(py))
+}
+
+/// Replays the CSV sensor log at `path` through a `MadgwickP`, `MahonyP` or `EkfP` `filter`, with
+/// the GIL released, and writes the timestamped quaternions and roll, pitch and yaw to a CSV at
+/// `output`.
+///
+/// The `timestamp` column is in units of `time_scale` seconds, and the `gyroscope`,
+/// `accelerometer` and `magnetometer` columns default to `gx, gy, gz`, `ax, ay, az` and
+/// `mx, my, mz`, in `gyro_unit` (`"rad/s"` or `"deg/s"`), `accel_unit` (`"g"` or `"m/s^2"`) and
+/// `mag_unit` (`"uT"` or `"gauss"`), separated by `delimiter`. With `imu_only` the magnetometer
+/// columns are ignored. Euler angles are written in degrees if `degrees` is true.
+///
+/// Returns the number of rows read and of failed filter updates.
+#[pyfunction(
+    timestamp = "\"timestamp\"",
+    gyroscope = "None",
+    accelerometer = "None",
+    magnetometer = "None",
+    imu_only = "false",
+    time_scale = "1.0",
+    gyro_unit = "\"rad/s\"",
+    accel_unit = "\"g\"",
+    mag_unit = "\"uT\"",
+    delimiter = "\",\"",
+    degrees = "false"
+)]
+fn replay_csv(
+    py: Python,
+    path: &str,
+    filter: &PyAny,
+    output: &str,
+    timestamp: &str,
+    gyroscope: Option<Vec<String>>,
+    accelerometer: Option<Vec<String>>,
+    magnetometer: Option<Vec<String>>,
+    imu_only: bool,
+    time_scale: f64,
+    gyro_unit: &str,
+    accel_unit: &str,
+    mag_unit: &str,
+    delimiter: &str,
+    degrees: bool,
+) -> PyResult<(usize, usize)> {
+    let defaults = Columns::default();
+    let magnetometer = match defaults.magnetometer {
+        Some(default) if !imu_only => Some(to_columns(magnetometer, default, "Magnetometer")?),
+        _ => None,
+    };
+
+    if delimiter.len() != 1 {
+        return PyResult::Err(PyErr::new::<ValueError, _>("Delimiter must be a single character."));
+    }
+
+    let format = LogFormat {
+        columns: Columns {
+            timestamp: timestamp.to_string(),
+            gyroscope: to_columns(gyroscope, defaults.gyroscope, "Gyroscope")?,
+            accelerometer: to_columns(accelerometer, defaults.accelerometer, "Accelerometer")?,
+            magnetometer,
+        },
+        time_scale,
+        gyro_unit: gyro_unit.parse().map_err(to_config_pyerr)?,
+        accel_unit: accel_unit.parse().map_err(to_config_pyerr)?,
+        mag_unit: mag_unit.parse().map_err(to_config_pyerr)?,
+        delimiter: delimiter.as_bytes()[0],
+    };
+
+    let summary = if let Ok(mut f) = filter.extract::<PyRefMut<MadgwickP>>() {
+        let mw = &mut f.mw;
+        py.allow_threads(move || replay::replay_csv(mw, path, output, format, degrees))
+    } else if let Ok(mut f) = filter.extract::<PyRefMut<MahonyP>>() {
+        let mh = &mut f.mh;
+        py.allow_threads(move || replay::replay_csv(mh, path, output, format, degrees))
+    } else if let Ok(mut f) = filter.extract::<PyRefMut<EkfP>>() {
+        let ekf = &mut f.ekf;
+        py.allow_threads(move || replay::replay_csv(ekf, path, output, format, degrees))
+    } else {
+        return PyResult::Err(PyErr::new::<pyo3::exceptions::TypeError, _>("Filter must be a MadgwickP, MahonyP or EkfP."));
+    };
+
+    match summary {
+        Ok(summary) => PyResult::Ok((summary.samples, summary.failed)),
+        Err(e) => PyResult::Err(to_log_pyerr(e))
+    }
+}
+
+#[pymodule]
+fn ahrs(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<QuaternionP>()?;
+    m.add_class::<MadgwickP>()?;
+    m.add_class::<MahonyP>()?;
+    m.add_class::<EkfP>()?;
+    m.add_class::<InsP>()?;
+    m.add_class::<MagCalibrationP>()?;
+    m.add_class::<AccelCalibrationP>()?;
+    m.add_class::<GyroCalibrationP>()?;
+    m.add_class::<StationaryDetectorP>()?;
+    m.add_class::<SensorModelP>()?;
+    m.add_class::<TrajectoryP>()?;
+    m.add_wrapped(wrap_pyfunction!(calibrate_magnetometer))?;
+    m.add_wrapped(wrap_pyfunction!(calibrate_accelerometer))?;
+    m.add_wrapped(wrap_pyfunction!(calibrate_gyroscope))?;
+    m.add_wrapped(wrap_pyfunction!(stationary_intervals))?;
+    m.add_wrapped(wrap_pyfunction!(allan_deviation))?;
+    m.add_wrapped(wrap_pyfunction!(angular_error))?;
+    m.add_wrapped(wrap_pyfunction!(evaluate))?;
+    m.add_wrapped(wrap_pyfunction!(replay_csv))?;
+
+    m.add("MadgwickError", py.get_type::<MadgwickError>())?;
+    m.add("AccelerometerNormError", py.get_type::<AccelerometerNormError>())?;
+    m.add("MagnetometerNormError", py.get_type::<MagnetometerNormError>())?;
+    m.add("NonFiniteInputError", py.get_type::<NonFiniteInputError>())?;
+    m.add("DegenerateGradientError", py.get_type::<DegenerateGradientError>())?;
+    m.add("NonFiniteStateError", py.get_type::<NonFiniteStateError>())?;
+    m.add("InvalidTimeStepError", py.get_type::<InvalidTimeStepError>())?;
+    m.add("SingularCovarianceError", py.get_type::<SingularCovarianceError>())?;
+    m.add("ParallelVectorsError", py.get_type::<ParallelVectorsError>())?;
+    m.add("NoSamplesError", py.get_type::<NoSamplesError>())?;
+    m.add("CalibrationError", py.get_type::<CalibrationError>())?;
 
     Ok(())
 }
\ No newline at end of file
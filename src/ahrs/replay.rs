@@ -0,0 +1,527 @@
+extern crate nalgebra as na;
+extern crate alga;
+extern crate csv;
+
+use alga::general::RealField;
+use na::Vector3;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+use crate::ahrs::acceleration::AccelUnit;
+use crate::ahrs::ahrs::Ahrs;
+use crate::ahrs::conversion::{euler_angles, EulerSequence};
+use crate::ahrs::error::ConfigError;
+
+/// Unit of an angular rate.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum GyroUnit {
+    /// Radians per second.
+    RadiansPerSecond,
+    /// Degrees per second.
+    DegreesPerSecond,
+}
+
+impl GyroUnit {
+    /// Converts an angular rate `value` from this unit to the unit `to`.
+    pub fn convert<N: RealField>(&self, value: N, to: GyroUnit) -> N {
+        let half_turn: N = na::convert(180.0);
+
+        match (self, to) {
+            (GyroUnit::DegreesPerSecond, GyroUnit::RadiansPerSecond) => value * N::pi() / half_turn,
+            (GyroUnit::RadiansPerSecond, GyroUnit::DegreesPerSecond) => value * half_turn / N::pi(),
+            _ => value,
+        }
+    }
+}
+
+impl FromStr for GyroUnit {
+    type Err = ConfigError;
+
+    /// Parses `"rad/s"`, or `"deg/s"`, `"dps"` or `"°/s"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rad/s" => Ok(GyroUnit::RadiansPerSecond),
+            "deg/s" | "dps" | "°/s" => Ok(GyroUnit::DegreesPerSecond),
+            _ => Err(ConfigError::InvalidUnit),
+        }
+    }
+}
+
+/// Unit of a magnetic flux density.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum MagUnit {
+    /// Microtesla.
+    Microtesla,
+    /// Gauss, equal to 100 µT.
+    Gauss,
+}
+
+impl MagUnit {
+    /// Converts a magnetic flux density `value` from this unit to the unit `to`.
+    pub fn convert<N: RealField>(&self, value: N, to: MagUnit) -> N {
+        let microtesla_per_gauss: N = na::convert(100.0);
+
+        match (self, to) {
+            (MagUnit::Gauss, MagUnit::Microtesla) => value * microtesla_per_gauss,
+            (MagUnit::Microtesla, MagUnit::Gauss) => value / microtesla_per_gauss,
+            _ => value,
+        }
+    }
+}
+
+impl FromStr for MagUnit {
+    type Err = ConfigError;
+
+    /// Parses `"uT"` or `"µT"`, or `"gauss"` or `"Ga"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ut" | "µt" => Ok(MagUnit::Microtesla),
+            "gauss" | "ga" => Ok(MagUnit::Gauss),
+            _ => Err(ConfigError::InvalidUnit),
+        }
+    }
+}
+
+/// Header names of the columns of a sensor log.
+#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Columns {
+    /// Timestamp column.
+    pub timestamp: String,
+    /// Gyroscope x, y and z columns.
+    pub gyroscope: [String; 3],
+    /// Accelerometer x, y and z columns.
+    pub accelerometer: [String; 3],
+    /// Magnetometer x, y and z columns, or `None` to replay without magnetometer.
+    pub magnetometer: Option<[String; 3]>,
+}
+
+impl Default for Columns {
+    /// `timestamp`, `gx`, `gy`, `gz`, `ax`, `ay`, `az`, `mx`, `my` and `mz`.
+    fn default() -> Self {
+        let axes = |prefix: &str| [format!("{}x", prefix), format!("{}y", prefix), format!("{}z", prefix)];
+
+        Columns {
+            timestamp: "timestamp".to_string(),
+            gyroscope: axes("g"),
+            accelerometer: axes("a"),
+            magnetometer: Some(axes("m")),
+        }
+    }
+}
+
+/// Layout and units of a CSV sensor log.
+#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct LogFormat<N: RealField> {
+    /// Header names of the sensor columns.
+    pub columns: Columns,
+    /// Seconds per timestamp unit, for example `0.001` for milliseconds.
+    pub time_scale: N,
+    /// Unit of the gyroscope columns.
+    pub gyro_unit: GyroUnit,
+    /// Unit of the accelerometer columns.
+    pub accel_unit: AccelUnit,
+    /// Unit of the magnetometer columns.
+    pub mag_unit: MagUnit,
+    /// Field delimiter.
+    pub delimiter: u8,
+}
+
+impl<N: RealField> Default for LogFormat<N> {
+    /// Comma separated default columns, with timestamps in seconds and measurements in rad/s, g
+    /// and µT.
+    fn default() -> Self {
+        LogFormat {
+            columns: Columns::default(),
+            time_scale: N::one(),
+            gyro_unit: GyroUnit::RadiansPerSecond,
+            accel_unit: AccelUnit::G,
+            mag_unit: MagUnit::Microtesla,
+            delimiter: b',',
+        }
+    }
+}
+
+impl<N: RealField> LogFormat<N> {
+    /// Converts a timestamp and measurements in the units of this format to a sample in seconds,
+    /// rad/s, g and µT.
+    pub fn sample(&self, timestamp: N, gyroscope: Vector3<N>, accelerometer: Vector3<N>, magnetometer: Option<Vector3<N>>) -> LogSample<N> {
+        LogSample {
+            timestamp: timestamp * self.time_scale,
+            gyroscope: gyroscope.map(|x| self.gyro_unit.convert(x, GyroUnit::RadiansPerSecond)),
+            accelerometer: accelerometer.map(|x| self.accel_unit.convert(x, AccelUnit::G)),
+            magnetometer: magnetometer.map(|m| m.map(|x| self.mag_unit.convert(x, MagUnit::Microtesla))),
+        }
+    }
+}
+
+/// Sensor measurements of a log row, converted to seconds, rad/s, g and µT.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct LogSample<N: RealField> {
+    /// Timestamp, in seconds.
+    pub timestamp: N,
+    /// Angular rate, in rad/s.
+    pub gyroscope: Vector3<N>,
+    /// Acceleration, in g.
+    pub accelerometer: Vector3<N>,
+    /// Magnetic field, in µT, or `None` if the row has no magnetometer measurement.
+    pub magnetometer: Option<Vector3<N>>,
+}
+
+/// Reasons reading or replaying a sensor log can fail.
+#[derive(Debug)]
+pub enum LogError {
+    /// The log or output could not be read or written.
+    Io(io::Error),
+    /// The log is not valid CSV.
+    Csv(csv::Error),
+    /// The header has no column with this name.
+    MissingColumn(String),
+    /// A field on the given line is not a number.
+    InvalidValue { line: u64, column: String },
+    /// The binary log ends within the given record, counted from 1.
+    TruncatedRecord(u64),
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogError::Io(e) => write!(f, "{}", e),
+            LogError::Csv(e) => write!(f, "{}", e),
+            LogError::MissingColumn(column) => write!(f, "Log has no column '{}'.", column),
+            LogError::InvalidValue { line, column } => write!(f, "Invalid value in column '{}' on line {}.", column, line),
+            LogError::TruncatedRecord(record) => write!(f, "Log ends within record {}.", record),
+        }
+    }
+}
+
+impl Error for LogError {}
+
+impl From<io::Error> for LogError {
+    fn from(e: io::Error) -> Self {
+        LogError::Io(e)
+    }
+}
+
+impl From<csv::Error> for LogError {
+    fn from(e: csv::Error) -> Self {
+        LogError::Csv(e)
+    }
+}
+
+/// Streaming reader of a CSV sensor log with a header row, yielding one `LogSample` per row.
+pub struct CsvReader<R: Read, N: RealField> {
+    reader: csv::Reader<R>,
+    format: LogFormat<N>,
+    /// Indices of the timestamp, gyroscope, accelerometer and magnetometer columns.
+    indices: Vec<usize>,
+    record: csv::StringRecord,
+}
+
+impl<N: RealField> CsvReader<File, N> {
+    /// Opens the log at `path`.
+    ///
+    /// Returns an `Err(LogError)` if the file cannot be read or the header lacks a column of the
+    /// `format`.
+    pub fn from_path<P: AsRef<Path>>(path: P, format: LogFormat<N>) -> Result<Self, LogError> {
+        CsvReader::new(File::open(path)?, format)
+    }
+}
+
+impl<R: Read, N: RealField> CsvReader<R, N> {
+    /// Creates a new `CsvReader` of the log read from `reader`, laid out in `format`.
+    ///
+    /// Returns an `Err(LogError)` if the header cannot be read or lacks a column of the `format`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::replay::{CsvReader, GyroUnit, LogFormat};
+    ///
+    /// fn main() {
+    ///     let log = "timestamp,gx,gy,gz,ax,ay,az,mx,my,mz\n0.01,0,0,90,0,0,1,20,0,-40\n";
+    ///
+    ///     let format = LogFormat { gyro_unit: GyroUnit::DegreesPerSecond, ..LogFormat::<f64>::default() };
+    ///
+    ///     let samples: Vec<_> = CsvReader::new(log.as_bytes(), format).unwrap().collect();
+    ///     let sample = samples[0].as_ref().unwrap();
+    ///
+    ///     assert_eq!(sample.gyroscope[2], std::f64::consts::FRAC_PI_2);
+    /// }
+    /// ```
+    pub fn new(reader: R, format: LogFormat<N>) -> Result<Self, LogError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(format.delimiter)
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+
+        let headers = reader.headers()?.clone();
+        let index = |name: &String| {
+            headers.iter().position(|h| h == name).ok_or_else(|| LogError::MissingColumn(name.clone()))
+        };
+
+        let columns = &format.columns;
+        let mut indices = vec!(index(&columns.timestamp)?);
+        for name in columns.gyroscope.iter().chain(columns.accelerometer.iter()) {
+            indices.push(index(name)?);
+        }
+        if let Some(magnetometer) = &columns.magnetometer {
+            for name in magnetometer.iter() {
+                indices.push(index(name)?);
+            }
+        }
+
+        Ok(CsvReader { reader, format, indices, record: csv::StringRecord::new() })
+    }
+
+    /// Parses the field of the current record in column `n` of the mapping, or `None` if it is
+    /// empty.
+    fn field(&self, n: usize) -> Result<Option<N>, LogError> {
+        let text = self.record.get(self.indices[n]).unwrap_or("");
+        if text.is_empty() {
+            return Ok(None);
+        }
+
+        match text.parse::<f64>() {
+            Ok(value) => Ok(Some(na::convert(value))),
+            Err(_) => Err(self.invalid(n)),
+        }
+    }
+
+    /// Error for an invalid or missing value in column `n` of the mapping on the current line.
+    fn invalid(&self, n: usize) -> LogError {
+        LogError::InvalidValue {
+            line: self.record.position().map_or(0, |p| p.line()),
+            column: self.column_name(n),
+        }
+    }
+
+    /// Name of column `n` of the mapping.
+    fn column_name(&self, n: usize) -> String {
+        let columns = &self.format.columns;
+
+        match n {
+            0 => columns.timestamp.clone(),
+            1..=3 => columns.gyroscope[n - 1].clone(),
+            4..=6 => columns.accelerometer[n - 4].clone(),
+            _ => columns.magnetometer.as_ref().map_or_else(String::new, |m| m[n - 7].clone()),
+        }
+    }
+
+    /// Parses the vector in the three columns of the mapping starting at `n`, or `None` if any
+    /// of them is empty.
+    fn vector(&self, n: usize) -> Result<Option<Vector3<N>>, LogError> {
+        match (self.field(n)?, self.field(n + 1)?, self.field(n + 2)?) {
+            (Some(x), Some(y), Some(z)) => Ok(Some(Vector3::new(x, y, z))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Converts the current record to a sample.
+    fn sample(&self) -> Result<LogSample<N>, LogError> {
+        let timestamp = self.field(0)?.ok_or_else(|| self.invalid(0))?;
+        let gyroscope = self.vector(1)?.ok_or_else(|| self.invalid(1))?;
+        let accelerometer = self.vector(4)?.ok_or_else(|| self.invalid(4))?;
+        let magnetometer = if self.indices.len() > 7 { self.vector(7)? } else { None };
+
+        Ok(self.format.sample(timestamp, gyroscope, accelerometer, magnetometer))
+    }
+}
+
+impl<R: Read, N: RealField> Iterator for CsvReader<R, N> {
+    type Item = Result<LogSample<N>, LogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.read_record(&mut self.record) {
+            Ok(true) => Some(self.sample()),
+            Ok(false) => None,
+            Err(e) => Some(Err(LogError::from(e))),
+        }
+    }
+}
+
+/// Size of a binary log record, in bytes.
+pub const RECORD_SIZE: usize = 80;
+
+/// Streaming reader of a binary sensor log, yielding one `LogSample` per record.
+///
+/// Every record holds ten little-endian `f64` values: the timestamp, then the gyroscope,
+/// accelerometer and magnetometer x, y and z, in the units of the format. A record without
+/// magnetometer measurement has NaN magnetometer values. The columns and delimiter of the format
+/// are ignored, except that the magnetometer is skipped if it has no columns.
+pub struct BinaryReader<R: Read, N: RealField> {
+    reader: R,
+    format: LogFormat<N>,
+    /// Number of records read.
+    records: u64,
+}
+
+impl<N: RealField> BinaryReader<BufReader<File>, N> {
+    /// Opens the log at `path`.
+    ///
+    /// Returns an `Err(LogError)` if the file cannot be opened.
+    pub fn from_path<P: AsRef<Path>>(path: P, format: LogFormat<N>) -> Result<Self, LogError> {
+        Ok(BinaryReader::new(BufReader::new(File::open(path)?), format))
+    }
+}
+
+impl<R: Read, N: RealField> BinaryReader<R, N> {
+    /// Creates a new `BinaryReader` of the log read from `reader`, with the units of `format`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::replay::{BinaryReader, LogFormat};
+    ///
+    /// fn main() {
+    ///     let record = [0.01f64, 0.0, 0.0, 0.5, 0.0, 0.0, 1.0, 20.0, 0.0, -40.0];
+    ///     let log: Vec<u8> = record.iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+    ///
+    ///     let samples: Vec<_> = BinaryReader::new(log.as_slice(), LogFormat::<f64>::default()).collect();
+    ///     let sample = samples[0].as_ref().unwrap();
+    ///
+    ///     assert_eq!(sample.gyroscope[2], 0.5);
+    /// }
+    /// ```
+    pub fn new(reader: R, format: LogFormat<N>) -> Self {
+        BinaryReader { reader, format, records: 0 }
+    }
+
+    /// Reads the next record, or `None` at the end of the log.
+    fn record(&mut self) -> Result<Option<[f64; 10]>, LogError> {
+        let mut buffer = [0u8; RECORD_SIZE];
+        let mut filled = 0;
+
+        while filled < RECORD_SIZE {
+            match self.reader.read(&mut buffer[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(LogError::TruncatedRecord(self.records + 1)),
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(LogError::Io(e)),
+            }
+        }
+        self.records += 1;
+
+        let mut values = [0.0; 10];
+        for (value, bytes) in values.iter_mut().zip(buffer.chunks(8)) {
+            let mut le = [0u8; 8];
+            le.copy_from_slice(bytes);
+            *value = f64::from_le_bytes(le);
+        }
+
+        Ok(Some(values))
+    }
+}
+
+impl<R: Read, N: RealField> Iterator for BinaryReader<R, N> {
+    type Item = Result<LogSample<N>, LogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let v = match self.record() {
+            Ok(Some(values)) => values,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let vector = |n: usize| Vector3::new(na::convert(v[n]), na::convert(v[n + 1]), na::convert(v[n + 2]));
+        let magnetometer = if self.format.columns.magnetometer.is_some() && !v[7..].iter().any(|x| x.is_nan()) {
+            Some(vector(7))
+        } else {
+            None
+        };
+
+        Some(Ok(self.format.sample(na::convert(v[0]), vector(1), vector(4), magnetometer)))
+    }
+}
+
+/// Outcome of replaying a sensor log.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct ReplaySummary {
+    /// Rows read from the log.
+    pub samples: usize,
+    /// Rows whose filter update failed, which are left out of the output.
+    pub failed: usize,
+}
+
+/// Feeds every sample of a log, read by a `CsvReader` or `BinaryReader`, to an `ahrs` and writes
+/// the orientation after every update as CSV.
+///
+/// The first sample is fused with the sampling period of the filter, later samples with the
+/// time elapsed since the previous sample. Rows without magnetometer measurement are fused with
+/// `update_imu_dt`. The output has the columns `timestamp`, in seconds, `qw`, `qx`, `qy`, `qz`,
+/// and `roll`, `pitch` and `yaw` of the intrinsic z-y-x sequence, in degrees if `degrees` is
+/// true and radians otherwise.
+///
+/// Returns an `Err(LogError)` if reading or writing fails. Failed filter updates are counted
+/// in the summary instead.
+pub fn replay<N, A, I, W>(ahrs: &mut A, reader: I, writer: W, degrees: bool) -> Result<ReplaySummary, LogError>
+where
+    N: RealField,
+    A: Ahrs<N>,
+    I: IntoIterator<Item = Result<LogSample<N>, LogError>>,
+    W: Write,
+{
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["timestamp", "qw", "qx", "qy", "qz", "roll", "pitch", "yaw"])?;
+
+    let mut summary = ReplaySummary { samples: 0, failed: 0 };
+    let mut previous: Option<N> = None;
+
+    for sample in reader {
+        let sample = sample?;
+        summary.samples += 1;
+
+        let result = match (sample.magnetometer, previous) {
+            (Some(m), Some(p)) => ahrs.update_dt(&sample.gyroscope, &sample.accelerometer, &m, sample.timestamp - p),
+            (Some(m), None) => ahrs.update(&sample.gyroscope, &sample.accelerometer, &m),
+            (None, Some(p)) => ahrs.update_imu_dt(&sample.gyroscope, &sample.accelerometer, sample.timestamp - p),
+            (None, None) => ahrs.update_imu(&sample.gyroscope, &sample.accelerometer),
+        };
+        previous = Some(sample.timestamp);
+
+        let quat = match result {
+            Ok(quat) => *quat,
+            Err(_) => {
+                summary.failed += 1;
+                continue;
+            }
+        };
+
+        let mut euler = euler_angles(&quat, EulerSequence::ZYX);
+        if degrees {
+            euler = euler.map(|a| a * na::convert(180.0) / N::pi());
+        }
+
+        let values = [sample.timestamp, quat.w, quat.i, quat.j, quat.k, euler[2], euler[1], euler[0]];
+        writer.write_record(values.iter().map(|v| na::try_convert::<N, f64>(*v).unwrap_or(f64::NAN).to_string()))?;
+    }
+
+    writer.flush()?;
+
+    Ok(summary)
+}
+
+/// Replays the log at `input` through an `ahrs` and writes the orientations to `output`.
+///
+/// See `replay`.
+pub fn replay_csv<N, A, P, Q>(ahrs: &mut A, input: P, output: Q, format: LogFormat<N>, degrees: bool) -> Result<ReplaySummary, LogError>
+where
+    N: RealField,
+    A: Ahrs<N>,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let reader = CsvReader::from_path(input, format)?;
+    replay(ahrs, reader, File::create(output)?, degrees)
+}
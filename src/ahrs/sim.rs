@@ -0,0 +1,374 @@
+extern crate nalgebra as na;
+extern crate alga;
+extern crate rand;
+
+use alga::general::RealField;
+use na::{Quaternion, UnitQuaternion, Vector3};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Motion of the body during a trajectory segment.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Motion<N: RealField> {
+    /// Constant body frame angular rate, in rad/s, and Earth frame linear acceleration, in the
+    /// unit of gravity.
+    Constant {
+        rate: Vector3<N>,
+        acceleration: Vector3<N>,
+    },
+    /// Body frame angular rate and Earth frame linear acceleration oscillating with `frequency`,
+    /// in Hz, around zero with the given amplitudes.
+    Sinusoidal {
+        rate: Vector3<N>,
+        acceleration: Vector3<N>,
+        frequency: N,
+    },
+}
+
+/// Part of a trajectory with a single kind of motion.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Segment<N: RealField> {
+    /// Duration of the segment, in seconds.
+    pub duration: N,
+    /// Motion during the segment.
+    pub motion: Motion<N>,
+}
+
+impl<N: RealField> Segment<N> {
+    /// Segment holding the body still for `duration` seconds.
+    pub fn still(duration: N) -> Self {
+        Segment::constant(duration, Vector3::zeros(), Vector3::zeros())
+    }
+
+    /// Segment rotating the body at a constant `rate`, in rad/s, while accelerating it with
+    /// `acceleration` in the Earth frame, for `duration` seconds.
+    pub fn constant(duration: N, rate: Vector3<N>, acceleration: Vector3<N>) -> Self {
+        Segment { duration, motion: Motion::Constant { rate, acceleration } }
+    }
+
+    /// Segment oscillating the angular `rate` and Earth frame `acceleration` with `frequency`, in
+    /// Hz, for `duration` seconds.
+    pub fn sinusoidal(duration: N, rate: Vector3<N>, acceleration: Vector3<N>, frequency: N) -> Self {
+        Segment { duration, motion: Motion::Sinusoidal { rate, acceleration, frequency } }
+    }
+
+    /// Angular rate and linear acceleration `t` seconds into the segment.
+    fn motion_at(&self, t: N) -> (Vector3<N>, Vector3<N>) {
+        match self.motion {
+            Motion::Constant { rate, acceleration } => (rate, acceleration),
+            Motion::Sinusoidal { rate, acceleration, frequency } => {
+                let s = (N::two_pi() * frequency * t).sin();
+                (rate * s, acceleration * s)
+            },
+        }
+    }
+}
+
+/// Ground truth and ideal sensor measurements of a trajectory sample.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct TruthSample<N: RealField> {
+    /// Time since the start of the trajectory, in seconds.
+    pub time: N,
+    /// Orientation from the body frame to the north-west-up Earth frame.
+    pub quat: Quaternion<N>,
+    /// Linear acceleration in the Earth frame, in the unit of gravity.
+    pub acceleration: Vector3<N>,
+    /// Ideal gyroscope measurement, in rad/s.
+    pub gyroscope: Vector3<N>,
+    /// Ideal accelerometer measurement, in the unit of gravity.
+    pub accelerometer: Vector3<N>,
+    /// Ideal magnetometer measurement, in the unit of the field.
+    pub magnetometer: Vector3<N>,
+}
+
+/// Orientation trajectory built from consecutive segments, sampled at a fixed rate.
+#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Trajectory<N: RealField> {
+    /// Orientation at the start of the trajectory, relative to north-west-up.
+    pub initial: UnitQuaternion<N>,
+    /// Sampling period, in seconds.
+    pub sample_period: N,
+    /// Magnitude of gravity.
+    pub gravity: N,
+    /// Magnetic field in the Earth frame.
+    pub field: Vector3<N>,
+    /// Segments of the trajectory, in order.
+    pub segments: Vec<Segment<N>>,
+}
+
+impl<N: RealField> Trajectory<N> {
+    /// Creates a new `Trajectory` without segments, with gravity of 1 and a unit magnetic field
+    /// pointing north with a dip of about 53°.
+    ///
+    /// # Arguments
+    ///
+    /// * `initial` - Orientation at the start of the trajectory.
+    /// * `sample_period` - Sampling period, in seconds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    ///
+    /// use na::{UnitQuaternion, Vector3};
+    /// use lobster_rust::ahrs::sim::{Segment, Trajectory};
+    ///
+    /// fn main() {
+    ///     let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+    ///     trajectory.push(Segment::still(1.0));
+    ///     trajectory.push(Segment::constant(2.0, Vector3::new(0.0, 0.0, 0.5), Vector3::zeros()));
+    ///
+    ///     let truth = trajectory.sample();
+    /// }
+    /// ```
+    pub fn new(initial: UnitQuaternion<N>, sample_period: N) -> Self {
+        Trajectory {
+            initial,
+            sample_period,
+            gravity: N::one(),
+            field: Vector3::new(na::convert(0.6), N::zero(), na::convert(-0.8)),
+            segments: Vec::new(),
+        }
+    }
+
+    /// Appends a `segment` to the trajectory.
+    pub fn push(&mut self, segment: Segment<N>) {
+        self.segments.push(segment);
+    }
+
+    /// Total duration of the trajectory, in seconds.
+    pub fn duration(&self) -> N {
+        self.segments.iter().fold(N::zero(), |sum, s| sum + s.duration)
+    }
+
+    /// Angular rate and linear acceleration at time `t`, holding still beyond the last segment.
+    fn motion_at(&self, t: N) -> (Vector3<N>, Vector3<N>) {
+        let mut start = N::zero();
+
+        for segment in &self.segments {
+            if t < start + segment.duration {
+                return segment.motion_at(t - start);
+            }
+            start += segment.duration;
+        }
+
+        (Vector3::zeros(), Vector3::zeros())
+    }
+
+    /// Samples the trajectory every sampling period over its duration.
+    ///
+    /// The orientation is integrated with the angular rate halfway through every sampling
+    /// period, so it is exact for constant rates.
+    pub fn sample(&self) -> Vec<TruthSample<N>> {
+        let dt = self.sample_period;
+        let half: N = na::convert(0.5);
+
+        let count = (self.duration() / dt + half).floor();
+        let count = na::try_convert::<N, f64>(count).unwrap_or(0.0).max(0.0) as usize;
+
+        let up = Vector3::z() * self.gravity;
+        let mut quat = self.initial;
+
+        (0..count).map(|k| {
+            let t = dt * na::convert(k as f64);
+            let (rate, acceleration) = self.motion_at(t);
+
+            let sample = TruthSample {
+                time: t,
+                quat: quat.into_inner(),
+                acceleration,
+                gyroscope: rate,
+                accelerometer: quat.inverse() * (acceleration + up),
+                magnetometer: quat.inverse() * self.field,
+            };
+
+            let (mid_rate, _) = self.motion_at(t + dt * half);
+            quat *= UnitQuaternion::from_scaled_axis(mid_rate * dt);
+
+            sample
+        }).collect()
+    }
+}
+
+/// Error model of a three-axis sensor.
+///
+/// Every component defaults to zero, which disables it. A measurement is the ideal value scaled
+/// per axis by `1 + scale_error`, offset by the bias terms, with white noise added, then
+/// quantized and clipped to the saturation limit.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct SensorModel<N: RealField> {
+    /// Standard deviation of the white noise of every sample.
+    pub white_noise: N,
+    /// Constant bias.
+    pub bias: Vector3<N>,
+    /// Standard deviation of the bias instability, modeled as a first order Gauss-Markov process.
+    pub bias_instability: N,
+    /// Correlation time of the bias instability, in seconds.
+    pub bias_correlation_time: N,
+    /// Random walk of the bias, per √s.
+    pub random_walk: N,
+    /// Relative scale factor error of every axis.
+    pub scale_error: Vector3<N>,
+    /// Quantization step.
+    pub quantization: N,
+    /// Largest measurable magnitude of every axis.
+    pub saturation: N,
+}
+
+impl<N: RealField> SensorModel<N> {
+    /// Sensor without errors.
+    pub fn ideal() -> Self {
+        SensorModel {
+            white_noise: N::zero(),
+            bias: Vector3::zeros(),
+            bias_instability: N::zero(),
+            bias_correlation_time: na::convert(100.0),
+            random_walk: N::zero(),
+            scale_error: Vector3::zeros(),
+            quantization: N::zero(),
+            saturation: N::zero(),
+        }
+    }
+
+    /// Corrupts an `ideal` measurement, advancing the time-varying bias terms in `state` over `dt`.
+    fn corrupt<R: Rng>(&self, ideal: &Vector3<N>, state: &mut SensorState<N>, dt: N, rng: &mut R) -> Vector3<N> {
+        let zero = N::zero();
+        let two: N = na::convert(2.0);
+
+        // First order Gauss-Markov bias instability, stationary with the configured deviation
+        if self.bias_instability > zero && self.bias_correlation_time > zero {
+            let decay = (-dt / self.bias_correlation_time).exp();
+            let drive = self.bias_instability * (N::one() - (-two * dt / self.bias_correlation_time).exp()).sqrt();
+            state.instability = state.instability * decay + gaussian(rng) * drive;
+        }
+
+        if self.random_walk > zero {
+            state.random_walk += gaussian(rng) * (self.random_walk * dt.sqrt());
+        }
+
+        let mut measurement = ideal.component_mul(&(self.scale_error + Vector3::repeat(N::one())))
+            + self.bias
+            + state.instability
+            + state.random_walk;
+
+        if self.white_noise > zero {
+            measurement += gaussian(rng) * self.white_noise;
+        }
+
+        if self.quantization > zero {
+            measurement = measurement.map(|x| (x / self.quantization).round() * self.quantization);
+        }
+
+        if self.saturation > zero {
+            measurement = measurement.map(|x| x.max(-self.saturation).min(self.saturation));
+        }
+
+        measurement
+    }
+}
+
+impl<N: RealField> Default for SensorModel<N> {
+    /// Sensor without errors.
+    fn default() -> Self {
+        SensorModel::ideal()
+    }
+}
+
+/// Time-varying bias terms of a simulated sensor.
+#[derive(Clone, Debug, Copy)]
+struct SensorState<N: RealField> {
+    instability: Vector3<N>,
+    random_walk: Vector3<N>,
+}
+
+impl<N: RealField> SensorState<N> {
+    fn new() -> Self {
+        SensorState { instability: Vector3::zeros(), random_walk: Vector3::zeros() }
+    }
+}
+
+/// Error models of the sensors of an IMU.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct ImuModel<N: RealField> {
+    /// Gyroscope errors, in rad/s.
+    pub gyroscope: SensorModel<N>,
+    /// Accelerometer errors, in the unit of gravity.
+    pub accelerometer: SensorModel<N>,
+    /// Magnetometer errors, in the unit of the field.
+    pub magnetometer: SensorModel<N>,
+}
+
+/// Ground truth and corrupted measurements of a simulated trajectory.
+#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+pub struct Simulation<N: RealField> {
+    /// Ground truth and ideal measurements of every sample.
+    pub truth: Vec<TruthSample<N>>,
+    /// Corrupted gyroscope measurements.
+    pub gyroscope: Vec<Vector3<N>>,
+    /// Corrupted accelerometer measurements.
+    pub accelerometer: Vec<Vector3<N>>,
+    /// Corrupted magnetometer measurements.
+    pub magnetometer: Vec<Vector3<N>>,
+}
+
+/// Samples a `trajectory` and corrupts the ideal measurements with the errors of `model`.
+///
+/// The noise is drawn from a random number generator seeded with `seed`, so a simulation is
+/// reproducible.
+///
+/// # Example
+///
+/// ```
+/// extern crate nalgebra as na;
+///
+/// use na::{UnitQuaternion, Vector3};
+/// use lobster_rust::ahrs::sim::{simulate, ImuModel, Segment, Trajectory};
+///
+/// fn main() {
+///     let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+///     trajectory.push(Segment::sinusoidal(5.0, Vector3::new(0.5, 0.0, 1.0), Vector3::zeros(), 0.5));
+///
+///     let mut model = ImuModel::default();
+///     model.gyroscope.white_noise = 0.01;
+///
+///     let sim = simulate(&trajectory, &model, 42);
+/// }
+/// ```
+pub fn simulate<N: RealField>(trajectory: &Trajectory<N>, model: &ImuModel<N>, seed: u64) -> Simulation<N> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let dt = trajectory.sample_period;
+
+    let truth = trajectory.sample();
+
+    let mut states = [SensorState::new(); 3];
+    let mut gyroscope = Vec::with_capacity(truth.len());
+    let mut accelerometer = Vec::with_capacity(truth.len());
+    let mut magnetometer = Vec::with_capacity(truth.len());
+
+    for sample in &truth {
+        gyroscope.push(model.gyroscope.corrupt(&sample.gyroscope, &mut states[0], dt, &mut rng));
+        accelerometer.push(model.accelerometer.corrupt(&sample.accelerometer, &mut states[1], dt, &mut rng));
+        magnetometer.push(model.magnetometer.corrupt(&sample.magnetometer, &mut states[2], dt, &mut rng));
+    }
+
+    Simulation { truth, gyroscope, accelerometer, magnetometer }
+}
+
+/// Draws a vector of independent standard normal samples with the Box-Muller transform.
+fn gaussian<N: RealField, R: Rng>(rng: &mut R) -> Vector3<N> {
+    let mut normal = || {
+        let u: f64 = 1.0 - rng.gen::<f64>();
+        let v: f64 = rng.gen();
+        na::convert::<f64, N>((-2.0 * u.ln()).sqrt() * (std::f64::consts::PI * 2.0 * v).cos())
+    };
+
+    Vector3::new(normal(), normal(), normal())
+}
@@ -0,0 +1,211 @@
+extern crate nalgebra as na;
+extern crate alga;
+
+use alga::general::RealField;
+use na::{MatrixMN, Vector3, U3, U64};
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Stationary detector based on the generalized likelihood ratio test of Skog et al., "Zero-Velocity
+/// Detection — An Algorithm Evaluation".
+///
+/// Over the last `window` samples the test statistic is
+///
+/// `T = 1/W Σ |a - g ā/|ā||² / σa² + |ω|² / σω²`
+///
+/// where `a` and `ω` are the accelerometer and gyroscope measurements and `ā` is the mean
+/// accelerometer measurement of the window. The sensor is stationary while `T` is below the
+/// threshold. For a stationary sensor whose noise matches `σa` and `σω`, `T` is about 6.
+///
+/// The window is stored inline, so the detector is `Copy` and can be held by `Copy` filters.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct StationaryDetector<N: RealField> {
+    /// Number of samples in the test window.
+    window: usize,
+    /// Accelerometer noise standard deviation, in accelerometer units.
+    accel_noise: N,
+    /// Gyroscope noise standard deviation, in rad/s.
+    gyro_noise: N,
+    /// Magnitude of gravity, in accelerometer units.
+    gravity: N,
+    /// Test statistic below which the sensor is stationary.
+    threshold: N,
+    /// Gyroscope measurements of the window, one per column of a ring buffer.
+    gyroscope: Window<N>,
+    /// Accelerometer measurements of the window, one per column of a ring buffer.
+    accelerometer: Window<N>,
+    /// Number of measurements in the window.
+    len: usize,
+    /// Column receiving the next measurement.
+    next: usize,
+    /// Test statistic after the last sample.
+    statistic: N,
+}
+
+/// Largest supported test window, in samples.
+pub const MAX_WINDOW: usize = 64;
+
+/// Measurements of a test window of up to `MAX_WINDOW` samples.
+type Window<N> = MatrixMN<N, U3, U64>;
+
+impl<N: RealField> StationaryDetector<N> {
+    /// Creates a new `StationaryDetector`.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - Number of samples in the test window, clamped to between 1 and `MAX_WINDOW`.
+    /// * `accel_noise` - Accelerometer noise standard deviation, in accelerometer units.
+    /// * `gyro_noise` - Gyroscope noise standard deviation, in rad/s.
+    /// * `gravity` - Magnitude of gravity, in accelerometer units.
+    /// * `threshold` - Test statistic below which the sensor is stationary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lobster_rust::ahrs::stationary::StationaryDetector;
+    ///
+    /// fn main() {
+    ///     let detector = StationaryDetector::new(32, 0.01f64, 0.005, 1.0, 30.0);
+    /// }
+    /// ```
+    pub fn new(window: usize, accel_noise: N, gyro_noise: N, gravity: N, threshold: N) -> Self {
+        let window = window.clamp(1, MAX_WINDOW);
+
+        StationaryDetector {
+            window,
+            accel_noise,
+            gyro_noise,
+            gravity,
+            threshold,
+            gyroscope: Window::zeros(),
+            accelerometer: Window::zeros(),
+            len: 0,
+            next: 0,
+            statistic: N::zero(),
+        }
+    }
+
+    /// Adds a `gyroscope` and `accelerometer` measurement to the test window.
+    ///
+    /// Returns whether the sensor is stationary, which is never the case before the window is
+    /// full.
+    pub fn update(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>) -> bool {
+        let (statistic, stationary) = self.test(gyroscope, accelerometer);
+        self.push(gyroscope, accelerometer, statistic);
+        stationary
+    }
+
+    /// Runs the test on the window with a `gyroscope` and `accelerometer` measurement added,
+    /// without adding it.
+    ///
+    /// Returns the test statistic and whether the sensor is stationary.
+    pub(crate) fn test(&self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>) -> (N, bool) {
+        let len = (self.len + 1).min(self.window);
+        let count: N = na::convert(len as f64);
+
+        // The new measurement takes the place of the oldest once the window is full
+        let sample = |n: usize| if n == self.next {
+            (*gyroscope, *accelerometer)
+        } else {
+            (self.gyroscope.column(n).into_owned(), self.accelerometer.column(n).into_owned())
+        };
+
+        // Gravity points along the mean accelerometer measurement of the window
+        let mean = (0..len).fold(Vector3::zeros(), |sum, n| sum + sample(n).1) / count;
+        let gravity = mean.try_normalize(N::zero()).unwrap_or_else(Vector3::zeros) * self.gravity;
+
+        let accel_variance = self.accel_noise * self.accel_noise;
+        let gyro_variance = self.gyro_noise * self.gyro_noise;
+
+        let statistic = (0..len).fold(N::zero(), |sum, n| {
+            let (g, a) = sample(n);
+            sum + (a - gravity).norm_squared() / accel_variance + g.norm_squared() / gyro_variance
+        }) / count;
+
+        (statistic, len == self.window && statistic < self.threshold)
+    }
+
+    /// Adds a `gyroscope` and `accelerometer` measurement with the `statistic` found by `test`.
+    pub(crate) fn push(&mut self, gyroscope: &Vector3<N>, accelerometer: &Vector3<N>, statistic: N) {
+        self.gyroscope.set_column(self.next, gyroscope);
+        self.accelerometer.set_column(self.next, accelerometer);
+        self.len = (self.len + 1).min(self.window);
+        self.next = (self.next + 1) % self.window;
+        self.statistic = statistic;
+    }
+
+    /// Whether the sensor was stationary at the last sample.
+    pub fn is_stationary(&self) -> bool {
+        self.len == self.window && self.statistic < self.threshold
+    }
+
+    /// Test statistic after the last sample.
+    pub fn statistic(&self) -> N {
+        self.statistic
+    }
+
+    /// Number of samples in the test window.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Test statistic below which the sensor is stationary.
+    pub fn threshold(&self) -> N {
+        self.threshold
+    }
+
+    /// Forgets every sample seen so far.
+    pub fn reset(&mut self) {
+        self.gyroscope = Window::zeros();
+        self.accelerometer = Window::zeros();
+        self.len = 0;
+        self.next = 0;
+        self.statistic = N::zero();
+    }
+
+    /// Runs the test over a recording of `gyroscope` and `accelerometer` measurements, continuing
+    /// from the current state.
+    ///
+    /// Returns whether the sensor is stationary at every sample. Extra samples of the longer
+    /// recording are ignored.
+    pub fn label(&mut self, gyroscope: &[Vector3<N>], accelerometer: &[Vector3<N>]) -> Vec<bool> {
+        gyroscope.iter().zip(accelerometer).map(|(g, a)| self.update(g, a)).collect()
+    }
+}
+
+/// Converts per-sample stationary `labels` into stationary intervals.
+///
+/// Returns the `(start, end)` sample range of every run of stationary samples, with `end`
+/// exclusive.
+///
+/// # Example
+///
+/// ```
+/// use lobster_rust::ahrs::stationary::intervals;
+///
+/// fn main() {
+///     assert_eq!(intervals(&[true, true, false, true]), vec!((0, 2), (3, 4)));
+/// }
+/// ```
+pub fn intervals(labels: &[bool]) -> Vec<(usize, usize)> {
+    let mut intervals = Vec::new();
+    let mut start = None;
+
+    for (n, stationary) in labels.iter().enumerate() {
+        match (start, *stationary) {
+            (None, true) => start = Some(n),
+            (Some(s), false) => {
+                intervals.push((s, n));
+                start = None;
+            },
+            _ => (),
+        }
+    }
+
+    if let Some(s) = start {
+        intervals.push((s, labels.len()));
+    }
+
+    intervals
+}
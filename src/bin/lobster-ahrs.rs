@@ -0,0 +1,446 @@
+//! Replays a recorded sensor log through an AHRS filter and writes the fused orientations as CSV.
+//!
+//! ```text
+//! lobster-ahrs recording.csv --filter madgwick --beta 0.05 --gyro-unit deg/s -o orientation.csv
+//! lobster-ahrs recording.bin --config field.toml > orientation.csv
+//! ```
+//!
+//! Settings are read from an optional TOML config and overridden by flags:
+//!
+//! ```toml
+//! filter = "mahony"
+//! sample_period = 0.01
+//! degrees = true
+//!
+//! [mahony]
+//! kp = 0.5
+//! ki = 0.01
+//!
+//! [log]
+//! format = "csv"
+//! timestamp = "time_ms"
+//! gyroscope = ["wx", "wy", "wz"]
+//! time_scale = 0.001
+//! gyro_unit = "deg/s"
+//! mag_unit = "gauss"
+//!
+//! [calibration]
+//! gyro_bias = [0.01, -0.02, 0.005]
+//! mag_hard_iron = [12.0, -30.0, 4.5]
+//! mag_soft_iron = [[0.83, 0.0, 0.0], [0.0, 1.11, 0.0], [0.0, 0.0, 1.0]]
+//! ```
+//!
+//! Logs ending in `.bin` are read as binary logs of little-endian `f64` records, see
+//! `BinaryReader`, and other logs as CSV with a header row.
+
+extern crate clap;
+extern crate serde;
+extern crate toml;
+
+use clap::{App, Arg, ArgMatches};
+use nalgebra::{Matrix3, Vector3};
+use serde::Deserialize;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::process;
+use std::str::FromStr;
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::calibration::{AccelCalibration, Calibrated, GyroCalibration, MagCalibration};
+use lobster_rust::ahrs::ekf::Ekf;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use lobster_rust::ahrs::mahony_filter::Mahony;
+use lobster_rust::ahrs::replay::{replay, BinaryReader, Columns, CsvReader, LogFormat, ReplaySummary};
+
+/// Settings of a TOML config. Missing settings fall back to flags or defaults.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    filter: Option<String>,
+    sample_period: Option<f64>,
+    degrees: Option<bool>,
+    madgwick: MadgwickConfig,
+    mahony: MahonyConfig,
+    ekf: EkfConfig,
+    log: LogConfig,
+    calibration: CalibrationConfig,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct MadgwickConfig {
+    beta: Option<f64>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct MahonyConfig {
+    kp: Option<f64>,
+    ki: Option<f64>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct EkfConfig {
+    gyro_noise: Option<f64>,
+    gyro_bias_noise: Option<f64>,
+    accel_noise: Option<f64>,
+    mag_noise: Option<f64>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct LogConfig {
+    format: Option<String>,
+    timestamp: Option<String>,
+    gyroscope: Option<Vec<String>>,
+    accelerometer: Option<Vec<String>>,
+    magnetometer: Option<Vec<String>>,
+    imu_only: Option<bool>,
+    time_scale: Option<f64>,
+    gyro_unit: Option<String>,
+    accel_unit: Option<String>,
+    mag_unit: Option<String>,
+    delimiter: Option<String>,
+}
+
+/// Sensor calibrations applied to the log before the filter, in rad/s, g and µT. Matrices are
+/// given as rows and default to identity when only the offset is set.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct CalibrationConfig {
+    gyro_bias: Option<[f64; 3]>,
+    accel_bias: Option<[f64; 3]>,
+    accel_sensitivity: Option<[[f64; 3]; 3]>,
+    mag_hard_iron: Option<[f64; 3]>,
+    mag_soft_iron: Option<[[f64; 3]; 3]>,
+}
+
+/// Encoding of a sensor log.
+#[derive(Eq, PartialEq, Clone, Debug, Copy)]
+enum Encoding {
+    Csv,
+    Binary,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Encoding::Csv),
+            "binary" => Ok(Encoding::Binary),
+            _ => Err(format!("Unknown log format '{}', expected csv or binary.", s)),
+        }
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    let value = |name: &'static str, help: &'static str| Arg::with_name(name).long(name).takes_value(true).help(help);
+
+    App::new("lobster-ahrs")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Replays a recorded sensor log through an AHRS filter and writes the orientations as CSV.")
+        .arg(Arg::with_name("input").required(true).help("Sensor log to replay, or - to read standard input"))
+        .arg(value("output", "Output CSV, written to standard output if omitted").short("o"))
+        .arg(value("config", "TOML config, overridden by flags").short("c"))
+        .arg(value("filter", "Filter to run: madgwick (default), mahony or ekf").short("f"))
+        .arg(value("sample-period", "Sampling period of the first sample, in seconds [default: 0.01]"))
+        .arg(value("beta", "Madgwick filter gain [default: 0.1]"))
+        .arg(value("kp", "Mahony proportional gain [default: 1.0]"))
+        .arg(value("ki", "Mahony integral gain [default: 0.0]"))
+        .arg(value("gyro-noise", "EKF gyroscope noise density, in rad/s/√Hz [default: 0.005]"))
+        .arg(value("gyro-bias-noise", "EKF gyroscope bias random walk density, in rad/s²/√Hz [default: 0.0001]"))
+        .arg(value("accel-noise", "EKF normalized accelerometer noise density [default: 0.01]"))
+        .arg(value("mag-noise", "EKF normalized magnetometer noise density [default: 0.01]"))
+        .arg(value("format", "Log format: csv or binary [default: binary for .bin logs, csv otherwise]"))
+        .arg(value("timestamp", "Timestamp column [default: timestamp]"))
+        .arg(value("gyroscope", "Comma separated gyroscope columns [default: gx,gy,gz]"))
+        .arg(value("accelerometer", "Comma separated accelerometer columns [default: ax,ay,az]"))
+        .arg(value("magnetometer", "Comma separated magnetometer columns [default: mx,my,mz]"))
+        .arg(value("time-scale", "Seconds per timestamp unit, 0.001 for milliseconds [default: 1]"))
+        .arg(value("gyro-unit", "Gyroscope unit: rad/s or deg/s [default: rad/s]"))
+        .arg(value("accel-unit", "Accelerometer unit: g or m/s^2 [default: g]"))
+        .arg(value("mag-unit", "Magnetometer unit: uT or gauss [default: uT]"))
+        .arg(value("delimiter", "CSV field delimiter [default: ,]"))
+        .arg(Arg::with_name("imu-only").long("imu-only").help("Ignores the magnetometer"))
+        .arg(Arg::with_name("degrees").long("degrees").help("Writes Euler angles in degrees instead of radians"))
+}
+
+/// Value of the flag `name` if given, the `config` value otherwise, or the `default`.
+fn setting<T: FromStr>(matches: &ArgMatches, name: &str, config: Option<T>, default: T) -> Result<T, String> {
+    match matches.value_of(name) {
+        Some(value) => value.parse().map_err(|_| format!("Invalid value '{}' for --{}.", value, name)),
+        None => Ok(config.unwrap_or(default)),
+    }
+}
+
+/// Unit of the flag `name` if given, the `config` value otherwise, or the `default`.
+fn unit<T: FromStr>(matches: &ArgMatches, name: &str, config: Option<String>, default: &str) -> Result<T, String> {
+    let value = setting(matches, name, config, default.to_string())?;
+    value.parse().map_err(|_| format!("Unknown unit '{}' for {}.", value, name))
+}
+
+/// Column names of the flag `name` if given, the `config` value otherwise, or the `default`.
+fn columns(matches: &ArgMatches, name: &str, config: Option<Vec<String>>, default: [String; 3]) -> Result<[String; 3], String> {
+    let names = match matches.value_of(name) {
+        Some(value) => value.split(',').map(|c| c.trim().to_string()).collect(),
+        None => match config {
+            Some(names) => names,
+            None => return Ok(default),
+        },
+    };
+
+    match names.as_slice() {
+        [x, y, z] => Ok([x.clone(), y.clone(), z.clone()]),
+        _ => Err(format!("The {} setting must name 3 columns.", name)),
+    }
+}
+
+/// Log format from the flags and `config`.
+fn log_format(matches: &ArgMatches, config: LogConfig) -> Result<LogFormat<f64>, String> {
+    let defaults = Columns::default();
+
+    let imu_only = matches.is_present("imu-only") || config.imu_only.unwrap_or(false);
+    let magnetometer = match defaults.magnetometer {
+        Some(default) if !imu_only => Some(columns(matches, "magnetometer", config.magnetometer, default)?),
+        _ => None,
+    };
+
+    let delimiter = setting(matches, "delimiter", config.delimiter, ",".to_string())?;
+    if delimiter.len() != 1 {
+        return Err("The delimiter must be a single character.".to_string());
+    }
+
+    Ok(LogFormat {
+        columns: Columns {
+            timestamp: setting(matches, "timestamp", config.timestamp, defaults.timestamp)?,
+            gyroscope: columns(matches, "gyroscope", config.gyroscope, defaults.gyroscope)?,
+            accelerometer: columns(matches, "accelerometer", config.accelerometer, defaults.accelerometer)?,
+            magnetometer,
+        },
+        time_scale: setting(matches, "time-scale", config.time_scale, 1.0)?,
+        gyro_unit: unit(matches, "gyro-unit", config.gyro_unit, "rad/s")?,
+        accel_unit: unit(matches, "accel-unit", config.accel_unit, "g")?,
+        mag_unit: unit(matches, "mag-unit", config.mag_unit, "uT")?,
+        delimiter: delimiter.as_bytes()[0],
+    })
+}
+
+/// `ahrs` with the calibrations of `config` applied before every update.
+fn calibrate<A: Ahrs<f64>>(ahrs: A, config: &CalibrationConfig) -> Result<Calibrated<f64, A>, String> {
+    let vector = |v: Option<[f64; 3]>| v.map_or_else(Vector3::zeros, Vector3::from);
+    let matrix = |m: Option<[[f64; 3]; 3]>| m.map_or_else(Matrix3::identity, |rows| Matrix3::from_fn(|r, c| rows[r][c]));
+
+    let mut calibrated = Calibrated::new(ahrs);
+
+    if let Some(bias) = config.gyro_bias {
+        calibrated.set_gyro_calibration(Some(GyroCalibration::new(Vector3::from(bias))));
+    }
+
+    if config.accel_bias.is_some() || config.accel_sensitivity.is_some() {
+        let cal = AccelCalibration::new(vector(config.accel_bias), matrix(config.accel_sensitivity), 1.0)
+            .map_err(|_| "The accel_sensitivity calibration matrix must be invertible.".to_string())?;
+        calibrated.set_accel_calibration(Some(cal));
+    }
+
+    if config.mag_hard_iron.is_some() || config.mag_soft_iron.is_some() {
+        let cal = MagCalibration::new(vector(config.mag_hard_iron), matrix(config.mag_soft_iron));
+        calibrated.set_mag_calibration(Some(cal));
+    }
+
+    Ok(calibrated)
+}
+
+/// Log to replay and where to write the orientations.
+struct Job {
+    input: String,
+    output: Option<String>,
+    encoding: Encoding,
+    format: LogFormat<f64>,
+    calibration: CalibrationConfig,
+    degrees: bool,
+}
+
+impl Job {
+    /// Replays the log through an `ahrs` with the calibrations of the job, reading standard input
+    /// if the input is `-` and writing standard output if there is no output.
+    fn run<A: Ahrs<f64>>(self, ahrs: A) -> Result<ReplaySummary, Box<dyn Error>> {
+        let mut ahrs = calibrate(ahrs, &self.calibration)?;
+
+        let input: Box<dyn Read> = match self.input.as_str() {
+            "-" => Box::new(io::stdin()),
+            path => Box::new(File::open(path)?),
+        };
+        let output: Box<dyn Write> = match self.output {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stdout()),
+        };
+
+        let summary = match self.encoding {
+            Encoding::Csv => replay(&mut ahrs, CsvReader::new(input, self.format)?, output, self.degrees)?,
+            Encoding::Binary => replay(&mut ahrs, BinaryReader::new(BufReader::new(input), self.format), output, self.degrees)?,
+        };
+
+        Ok(summary)
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<ReplaySummary, Box<dyn Error>> {
+    let config: Config = match matches.value_of("config") {
+        Some(path) => toml::from_str(&fs::read_to_string(path)?)?,
+        None => Config::default(),
+    };
+
+    let input = matches.value_of("input").unwrap_or("-").to_string();
+    let encoding = if input.ends_with(".bin") { "binary" } else { "csv" };
+    let encoding: Encoding = setting(matches, "format", config.log.format.clone(), encoding.to_string())?.parse()?;
+
+    let job = Job {
+        input,
+        output: matches.value_of("output").map(String::from),
+        encoding,
+        format: log_format(matches, config.log)?,
+        calibration: config.calibration,
+        degrees: matches.is_present("degrees") || config.degrees.unwrap_or(false),
+    };
+
+    let sample_period = setting(matches, "sample-period", config.sample_period, 0.01)?;
+    let filter = setting(matches, "filter", config.filter, "madgwick".to_string())?;
+
+    match filter.as_str() {
+        "madgwick" => {
+            let beta = setting(matches, "beta", config.madgwick.beta, 0.1)?;
+            job.run(Madgwick::new(sample_period, beta))
+        }
+        "mahony" => {
+            let kp = setting(matches, "kp", config.mahony.kp, 1.0)?;
+            let ki = setting(matches, "ki", config.mahony.ki, 0.0)?;
+            job.run(Mahony::new(sample_period, kp, ki))
+        }
+        "ekf" => {
+            let ekf = config.ekf;
+            job.run(Ekf::new(
+                sample_period,
+                setting(matches, "gyro-noise", ekf.gyro_noise, 0.005)?,
+                setting(matches, "gyro-bias-noise", ekf.gyro_bias_noise, 0.0001)?,
+                setting(matches, "accel-noise", ekf.accel_noise, 0.01)?,
+                setting(matches, "mag-noise", ekf.mag_noise, 0.01)?,
+            ))
+        }
+        _ => Err(format!("Unknown filter '{}', expected madgwick, mahony or ekf.", filter).into()),
+    }
+}
+
+fn main() {
+    let matches = app().get_matches();
+
+    match run(&matches) {
+        Ok(summary) if summary.failed > 0 => {
+            eprintln!("lobster-ahrs: {} of {} updates failed and were skipped.", summary.failed, summary.samples);
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("lobster-ahrs: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{app, calibrate, log_format, setting, Config};
+    use lobster_rust::ahrs::acceleration::AccelUnit;
+    use lobster_rust::ahrs::calibration::{AccelCalibration, GyroCalibration, MagCalibration};
+    use lobster_rust::ahrs::mahony_filter::Mahony;
+    use nalgebra::{Matrix3, Vector3};
+    use lobster_rust::ahrs::replay::{Columns, GyroUnit, LogFormat, MagUnit};
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_setting_precedence() {
+        let config = config("sample_period = 0.02\n[madgwick]\nbeta = 0.3");
+        let flags = app().get_matches_from(vec!["lobster-ahrs", "log.csv", "--beta", "0.2"]);
+
+        // Flags override the config, which overrides the default
+        assert_eq!(setting(&flags, "beta", config.madgwick.beta, 0.1), Ok(0.2));
+        assert_eq!(setting(&flags, "sample-period", config.sample_period, 0.01), Ok(0.02));
+        assert_eq!(setting(&flags, "kp", config.mahony.kp, 1.0), Ok(1.0));
+
+        let flags = app().get_matches_from(vec!["lobster-ahrs", "log.csv", "--beta", "fast"]);
+        assert!(setting(&flags, "beta", config.madgwick.beta, 0.1).is_err());
+    }
+
+    #[test]
+    fn test_log_format_defaults() {
+        let flags = app().get_matches_from(vec!["lobster-ahrs", "log.csv"]);
+        assert_eq!(log_format(&flags, config("").log), Ok(LogFormat::default()));
+    }
+
+    #[test]
+    fn test_log_format_precedence() {
+        let config = config(
+            "[log]\ntimestamp = \"time_ms\"\ntime_scale = 0.001\ngyroscope = [\"wx\", \"wy\", \"wz\"]\n\
+             gyro_unit = \"deg/s\"\nmag_unit = \"gauss\"\ndelimiter = \";\"\nimu_only = true",
+        );
+        let flags = app().get_matches_from(vec![
+            "lobster-ahrs", "log.csv", "--timestamp", "t", "--gyroscope", "x, y, z", "--mag-unit", "uT",
+        ]);
+
+        let format = log_format(&flags, config.log).unwrap();
+        let columns = Columns {
+            timestamp: "t".to_string(),
+            gyroscope: ["x".to_string(), "y".to_string(), "z".to_string()],
+            magnetometer: None,
+            ..Columns::default()
+        };
+        assert_eq!(format, LogFormat {
+            columns,
+            time_scale: 0.001,
+            gyro_unit: GyroUnit::DegreesPerSecond,
+            accel_unit: AccelUnit::G,
+            mag_unit: MagUnit::Microtesla,
+            delimiter: b';',
+        });
+    }
+
+    #[test]
+    fn test_log_format_errors() {
+        let flags = app().get_matches_from(vec!["lobster-ahrs", "log.csv", "--gyroscope", "x,y"]);
+        assert!(log_format(&flags, config("").log).is_err());
+
+        let flags = app().get_matches_from(vec!["lobster-ahrs", "log.csv"]);
+        assert!(log_format(&flags, config("[log]\ndelimiter = \"::\"").log).is_err());
+        assert!(log_format(&flags, config("[log]\ngyro_unit = \"rpm\"").log).is_err());
+        assert!(toml::from_str::<Config>("[log]\ntimestmap = \"t\"").is_err());
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let ahrs = Mahony::new(0.01, 1.0, 0.0);
+        let calibrated = calibrate(ahrs, &config("").calibration).unwrap();
+        assert_eq!(calibrated.gyro_calibration(), None);
+        assert_eq!(calibrated.accel_calibration(), None);
+        assert_eq!(calibrated.mag_calibration(), None);
+
+        // Matrices default to identity
+        let calibration = config(
+            "[calibration]\ngyro_bias = [0.01, -0.02, 0.005]\naccel_bias = [0.01, 0.0, -0.02]\n\
+             mag_soft_iron = [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 10.0]]",
+        ).calibration;
+        let calibrated = calibrate(ahrs, &calibration).unwrap();
+
+        let gyro = GyroCalibration::new(Vector3::new(0.01, -0.02, 0.005));
+        let accel = AccelCalibration::new(Vector3::new(0.01, 0.0, -0.02), Matrix3::identity(), 1.0).unwrap();
+        let mag = MagCalibration::new(Vector3::zeros(), Matrix3::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 10.0));
+        assert_eq!(calibrated.gyro_calibration(), Some(gyro));
+        assert_eq!(calibrated.accel_calibration(), Some(accel));
+        assert_eq!(calibrated.mag_calibration(), Some(mag));
+
+        let calibration = config("[calibration]\naccel_sensitivity = [[1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 1.0]]").calibration;
+        assert!(calibrate(ahrs, &calibration).is_err());
+    }
+}
@@ -1,12 +1,18 @@
 pub mod ahrs;
+#[cfg(feature = "python")]
 pub mod increment;
 
+#[cfg(feature = "python")]
 use crate::increment::increment::PyInit_increment;
+#[cfg(feature = "python")]
 use crate::ahrs::python_interface::PyInit_ahrs;
 
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
+#[cfg(feature = "python")]
 use pyo3::wrap_pymodule;
 
+#[cfg(feature = "python")]
 #[pymodule]
 fn lobster_rust(_py: Python, module: &PyModule) -> PyResult<()> {
     module.add_wrapped(wrap_pymodule!(increment))?;
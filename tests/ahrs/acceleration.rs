@@ -0,0 +1,60 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::acceleration::{earth_acceleration, linear_acceleration, AccelUnit, STANDARD_GRAVITY};
+use lobster_rust::ahrs::error::ConfigError;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use na::{UnitQuaternion, Vector3};
+use std::f64;
+
+#[test]
+fn test_linear_acceleration_stationary_is_zero() {
+
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0);
+  let accel = truth.inverse() * Vector3::new(0.0, 0.0, 1.0);
+
+  let linear = linear_acceleration(&truth.into_inner(), &accel, 1.0, AccelUnit::G, AccelUnit::G);
+
+  assert!(relative_eq!(earth_acceleration(&truth.into_inner(), &accel), Vector3::z(), epsilon = 1.0e-12));
+  assert!(relative_eq!(linear.body, Vector3::zeros(), epsilon = 1.0e-12));
+  assert!(relative_eq!(linear.earth, Vector3::zeros(), epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_linear_acceleration_frames_and_units() {
+
+  let truth = UnitQuaternion::from_euler_angles(0.0f64, 0.0, f64::consts::FRAC_PI_2);
+
+  // Accelerating north at 2 m/s² while heading east
+  let earth = Vector3::new(2.0, 0.0, 0.0);
+  let accel = truth.inverse() * (earth + Vector3::new(0.0, 0.0, 9.81));
+
+  let linear = linear_acceleration(&truth.into_inner(), &accel, 9.81, AccelUnit::MetersPerSecondSquared, AccelUnit::MetersPerSecondSquared);
+
+  assert!(relative_eq!(linear.earth, earth, epsilon = 1.0e-12));
+  assert!(relative_eq!(linear.body, Vector3::new(0.0, -2.0, 0.0), epsilon = 1.0e-12));
+
+  let linear = linear_acceleration(&truth.into_inner(), &accel, 9.81, AccelUnit::MetersPerSecondSquared, AccelUnit::G);
+
+  assert!(relative_eq!(linear.earth, earth / STANDARD_GRAVITY, epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_accel_unit_parse_and_convert() {
+
+  assert_eq!("g".parse(), Ok(AccelUnit::G));
+  assert_eq!("m/s^2".parse(), Ok(AccelUnit::MetersPerSecondSquared));
+  assert_eq!("ft/s^2".parse::<AccelUnit>(), Err(ConfigError::InvalidUnit));
+
+  assert_eq!(AccelUnit::G.convert(2.0, AccelUnit::MetersPerSecondSquared), 2.0 * STANDARD_GRAVITY);
+  assert_eq!(AccelUnit::MetersPerSecondSquared.convert(STANDARD_GRAVITY, AccelUnit::G), 1.0);
+}
+
+#[test]
+fn test_madgwick_linear_acceleration() {
+
+  let ahrs = Madgwick::default();
+
+  let linear = ahrs.linear_acceleration(&Vector3::new(0.5, 0.0, 1.0), 1.0, AccelUnit::G, AccelUnit::MetersPerSecondSquared);
+
+  assert!(relative_eq!(linear.earth, Vector3::new(0.5 * STANDARD_GRAVITY, 0.0, 0.0), epsilon = 1.0e-12));
+}
@@ -0,0 +1,94 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::allan::{allan_deviation, allan_deviation_xyz, cluster_sizes, AllanDeviation, NoiseParameters};
+use lobster_rust::ahrs::error::AhrsError;
+use lobster_rust::ahrs::sim::{simulate, ImuModel, Segment, Trajectory};
+use na::{UnitQuaternion, Vector3};
+
+// Gyroscope recording of a still sensor with the given error model
+fn recording(duration: f64, model: &ImuModel<f64>, seed: u64) -> Vec<Vector3<f64>> {
+  let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+  trajectory.push(Segment::still(duration));
+
+  simulate(&trajectory, model, seed).gyroscope
+}
+
+#[test]
+fn test_cluster_sizes() {
+
+  assert_eq!(cluster_sizes(1, 10), Vec::<usize>::new());
+  assert_eq!(cluster_sizes(100, 2), vec!(1, 3, 10, 32));
+  assert_eq!(cluster_sizes(40, 10), vec!(1, 2, 3, 4, 5, 6, 8, 10, 13, 16, 20));
+}
+
+#[test]
+fn test_allan_deviation_white_noise() {
+
+  let mut model = ImuModel::default();
+  model.gyroscope.white_noise = 0.01;
+  model.gyroscope.bias = Vector3::new(0.5, 0.0, 0.0);
+
+  let samples = recording(1000.0, &model, 0);
+  let adevs = allan_deviation_xyz(&samples, 0.01, &cluster_sizes(samples.len(), 10)).unwrap();
+
+  for adev in adevs.iter() {
+    // White noise falls with the square root of the cluster time, whatever the bias
+    assert!(relative_eq!(adev.tau[0], 0.01));
+    assert!(relative_eq!(adev.deviation[0], 0.01, max_relative = 0.02));
+
+    let n = adev.tau.iter().position(|t| relative_eq!(*t, 1.0)).unwrap();
+    assert!(relative_eq!(adev.deviation[n], 0.001, max_relative = 0.05));
+
+    let noise = adev.fit().unwrap();
+    assert!(relative_eq!(noise.angle_random_walk, 0.001, max_relative = 0.03), "{:?}", noise);
+    assert!(noise.bias_instability < 1.0e-4 && noise.rate_random_walk < 1.0e-4, "{:?}", noise);
+  }
+}
+
+#[test]
+fn test_allan_deviation_random_walk() {
+
+  let mut model = ImuModel::default();
+  model.gyroscope.white_noise = 0.01;
+  model.gyroscope.random_walk = 0.001;
+
+  let samples = recording(2000.0, &model, 1);
+  let samples: Vec<f64> = samples.iter().map(|s| s[2]).collect();
+
+  let noise = allan_deviation(&samples, 0.01, &cluster_sizes(samples.len(), 10)).unwrap().fit().unwrap();
+
+  assert!(relative_eq!(noise.angle_random_walk, 0.001, max_relative = 0.05), "{:?}", noise);
+  assert!(relative_eq!(noise.rate_random_walk, 0.001, max_relative = 0.3), "{:?}", noise);
+}
+
+#[test]
+fn test_fit_recovers_noise_model() {
+
+  let truth = NoiseParameters { angle_random_walk: 0.002f64, bias_instability: 0.0005, rate_random_walk: 0.0001 };
+
+  let tau: Vec<f64> = cluster_sizes(1_000_000, 10).iter().map(|m| *m as f64 * 0.01).collect();
+  let deviation = tau.iter().map(|t| truth.deviation(*t)).collect();
+  let error = vec!(0.1; tau.len());
+
+  let fitted = AllanDeviation { tau, deviation, error }.fit().unwrap();
+
+  assert!(relative_eq!(fitted.angle_random_walk, truth.angle_random_walk, max_relative = 1.0e-9));
+  assert!(relative_eq!(fitted.bias_instability, truth.bias_instability, max_relative = 1.0e-9));
+  assert!(relative_eq!(fitted.rate_random_walk, truth.rate_random_walk, max_relative = 1.0e-9));
+}
+
+#[test]
+fn test_allan_deviation_errors() {
+
+  assert_eq!(allan_deviation(&[1.0f64; 10], 0.0, &[1]), Err(AhrsError::InvalidTimeStep));
+  assert_eq!(allan_deviation::<f64>(&[], 0.01, &[1]), Err(AhrsError::NoSamples));
+  assert_eq!(allan_deviation(&[1.0f64; 10], 0.01, &[0, 6]), Err(AhrsError::NoSamples));
+
+  // A constant stream has no noise
+  let adev = allan_deviation(&[1.0f64; 10], 0.01, &[5, 1, 2, 1]).unwrap();
+  assert_eq!(adev.tau, vec!(0.01, 0.02, 0.05));
+  assert_eq!(adev.deviation, vec!(0.0, 0.0, 0.0));
+  assert_eq!(adev.fit().unwrap(), NoiseParameters { angle_random_walk: 0.0, bias_instability: 0.0, rate_random_walk: 0.0 });
+
+  assert_eq!(AllanDeviation::<f64> { tau: vec!(), deviation: vec!(), error: vec!() }.fit(), Err(AhrsError::NoSamples));
+}
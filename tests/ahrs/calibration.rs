@@ -0,0 +1,230 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::calibration::{AccelCalibration, Calibrated, GyroCalibration, MagCalibration};
+use lobster_rust::ahrs::ekf::Ekf;
+use lobster_rust::ahrs::error::ConfigError;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use lobster_rust::ahrs::mahony_filter::Mahony;
+use na::{Matrix3, Quaternion, UnitQuaternion, Vector3};
+use std::f64;
+
+// Distortion with unit determinant and an offset, in µT
+macro_rules! distortion(
+  () => {
+    {
+      let soft = Matrix3::new(1.2f64, 0.1, 0.0,
+                              0.1, 0.9, 0.05,
+                              0.0, 0.05, 1.0);
+      (soft / soft.determinant().cbrt(), Vector3::new(12.0, -30.0, 4.5))
+    }
+  };
+);
+
+// Field directions spread evenly over the sphere
+fn sphere(count: usize, radius: f64) -> Vec<Vector3<f64>> {
+  let golden = f64::consts::PI * (3.0 - 5.0f64.sqrt());
+
+  (0..count).map(|n| {
+    let z = 1.0 - 2.0 * (n as f64 + 0.5) / count as f64;
+    let r = (1.0 - z * z).sqrt();
+    let theta = golden * n as f64;
+    Vector3::new(r * theta.cos(), r * theta.sin(), z) * radius
+  }).collect()
+}
+
+#[test]
+fn test_mag_calibration_fit_recovers_distortion() {
+
+  let (soft, offset) = distortion!();
+  let raw: Vec<Vector3<f64>> = sphere(200, 50.0).iter().map(|m| soft * m + offset).collect();
+
+  let cal = MagCalibration::fit(&raw).unwrap();
+
+  assert!(relative_eq!(cal.hard_iron(), offset, epsilon = 1.0e-6));
+  assert!(relative_eq!(cal.soft_iron(), soft.try_inverse().unwrap(), epsilon = 1.0e-6));
+  assert!(relative_eq!(cal.field_strength(), 50.0, epsilon = 1.0e-6));
+  assert!(cal.fit_error() < 1.0e-9, "fit error: {}", cal.fit_error());
+
+  for (r, m) in raw.iter().zip(sphere(200, 50.0)) {
+    assert!(relative_eq!(cal.apply(r), m, epsilon = 1.0e-6));
+  }
+}
+
+#[test]
+fn test_mag_calibration_fit_error_grows_with_noise() {
+
+  let (soft, offset) = distortion!();
+  let raw: Vec<Vector3<f64>> = sphere(200, 50.0).iter().enumerate()
+    .map(|(n, m)| soft * m + offset + Vector3::new(1.0, -1.0, 0.5) * (n as f64).sin())
+    .collect();
+
+  let cal = MagCalibration::fit(&raw).unwrap();
+
+  assert!(cal.fit_error() > 1.0e-3 && cal.fit_error() < 0.1, "fit error: {}", cal.fit_error());
+}
+
+#[test]
+fn test_mag_calibration_fit_invalid() {
+
+  let too_few = sphere(8, 50.0);
+  let flat: Vec<Vector3<f64>> = sphere(50, 50.0).iter().map(|m| Vector3::new(m[0], m[1], 0.0)).collect();
+
+  assert_eq!(MagCalibration::fit(&too_few), Err(ConfigError::InvalidCalibration));
+  assert_eq!(MagCalibration::fit(&flat), Err(ConfigError::InvalidCalibration));
+}
+
+// Whether `ahrs` fed distorted magnetometer measurements through a fitted calibration follows
+// a copy fed undistorted ones
+fn follows_mag_calibration<A: Ahrs<f64> + Copy>(ahrs: A) -> bool {
+  let (soft, offset) = distortion!();
+  let cal = MagCalibration::fit(&sphere(200, 50.0).iter().map(|m| soft * m + offset).collect::<Vec<_>>()).unwrap();
+
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = truth.inverse() * Vector3::new(0.0, 0.0, 1.0);
+  let mag = truth.inverse() * Vector3::new(30.0, 0.0, -40.0);
+
+  let mut clean = ahrs;
+  let mut calibrated = Calibrated::new(ahrs);
+  calibrated.set_mag_calibration(Some(cal));
+
+  let (mut expected, mut actual) = (Quaternion::identity(), Quaternion::identity());
+  for _ in 0..100 {
+    expected = *clean.update(&gyro, &accel, &mag).unwrap();
+    actual = *calibrated.update(&gyro, &accel, &(soft * mag + offset)).unwrap();
+  }
+
+  relative_eq!(actual, expected, epsilon = 1.0e-6)
+}
+
+#[test]
+fn test_calibrated_applies_mag_calibration() {
+
+  assert!(follows_mag_calibration(Madgwick::default()));
+  assert!(follows_mag_calibration(Mahony::new(1.0 / 256.0, 2.0, 0.5)));
+  assert!(follows_mag_calibration(Ekf::default()));
+}
+
+// Sensitivity with scale errors and cross-axis coupling, and a bias, in g
+macro_rules! accel_distortion(
+  () => {
+    (
+      Matrix3::new(1.02f64, 0.01, -0.005,
+                   0.008, 0.98, 0.012,
+                   -0.01, 0.004, 1.01),
+      Vector3::new(0.03, -0.02, 0.05)
+    )
+  };
+);
+
+// Gravity along every sensor axis, pointing up and down
+fn six_positions(gravity: f64) -> Vec<Vector3<f64>> {
+  vec!(
+    Vector3::new(gravity, 0.0, 0.0), Vector3::new(-gravity, 0.0, 0.0),
+    Vector3::new(0.0, gravity, 0.0), Vector3::new(0.0, -gravity, 0.0),
+    Vector3::new(0.0, 0.0, gravity), Vector3::new(0.0, 0.0, -gravity),
+  )
+}
+
+#[test]
+fn test_accel_calibration_fit_recovers_distortion() {
+
+  let (sensitivity, bias) = accel_distortion!();
+  let raw: Vec<Vector3<f64>> = six_positions(9.80665).iter().map(|a| sensitivity * a + bias * 9.80665).collect();
+
+  let cal = AccelCalibration::fit(&raw, 9.80665).unwrap();
+
+  assert!(relative_eq!(cal.sensitivity(), sensitivity, epsilon = 1.0e-9));
+  assert!(relative_eq!(cal.bias(), bias * 9.80665, epsilon = 1.0e-9));
+  assert!(relative_eq!(cal.scale(), Vector3::new(1.02, 0.98, 1.01), epsilon = 1.0e-9));
+  assert!(relative_eq!(cal.misalignment().diagonal(), Vector3::new(1.0, 1.0, 1.0), epsilon = 1.0e-9));
+  assert!(cal.fit_error() < 1.0e-9, "fit error: {}", cal.fit_error());
+
+  for (r, a) in raw.iter().zip(six_positions(9.80665)) {
+    assert!(relative_eq!(cal.apply(r), a, epsilon = 1.0e-9));
+  }
+}
+
+#[test]
+fn test_accel_calibration_residuals() {
+
+  let (sensitivity, bias) = accel_distortion!();
+
+  // Several noisy samples per position
+  let raw: Vec<Vector3<f64>> = six_positions(1.0).iter().enumerate()
+    .flat_map(|(n, a)| (0..3).map(move |k| a + Vector3::new(1.0, -1.0, 1.0) * 0.002 * ((n * 3 + k) as f64).sin()))
+    .map(|a| sensitivity * a + bias)
+    .collect();
+
+  let cal = AccelCalibration::fit(&raw, 1.0).unwrap();
+  let residuals = cal.residuals(&raw);
+
+  assert_eq!(residuals.len(), raw.len());
+  assert!(residuals.iter().all(|r| *r < 0.01));
+  assert!(cal.fit_error() > 0.0 && cal.fit_error() < 0.01, "fit error: {}", cal.fit_error());
+}
+
+#[test]
+fn test_accel_calibration_fit_invalid() {
+
+  let up_down = vec!(Vector3::new(0.0f64, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+  let samples: [Vector3<f64>; 0] = [];
+
+  assert_eq!(AccelCalibration::fit(&up_down, 1.0), Err(ConfigError::InvalidCalibration));
+  assert_eq!(AccelCalibration::fit(&samples, 1.0), Err(ConfigError::InvalidCalibration));
+  assert_eq!(AccelCalibration::new(Vector3::zeros(), Matrix3::zeros(), 1.0), Err(ConfigError::InvalidCalibration));
+}
+
+#[test]
+fn test_gyro_calibration_fit() {
+
+  let bias = Vector3::new(0.01f64, -0.02, 0.005);
+  let raw: Vec<Vector3<f64>> = (0..100).map(|n| bias + Vector3::new(0.001, 0.0, -0.001) * if n % 2 == 0 { 1.0 } else { -1.0 }).collect();
+
+  let cal = GyroCalibration::fit(&raw).unwrap();
+
+  assert!(relative_eq!(cal.bias(), bias, epsilon = 1.0e-12));
+  assert!(relative_eq!(cal.noise(), Vector3::new(0.001, 0.0, 0.001), epsilon = 1.0e-12));
+  assert!(relative_eq!(cal.apply(&bias), Vector3::zeros(), epsilon = 1.0e-12));
+
+  let samples: [Vector3<f64>; 0] = [];
+  assert_eq!(GyroCalibration::fit(&samples), Err(ConfigError::InvalidCalibration));
+}
+
+// Whether `ahrs` fed biased gyroscope and distorted accelerometer measurements through
+// calibrations follows a copy fed undistorted ones
+fn follows_accel_and_gyro_calibration<A: Ahrs<f64> + Copy>(ahrs: A) -> bool {
+  let (sensitivity, bias) = accel_distortion!();
+  let gyro_bias = Vector3::new(0.01f64, -0.02, 0.005);
+
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0);
+
+  let gyro = Vector3::new(0.1, -0.05, 0.2);
+  let accel = truth.inverse() * Vector3::new(0.0, 0.0, 1.0);
+
+  let mut clean = ahrs;
+  let mut calibrated = Calibrated::new(ahrs);
+  calibrated.set_gyro_calibration(Some(GyroCalibration::new(gyro_bias)));
+  calibrated.set_accel_calibration(Some(AccelCalibration::new(bias, sensitivity, 1.0).unwrap()));
+
+  let (mut expected, mut actual) = (Quaternion::identity(), Quaternion::identity());
+  for _ in 0..100 {
+    expected = *clean.update_imu(&gyro, &accel).unwrap();
+    actual = *calibrated.update_imu(&(gyro + gyro_bias), &(sensitivity * accel + bias)).unwrap();
+  }
+
+  // Navigation corrects the accelerometer like the filter does
+  let corrected = calibrated.correct_accelerometer(&(sensitivity * accel + bias));
+
+  relative_eq!(actual, expected, epsilon = 1.0e-9) && relative_eq!(corrected, accel, epsilon = 1.0e-9)
+}
+
+#[test]
+fn test_calibrated_applies_accel_and_gyro_calibration() {
+
+  assert!(follows_accel_and_gyro_calibration(Madgwick::default()));
+  assert!(follows_accel_and_gyro_calibration(Mahony::new(1.0 / 256.0, 2.0, 0.5)));
+  assert!(follows_accel_and_gyro_calibration(Ekf::default()));
+}
@@ -0,0 +1,113 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::conversion::{axis_angle, euler_angles, rotation_matrix, rotation_vector, EulerSequence};
+use na::{Vector3, Quaternion, Unit, UnitQuaternion};
+use std::f64;
+
+const SEQUENCES: [EulerSequence; 12] = [
+  EulerSequence::XYZ, EulerSequence::XZY, EulerSequence::YXZ, EulerSequence::YZX,
+  EulerSequence::ZXY, EulerSequence::ZYX, EulerSequence::XYX, EulerSequence::XZX,
+  EulerSequence::YXY, EulerSequence::YZY, EulerSequence::ZXZ, EulerSequence::ZYZ,
+];
+
+// Composes the intrinsic rotation for `angles` about the axes of `sequence`
+fn compose(sequence: EulerSequence, angles: &Vector3<f64>) -> UnitQuaternion<f64> {
+  let axes = [Vector3::x_axis(), Vector3::y_axis(), Vector3::z_axis()];
+  let seq = sequence.axes();
+
+  UnitQuaternion::from_axis_angle(&axes[seq[0]], angles[0])
+    * UnitQuaternion::from_axis_angle(&axes[seq[1]], angles[1])
+    * UnitQuaternion::from_axis_angle(&axes[seq[2]], angles[2])
+}
+
+#[test]
+fn test_euler_sequence_from_str() {
+
+  assert_eq!("zyx".parse::<EulerSequence>(), Ok(EulerSequence::ZYX));
+  assert_eq!("ZXZ".parse::<EulerSequence>(), Ok(EulerSequence::ZXZ));
+  assert!("zzx".parse::<EulerSequence>().is_err(), "Parsing invalid sequence should have failed.");
+}
+
+#[test]
+fn test_euler_angles_zyx() {
+
+  let quat = compose(EulerSequence::ZYX, &Vector3::new(0.3, -0.2, 0.1)).into_inner();
+
+  let actual = euler_angles(&quat, EulerSequence::ZYX);
+
+  // nalgebra's roll, pitch, yaw uses the same convention
+  let (roll, pitch, yaw) = UnitQuaternion::from_quaternion(quat).euler_angles();
+
+  assert!(relative_eq!(actual, Vector3::new(yaw, pitch, roll), epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_euler_angles_round_trip() {
+
+  let tait_bryan = Vector3::new(0.7, -0.4, 2.1);
+  let proper = Vector3::new(-1.2, 0.9, 0.5);
+
+  for &sequence in SEQUENCES.iter() {
+    let seq = sequence.axes();
+    let expected = if seq[0] == seq[2] { proper } else { tait_bryan };
+
+    let quat = compose(sequence, &expected).into_inner();
+    let actual = euler_angles(&quat, sequence);
+
+    let fail_message = format!("{:?} angles did not match:\n\
+        actual: {:?}\n\
+        expect: {:?}", sequence, actual, expected);
+
+    assert!(relative_eq!(actual, expected, epsilon = 1.0e-12), "{}", fail_message);
+  }
+}
+
+#[test]
+fn test_euler_angles_gimbal_lock() {
+
+  for &sequence in SEQUENCES.iter() {
+    let seq = sequence.axes();
+    let middle = if seq[0] == seq[2] { 0.0 } else { f64::consts::FRAC_PI_2 };
+
+    let expected = compose(sequence, &Vector3::new(0.4, middle, 0.3));
+    let angles = euler_angles(&expected.into_inner(), sequence);
+
+    assert_eq!(angles[2], 0.0);
+
+    let actual = compose(sequence, &angles);
+
+    assert!(actual.angle_to(&expected) < 1.0e-9, "{:?} angle error: {}", sequence, actual.angle_to(&expected));
+  }
+}
+
+#[test]
+fn test_rotation_matrix() {
+
+  let quat = UnitQuaternion::from_euler_angles(0.1f64, 0.2, 0.3);
+
+  let actual = rotation_matrix(&quat.into_inner());
+
+  assert!(relative_eq!(actual * Vector3::new(1.0, 2.0, 3.0), quat * Vector3::new(1.0, 2.0, 3.0), epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_axis_angle() {
+
+  let axis = Unit::new_normalize(Vector3::new(1.0f64, -2.0, 0.5));
+  let quat = UnitQuaternion::from_axis_angle(&axis, 0.8).into_inner();
+
+  let (actual_axis, actual_angle) = axis_angle(&quat);
+
+  assert!(relative_eq!(actual_axis, axis.into_inner(), epsilon = 1.0e-12));
+  assert!(relative_eq!(actual_angle, 0.8, epsilon = 1.0e-12));
+  assert!(relative_eq!(rotation_vector(&quat), axis.into_inner() * 0.8, epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_axis_angle_identity() {
+
+  let quat = Quaternion::new(1.0f64, 0.0, 0.0, 0.0);
+
+  assert_eq!(axis_angle(&quat), (Vector3::x(), 0.0));
+  assert_eq!(rotation_vector(&quat), Vector3::zeros());
+}
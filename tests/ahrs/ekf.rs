@@ -0,0 +1,153 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::ekf::Ekf;
+use na::{Vector3, UnitQuaternion};
+use std::f64;
+
+#[test]
+fn test_update_accel_zero() {
+
+  let mut ahrs = Ekf::default();
+
+  let g: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+  let m: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+
+  let res = ahrs.update(&g, &a, &m);
+
+  let fail_message = "Normalizing zero-value accel should have failed.";
+
+  assert!(res.is_err(), "{}", fail_message);
+}
+
+#[test]
+fn test_update_mag_zero() {
+
+  let mut ahrs = Ekf::default();
+
+  let g: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let a: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let m: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+
+  let res = ahrs.update(&g, &a, &m);
+
+  let fail_message = "Normalizing zero-value mag should have failed.";
+
+  assert!(res.is_err(), "{}", fail_message);
+}
+
+#[test]
+fn test_update_imu_accel_zero() {
+
+  let mut ahrs = Ekf::default();
+
+  let g: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+
+  let res = ahrs.update_imu(&g, &a);
+
+  let fail_message = "Normalizing zero-value accel should have failed.";
+
+  assert!(res.is_err(), "{}", fail_message);
+}
+
+#[test]
+fn test_ekf_update_converges_to_orientation() {
+
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0);
+
+  // Earth frame references, rotated into the sensor frame
+  let accel = truth.inverse() * Vector3::new(0.0, 0.0, 1.0);
+  let mag = truth.inverse() * Vector3::new(0.6, 0.0, -0.8);
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+
+  let mut ahrs = Ekf::default();
+
+  for _ in 0..10000 {
+    ahrs.update(&gyro, &accel, &mag).unwrap();
+  }
+
+  let actual = UnitQuaternion::from_quaternion(ahrs.quat);
+
+  assert!(actual.angle_to(&truth) < 1.0e-3, "angle error: {}", actual.angle_to(&truth));
+}
+
+#[test]
+fn test_ekf_estimates_gyro_bias() {
+
+  let bias = Vector3::new(0.02, -0.01, 0.015);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+  let mag = Vector3::new(0.6, 0.0, -0.8);
+
+  let mut ahrs = Ekf::default();
+
+  for _ in 0..10000 {
+    ahrs.update(&bias, &accel, &mag).unwrap();
+  }
+
+  assert!(relative_eq!(ahrs.gyro_bias(), bias, epsilon = 1.0e-4));
+}
+
+#[test]
+fn test_ekf_update_imu_covariance_shrinks() {
+
+  let mut ahrs = Ekf::default();
+  let initial = ahrs.covariance();
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+
+  for _ in 0..1000 {
+    ahrs.update_imu(&gyro, &accel).unwrap();
+  }
+
+  let actual = ahrs.covariance();
+
+  assert!(relative_eq!(actual, actual.transpose()));
+
+  // Roll and pitch are observable from gravity, so their uncertainty must drop
+  assert!(actual[(1, 1)] < initial[(1, 1)]);
+  assert!(actual[(2, 2)] < initial[(2, 2)]);
+  assert!(actual[(4, 4)] < initial[(4, 4)]);
+  assert!(actual[(5, 5)] < initial[(5, 5)]);
+}
+
+#[test]
+fn test_ekf_update_dt_invalid() {
+
+  let mut ahrs = Ekf::default();
+
+  let g: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 1.0);
+  let m: Vector3<f64> = Vector3::new(0.6, 0.0, -0.8);
+
+  assert!(ahrs.update_dt(&g, &a, &m, 0.0).is_err());
+  assert!(ahrs.update_imu_dt(&g, &a, f64::NAN).is_err());
+}
+
+#[cfg(feature = "field_access")]
+#[test]
+fn test_ekf_singular_covariance_leaves_state_unchanged() {
+
+  use lobster_rust::ahrs::error::AhrsError;
+  use na::{MatrixN, U7};
+
+  // Without state uncertainty or measurement noise the innovation covariance is zero
+  let mut ahrs = Ekf::new(0.01, 0.0, 0.0, 0.0, 0.0);
+  *ahrs.covariance_mut() = MatrixN::<f64, U7>::zeros();
+
+  let quat = ahrs.quat;
+  let covariance = ahrs.covariance();
+
+  let g: Vector3<f64> = Vector3::new(0.5, -0.2, 1.0);
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 1.0);
+  let m: Vector3<f64> = Vector3::new(0.6, 0.0, -0.8);
+
+  assert_eq!(ahrs.update(&g, &a, &m), Err(AhrsError::SingularCovariance));
+  assert_eq!(ahrs.update_imu(&g, &a), Err(AhrsError::SingularCovariance));
+
+  assert_eq!(ahrs.quat, quat);
+  assert_eq!(ahrs.covariance(), covariance);
+  assert_eq!(ahrs.gyro_bias(), Vector3::zeros());
+}
@@ -0,0 +1,53 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::error::{AhrsError, ConfigError};
+use lobster_rust::ahrs::init::{davenport, mean};
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use na::Vector3;
+use std::error::Error;
+
+#[test]
+fn test_error_display() {
+
+  assert_eq!(AhrsError::AccelerometerNormZero.to_string(), "Accelerometer norm divided by zero.");
+  assert_eq!(AhrsError::MagnetometerNormZero.to_string(), "Magnetometer norm divided by zero.");
+  assert_eq!(AhrsError::InvalidTimeStep.to_string(), "Time step must be positive and finite.");
+  assert_eq!(ConfigError::InvalidFrame.to_string(), "Unknown Earth frame.");
+}
+
+#[test]
+fn test_error_is_std_error() {
+
+  let err: Box<dyn Error> = Box::new(AhrsError::NonFiniteInput);
+
+  assert_eq!(err.to_string(), "Sensor measurement is not finite.");
+
+  let err: Box<dyn Error> = Box::new(ConfigError::InvalidUnit);
+
+  assert_eq!(err.to_string(), "Unknown unit.");
+}
+
+#[test]
+fn test_update_errors_are_distinct() {
+
+  let mut ahrs = Madgwick::default();
+
+  let g: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let zero: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+
+  assert_eq!(ahrs.update(&g, &zero, &g), Err(AhrsError::AccelerometerNormZero));
+  assert_eq!(ahrs.update(&g, &g, &zero), Err(AhrsError::MagnetometerNormZero));
+  assert_eq!(ahrs.update_imu(&g, &zero), Err(AhrsError::AccelerometerNormZero));
+  assert_eq!(ahrs.update_imu_dt(&g, &g, 0.0), Err(AhrsError::InvalidTimeStep));
+}
+
+#[test]
+fn test_init_errors_are_distinct() {
+
+  let samples: [Vector3<f64>; 0] = [];
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 1.0);
+
+  assert_eq!(mean(&samples), Err(AhrsError::NoSamples));
+  assert_eq!(davenport(&a, &(a * 2.0), 1.0, 1.0), Err(AhrsError::ParallelVectors));
+}
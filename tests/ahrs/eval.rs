@@ -0,0 +1,157 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::ekf::Ekf;
+use lobster_rust::ahrs::eval::{angular_error, angular_errors, convergence_time, euler_error, evaluate, heading_drift_rate, track, Accuracy, EvalError};
+use lobster_rust::ahrs::frame::{EarthFrame, Frames};
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use lobster_rust::ahrs::mahony_filter::Mahony;
+use lobster_rust::ahrs::sim::{simulate, ImuModel, Segment, Simulation, Trajectory};
+use na::{Quaternion, UnitQuaternion, Vector3};
+use std::f64;
+
+// Orientation at the start of the simulation
+fn initial() -> UnitQuaternion<f64> {
+  UnitQuaternion::from_euler_angles(0.2, -0.1, 0.5)
+}
+
+// Noisy recording of a still start followed by sinusoidal rotation about every axis
+fn simulation() -> Simulation<f64> {
+  let mut trajectory = Trajectory::new(initial(), 0.01f64);
+  trajectory.push(Segment::still(2.0));
+  trajectory.push(Segment::sinusoidal(20.0, Vector3::new(0.8, -0.5, 1.5), Vector3::zeros(), 0.2));
+
+  let mut model = ImuModel::default();
+  model.gyroscope.white_noise = 0.005;
+  model.accelerometer.white_noise = 0.005;
+  model.magnetometer.white_noise = 0.005;
+
+  simulate(&trajectory, &model, 11)
+}
+
+// Accuracy of a filter against the simulation, over every sample and after the first `settle`
+// seconds
+fn accuracy<A: Ahrs<f64>>(ahrs: &mut A, magnetometer: bool, settle: f64) -> (Accuracy<f64>, Accuracy<f64>) {
+  let sim = simulation();
+  let estimates = track(ahrs, &sim, magnetometer).unwrap();
+
+  let truth: Vec<Quaternion<f64>> = sim.truth.iter().map(|t| t.quat).collect();
+  let times: Vec<f64> = sim.truth.iter().map(|t| t.time).collect();
+  let start = times.iter().position(|t| *t >= settle).unwrap();
+
+  (
+    evaluate(&estimates, &truth, &times, 0.05).unwrap(),
+    evaluate(&estimates[start..], &truth[start..], &times[start..], 0.05).unwrap(),
+  )
+}
+
+#[test]
+fn test_angular_error_sign_ambiguity() {
+
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0).into_inner();
+  let estimate = truth * UnitQuaternion::from_euler_angles(0.0, 0.0, 0.1).into_inner();
+
+  assert!(relative_eq!(angular_error(&estimate, &truth), 0.1, epsilon = 1.0e-12));
+  assert!(relative_eq!(angular_error(&-estimate, &truth), 0.1, epsilon = 1.0e-12));
+  assert!(relative_eq!(angular_error(&estimate, &-truth), 0.1, epsilon = 1.0e-12));
+  assert!(relative_eq!(angular_error(&(estimate * 2.0), &truth), 0.1, epsilon = 1.0e-12));
+
+  let flipped = UnitQuaternion::from_euler_angles(f64::consts::PI, 0.0, 0.0).into_inner();
+  assert!(relative_eq!(angular_error(&flipped, &Quaternion::identity()), f64::consts::PI, epsilon = 1.0e-12));
+
+  assert_eq!(angular_errors(&[truth], &[]), Err(EvalError::LengthMismatch));
+}
+
+#[test]
+fn test_euler_error_wraps() {
+
+  let estimate = UnitQuaternion::from_euler_angles(0.1f64, 0.2, 3.1).into_inner();
+  let truth = UnitQuaternion::from_euler_angles(0.0f64, 0.1, -3.1).into_inner();
+
+  let expected = Vector3::new(0.1, 0.1, 6.2 - 2.0 * f64::consts::PI);
+  assert!(relative_eq!(euler_error(&estimate, &truth), expected, epsilon = 1.0e-12));
+  assert!(relative_eq!(euler_error(&-estimate, &truth), expected, epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_convergence_time() {
+
+  let times = [0.0f64, 0.1, 0.2, 0.3, 0.4];
+
+  assert_eq!(convergence_time(&[0.5, 0.01, 0.2, 0.01, 0.01], &times, 0.1), Some(0.3));
+  assert_eq!(convergence_time(&[0.01; 5], &times, 0.1), Some(0.0));
+  assert_eq!(convergence_time(&[0.01, 0.01, 0.01, 0.01, 0.5], &times, 0.1), None);
+  assert_eq!(convergence_time(&[0.01, 0.01, 0.01, 0.01, f64::NAN], &times, 0.1), None);
+}
+
+#[test]
+fn test_heading_drift_rate() {
+
+  // Heading error growing at 0.5 rad/s, passing through ±π
+  let times: Vec<f64> = (0..1000).map(|n| n as f64 * 0.01).collect();
+  let truth: Vec<Quaternion<f64>> = times.iter().map(|t| UnitQuaternion::from_euler_angles(0.1, -0.2, *t).into_inner()).collect();
+  let estimates: Vec<Quaternion<f64>> = times.iter().zip(truth.iter()).map(|(t, q)| {
+    UnitQuaternion::from_euler_angles(0.0, 0.0, 0.5 * t).into_inner() * q
+  }).collect();
+
+  let rate = heading_drift_rate(&estimates, &truth, &times).unwrap();
+  assert!(relative_eq!(rate, 0.5, epsilon = 1.0e-9), "{}", rate);
+
+  let accuracy = evaluate(&estimates, &truth, &times, 0.1).unwrap();
+  assert!(relative_eq!(accuracy.euler_rmse[0], 0.0, epsilon = 1.0e-9));
+  assert!(relative_eq!(accuracy.max_error, f64::consts::PI, epsilon = 1.0e-2));
+  assert_eq!(accuracy.convergence_time, None);
+
+  assert_eq!(heading_drift_rate(&estimates, &truth, &times[1..]), Err(EvalError::LengthMismatch));
+  assert_eq!(evaluate::<f64>(&[], &[], &[], 0.1), Err(EvalError::NoSamples));
+}
+
+#[test]
+fn test_madgwick_accuracy() {
+
+  // Converging from identity
+  let (full, settled) = accuracy(&mut Madgwick::new(0.01, 0.1), true, 5.0);
+
+  assert!(full.convergence_time.unwrap() < 5.0, "{:?}", full);
+  assert!(settled.rmse < 0.05 && settled.euler_rmse.max() < 0.05, "{:?}", settled);
+  assert!(settled.heading_drift_rate.abs() < 1.0e-3, "{:?}", settled);
+
+  // Without magnetometer the heading never converges, but roll and pitch do
+  let (_, imu) = accuracy(&mut Madgwick::new(0.01, 0.1), false, 5.0);
+
+  assert!(imu.euler_rmse[0] < 0.05 && imu.euler_rmse[1] < 0.05, "{:?}", imu);
+  assert!(imu.euler_rmse[2] > 0.4, "{:?}", imu);
+}
+
+#[test]
+fn test_madgwick_accuracy_in_ned() {
+
+  let mut ned = Madgwick::new_with_frames(0.01, 0.1, Frames::new(EarthFrame::Ned));
+  ned.quat = EarthFrame::Ned.quat_from_nwu(&initial().into_inner());
+
+  let (nwu, _) = accuracy(&mut Madgwick::new_with_quat(0.01, 0.1, initial().into_inner()), true, 0.0);
+  let (ned, _) = accuracy(&mut ned, true, 0.0);
+
+  assert!(nwu.rmse < 0.05 && nwu.convergence_time == Some(0.0), "{:?}", nwu);
+  assert!(relative_eq!(ned.rmse, nwu.rmse, max_relative = 1.0e-6), "{:?} {:?}", ned, nwu);
+}
+
+#[test]
+fn test_mahony_accuracy() {
+
+  let (full, settled) = accuracy(&mut Mahony::new(0.01, 1.0, 0.0), true, 15.0);
+
+  assert!(full.convergence_time.unwrap() < 15.0, "{:?}", full);
+  assert!(settled.rmse < 0.05, "{:?}", settled);
+  assert!(settled.heading_drift_rate.abs() < 5.0e-3, "{:?}", settled);
+}
+
+#[test]
+fn test_ekf_accuracy() {
+
+  let (full, settled) = accuracy(&mut Ekf::new(0.01, 0.005, 1.0e-5, 0.005, 0.005), true, 2.0);
+
+  assert!(full.convergence_time.unwrap() < 2.0, "{:?}", full);
+  assert!(settled.rmse < 0.05, "{:?}", settled);
+  assert!(settled.heading_drift_rate.abs() < 1.0e-3, "{:?}", settled);
+}
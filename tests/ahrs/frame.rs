@@ -0,0 +1,164 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::acceleration::{AccelUnit, STANDARD_GRAVITY};
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::error::ConfigError;
+use lobster_rust::ahrs::frame::{AxisMapping, EarthFrame, Frames};
+use lobster_rust::ahrs::ins::Ins;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use na::{Matrix3, Quaternion, UnitQuaternion, Vector3};
+
+// Body frame measurements of a sensor held at a fixed orientation while the gyroscope reads a
+// constant rate
+fn measurements() -> (Vector3<f64>, Vector3<f64>, Vector3<f64>) {
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0);
+
+  (
+    Vector3::new(0.1, -0.05, 0.2),
+    truth.inverse() * Vector3::new(0.0, 0.0, 1.0),
+    truth.inverse() * Vector3::new(0.6, 0.0, -0.8),
+  )
+}
+
+#[test]
+fn test_earth_frames_are_equivalent() {
+
+  let (gyro, accel, mag) = measurements();
+
+  for frame in &[EarthFrame::Ned, EarthFrame::Enu] {
+    let mut nwu = Madgwick::new(0.01f64, 0.1);
+    let mut other = Madgwick::new_with_frames(0.01, 0.1, Frames::new(*frame));
+
+    // Start from the same physical orientation
+    other.quat = frame.quat_from_nwu(&nwu.quat);
+
+    for _ in 0..200 {
+      nwu.update(&gyro, &accel, &mag).unwrap();
+      other.update(&gyro, &accel, &mag).unwrap();
+    }
+
+    for _ in 0..200 {
+      nwu.update_imu(&gyro, &accel).unwrap();
+      other.update_imu(&gyro, &accel).unwrap();
+    }
+
+    assert!(relative_eq!(other.quat, frame.quat_from_nwu(&nwu.quat), epsilon = 1.0e-9));
+    assert!(relative_eq!(frame.quat_to_nwu(&other.quat), nwu.quat, epsilon = 1.0e-9));
+  }
+}
+
+#[test]
+fn test_earth_frame_axes() {
+
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0);
+  let accel = truth.inverse() * Vector3::new(0.0, 0.0, 1.0);
+  let mag = truth.inverse() * Vector3::new(0.6, 0.0, -0.8);
+
+  // At rest the accelerometer measures up and the horizontal field points north
+  let check = |frame: EarthFrame, up: Vector3<f64>, north: Vector3<f64>| {
+    let rotation = UnitQuaternion::from_quaternion(frame.quat_from_nwu(&truth.into_inner()));
+    let field = rotation * mag;
+
+    assert!(relative_eq!(rotation * accel, up, epsilon = 1.0e-12));
+    assert!(relative_eq!((field - up * field.dot(&up)).normalize(), north, epsilon = 1.0e-12));
+    assert_eq!(frame.up::<f64>(), up);
+  };
+
+  check(EarthFrame::Nwu, Vector3::z(), Vector3::x());
+  check(EarthFrame::Ned, -Vector3::z(), Vector3::x());
+  check(EarthFrame::Enu, Vector3::z(), Vector3::y());
+}
+
+#[test]
+fn test_axis_mappings_are_equivalent() {
+
+  let (gyro, accel, mag) = measurements();
+
+  // Board rotated on its mount, with the sensor axes swapped and a left-handed magnetometer
+  let mounting = UnitQuaternion::from_euler_angles(0.0f64, 0.1, 0.5);
+
+  let mut frames = Frames::new(EarthFrame::Nwu);
+  frames.gyroscope = AxisMapping::from_axes("y,-x,z").unwrap().rotated(&mounting);
+  frames.accelerometer = AxisMapping::from_axes("y,-x,z").unwrap().rotated(&mounting);
+  frames.magnetometer = AxisMapping::from_axes("y,x,z").unwrap().rotated(&mounting);
+
+  assert!(frames.magnetometer.matrix().determinant() < 0.0);
+
+  // Raw measurements in the sensor axes
+  let raw = |mapping: &AxisMapping<f64>, v: &Vector3<f64>| mapping.matrix().transpose() * v;
+
+  let mut body = Madgwick::new(0.01f64, 0.1);
+  let mut mapped = Madgwick::new_with_frames(0.01, 0.1, frames);
+
+  for _ in 0..200 {
+    body.update(&gyro, &accel, &mag).unwrap();
+    mapped.update(
+      &raw(&frames.gyroscope, &gyro),
+      &raw(&frames.accelerometer, &accel),
+      &raw(&frames.magnetometer, &mag)
+    ).unwrap();
+  }
+
+  assert!(relative_eq!(mapped.quat, body.quat, epsilon = 1.0e-9));
+
+  let linear = mapped.linear_acceleration(&raw(&frames.accelerometer, &accel), 1.0, AccelUnit::G, AccelUnit::G);
+  assert!(relative_eq!(linear.body, body.linear_acceleration(&accel, 1.0, AccelUnit::G, AccelUnit::G).body, epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_set_frames_keeps_orientation() {
+
+  let (gyro, accel, mag) = measurements();
+
+  let mut ahrs = Madgwick::new(0.01f64, 0.1);
+  for _ in 0..100 {
+    ahrs.update(&gyro, &accel, &mag).unwrap();
+  }
+
+  let nwu = ahrs.quat;
+  ahrs.set_frames(Frames::new(EarthFrame::Ned));
+
+  assert_eq!(ahrs.frames().earth, EarthFrame::Ned);
+  assert_eq!(ahrs.earth_frame(), EarthFrame::Ned);
+  assert!(relative_eq!(ahrs.quat, EarthFrame::Ned.quat_from_nwu(&nwu), epsilon = 1.0e-12));
+
+  ahrs.set_frames(Frames::new(EarthFrame::Enu));
+  assert!(relative_eq!(ahrs.quat, EarthFrame::Enu.quat_from_nwu(&nwu), epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_ins_in_ned() {
+
+  // Zero gain holds the identity orientation, with the body z axis pointing down
+  let mut ins = Ins::new(Madgwick::new_with_frames(0.01f64, 0.0, Frames::new(EarthFrame::Ned)), 1.0, AccelUnit::G);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+
+  for _ in 0..100 {
+    ins.update_imu(&gyro, &Vector3::new(0.1, 0.0, -1.0), 0.01).unwrap();
+  }
+
+  assert_eq!(ins.ahrs().quat, Quaternion::new(1.0, 0.0, 0.0, 0.0));
+  assert!(relative_eq!(ins.velocity(), Vector3::new(0.1 * STANDARD_GRAVITY, 0.0, 0.0), epsilon = 1.0e-9));
+  assert!(relative_eq!(ins.position(), Vector3::new(0.05 * STANDARD_GRAVITY, 0.0, 0.0), epsilon = 1.0e-9));
+}
+
+#[test]
+fn test_frame_parsing() {
+
+  assert_eq!("NED".parse(), Ok(EarthFrame::Ned));
+  assert_eq!("enu".parse(), Ok(EarthFrame::Enu));
+  assert_eq!("xyz".parse::<EarthFrame>(), Err(ConfigError::InvalidFrame));
+
+  assert_eq!(AxisMapping::<f64>::from_axes("x, +y, z"), Ok(AxisMapping::identity()));
+  assert_eq!(
+    AxisMapping::from_axes("-z,x,-y"),
+    Ok(AxisMapping::new(Matrix3::new(0.0f64, 0.0, -1.0,
+                                     1.0, 0.0, 0.0,
+                                     0.0, -1.0, 0.0)))
+  );
+
+  for axes in &["x,y", "x,x,z", "x,y,w", "--x,y,z", "x,y,z,x"] {
+    assert_eq!(AxisMapping::<f64>::from_axes(axes), Err(ConfigError::InvalidAxisMapping));
+  }
+}
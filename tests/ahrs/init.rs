@@ -0,0 +1,99 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::init::{davenport, mean, triad};
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use na::{Vector3, UnitQuaternion};
+use std::f64;
+
+// orientation, accel, mag values
+macro_rules! static_sensors(
+  () => {
+    {
+      let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 2.5);
+      (
+        truth,
+        truth.inverse() * Vector3::new(0.0, 0.0, 1.0),
+        truth.inverse() * Vector3::new(0.4, 0.0, -0.9) * 0.5
+      )
+    }
+  };
+);
+
+#[test]
+fn test_mean() {
+
+  let samples = [Vector3::new(1.0f64, 2.0, 3.0), Vector3::new(3.0, 2.0, 1.0)];
+
+  assert_eq!(mean(&samples).unwrap(), Vector3::new(2.0, 2.0, 2.0));
+}
+
+#[test]
+fn test_mean_empty() {
+
+  let samples: [Vector3<f64>; 0] = [];
+
+  assert!(mean(&samples).is_err(), "Averaging no samples should have failed.");
+}
+
+#[test]
+fn test_triad() {
+
+  let (truth, accel, mag) = static_sensors!();
+
+  let actual = UnitQuaternion::from_quaternion(triad(&accel, &mag).unwrap());
+
+  assert!(actual.angle_to(&truth) < 1.0e-9, "angle error: {}", actual.angle_to(&truth));
+}
+
+#[test]
+fn test_triad_parallel() {
+
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 1.0);
+  let m: Vector3<f64> = Vector3::new(0.0, 0.0, -2.0);
+
+  assert!(triad(&a, &m).is_err(), "Parallel accel and mag should have failed.");
+}
+
+#[test]
+fn test_davenport() {
+
+  let (truth, accel, mag) = static_sensors!();
+
+  let actual = UnitQuaternion::from_quaternion(davenport(&accel, &mag, 1.0, 1.0).unwrap());
+
+  assert!(actual.angle_to(&truth) < 1.0e-9, "angle error: {}", actual.angle_to(&truth));
+}
+
+#[test]
+fn test_davenport_mag_zero() {
+
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 1.0);
+  let m: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+
+  assert!(davenport(&a, &m, 1.0, 1.0).is_err(), "Normalizing zero-value mag should have failed.");
+}
+
+#[test]
+fn test_madgwick_from_static_samples() {
+
+  let (truth, accel, mag) = static_sensors!();
+
+  let accels = [accel + Vector3::new(0.01, 0.0, 0.0), accel - Vector3::new(0.01, 0.0, 0.0)];
+  let mags = [mag + Vector3::new(0.0, 0.0, 0.01), mag - Vector3::new(0.0, 0.0, 0.01)];
+
+  let mut ahrs = Madgwick::from_static_samples(1.0f64 / 256.0, 0.1, &accels, &mags).unwrap();
+
+  let start = UnitQuaternion::from_quaternion(ahrs.quat);
+
+  assert!(start.angle_to(&truth) < 1.0e-9, "angle error: {}", start.angle_to(&truth));
+
+  // An aligned filter should stay aligned
+  for _ in 0..100 {
+    ahrs.update(&Vector3::new(0.0, 0.0, 0.0), &accel, &mag).unwrap();
+  }
+
+  let actual = UnitQuaternion::from_quaternion(ahrs.quat);
+
+  assert!(actual.angle_to(&truth) < 1.0e-3, "angle error: {}", actual.angle_to(&truth));
+}
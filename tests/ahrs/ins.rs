@@ -0,0 +1,97 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::acceleration::{AccelUnit, STANDARD_GRAVITY};
+use lobster_rust::ahrs::error::AhrsError;
+use lobster_rust::ahrs::ins::Ins;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use na::{Quaternion, UnitQuaternion, Vector3};
+
+#[test]
+fn test_ins_constant_acceleration() {
+
+  // Zero gain keeps the orientation fixed while accelerating
+  let mut ins = Ins::new(Madgwick::new(0.01f64, 0.0), 1.0, AccelUnit::G);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = Vector3::new(0.1, -0.2, 1.0);
+
+  for _ in 0..100 {
+    ins.update_imu(&gyro, &accel, 0.01).unwrap();
+  }
+
+  let expected = Vector3::new(0.1, -0.2, 0.0) * STANDARD_GRAVITY;
+
+  assert!(relative_eq!(ins.acceleration(), expected, epsilon = 1.0e-9));
+  assert!(relative_eq!(ins.velocity(), expected, epsilon = 1.0e-9));
+  assert!(relative_eq!(ins.position(), expected * 0.5, epsilon = 1.0e-9));
+}
+
+#[test]
+fn test_ins_rotated_stationary_removes_gravity() {
+
+  // Zero gain holds the true orientation, without the gradient step jitter of a converged filter
+  let truth = UnitQuaternion::from_euler_angles(0.4f64, -0.2, 1.0);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = truth.inverse() * Vector3::new(0.0, 0.0, 9.81);
+
+  let mut ins = Ins::new(
+    Madgwick::new_with_quat(0.01, 0.0, truth.into_inner()),
+    9.81,
+    AccelUnit::MetersPerSecondSquared
+  );
+
+  for _ in 0..1000 {
+    ins.update_imu(&gyro, &accel, 0.01).unwrap();
+  }
+
+  assert!(relative_eq!(ins.velocity(), Vector3::zeros(), epsilon = 1.0e-9));
+  assert!(relative_eq!(ins.position(), Vector3::zeros(), epsilon = 1.0e-9));
+}
+
+#[test]
+fn test_ins_zero_velocity_update_and_position_fix() {
+
+  let mut ins = Ins::new(Madgwick::new(0.01f64, 0.0), 1.0, AccelUnit::G);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let moving = Vector3::new(0.1, 0.0, 1.0);
+  let still = Vector3::new(0.0, 0.0, 1.0);
+
+  for _ in 0..100 {
+    ins.update_imu(&gyro, &moving, 0.01).unwrap();
+  }
+
+  let drift = ins.zero_velocity_update();
+  assert!(relative_eq!(drift, Vector3::new(0.1 * STANDARD_GRAVITY, 0.0, 0.0), epsilon = 1.0e-9));
+
+  // Position holds once velocity is zeroed
+  let position = ins.position();
+  for _ in 0..100 {
+    ins.update_imu(&gyro, &still, 0.01).unwrap();
+  }
+  assert!(relative_eq!(ins.position(), position, epsilon = 1.0e-12));
+
+  ins.correct_position(&Vector3::new(1.0, 1.0, 0.0), 0.5);
+  assert!(relative_eq!(ins.position(), (position + Vector3::new(1.0, 1.0, 0.0)) * 0.5, epsilon = 1.0e-12));
+
+  ins.reset();
+  assert_eq!(ins.position(), Vector3::zeros());
+  assert_eq!(ins.velocity(), Vector3::zeros());
+}
+
+#[test]
+fn test_ins_failed_update_keeps_state() {
+
+  let mut ins = Ins::new(Madgwick::new(0.01f64, 0.1), 1.0, AccelUnit::G);
+  ins.set_velocity(Vector3::new(1.0, 0.0, 0.0));
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+
+  assert_eq!(ins.update_imu(&gyro, &Vector3::new(0.0, 0.0, 1.0), 0.0), Err(AhrsError::InvalidTimeStep));
+  assert_eq!(ins.update_imu(&gyro, &Vector3::new(0.0, 0.0, 0.0), 0.01), Err(AhrsError::AccelerometerNormZero));
+
+  assert_eq!(ins.velocity(), Vector3::new(1.0, 0.0, 0.0));
+  assert_eq!(ins.position(), Vector3::zeros());
+  assert_eq!(ins.ahrs().quat, Quaternion::new(1.0, 0.0, 0.0, 0.0));
+}
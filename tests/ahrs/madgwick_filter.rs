@@ -1,8 +1,10 @@
 extern crate nalgebra as na;
 
 use lobster_rust::ahrs::ahrs::Ahrs;
-use lobster_rust::ahrs::madgwick_filter::Madgwick;
-use na::{Vector3, Quaternion};
+use lobster_rust::ahrs::conversion::EulerSequence;
+use lobster_rust::ahrs::error::AhrsError;
+use lobster_rust::ahrs::madgwick_filter::{AccelAdaptation, Madgwick, MagRejection};
+use na::{Vector3, Quaternion, UnitQuaternion};
 use std::f64;
 
 // accel, gyro, mag values
@@ -29,7 +31,7 @@ fn test_update_accel_zero() {
 
   let fail_message = "Normalizing zero-value accel should have failed.";
 
-  assert!(res.is_err(), fail_message);
+  assert!(res.is_err(), "{}", fail_message);
 }
 
 #[test]
@@ -45,7 +47,7 @@ fn test_update_mag_zero() {
 
   let fail_message = "Normalizing zero-value mag should have failed.";
 
-  assert!(res.is_err(), fail_message);
+  assert!(res.is_err(), "{}", fail_message);
 }
 
 #[test]
@@ -61,7 +63,7 @@ fn test_update_imu_accel_zero() {
 
   let fail_message = "Normalizing zero-value accel should have failed.";
 
-  assert!(res.is_err(), fail_message);
+  assert!(res.is_err(), "{}", fail_message);
 }
 
 #[test]
@@ -88,7 +90,7 @@ fn test_madgwick_update() {
         actual: {:?}\n\
         expect: {:?}", actual, expected);
 
-  assert!(relative_eq!(actual, &expected), fail_message);
+  assert!(relative_eq!(actual, &expected), "{}", fail_message);
 }
 
 #[test]
@@ -114,6 +116,368 @@ fn test_madgwick_update_imu() {
       actual: {:?}\n\
       expect: {:?}", actual, expected);
 
-    assert!(relative_eq!(actual, &expected), fail_message);
+    assert!(relative_eq!(actual, &expected), "{}", fail_message);
 }
 
+#[test]
+fn test_madgwick_zero_zeta_keeps_bias() {
+
+  let mut ahrs = Madgwick::default();
+
+  let (accel, gyro, mag) = default_sensors!();
+
+  ahrs.update(&(gyro * (f64::consts::PI / 180.0)), &accel, &mag).unwrap();
+  ahrs.update_imu(&(gyro * (f64::consts::PI / 180.0)), &accel).unwrap();
+
+  assert_eq!(ahrs.gyro_bias(), Vector3::zeros());
+}
+
+#[test]
+fn test_madgwick_zeta_estimates_gyro_bias() {
+
+  let start_quat = UnitQuaternion::from_euler_angles(0.2f64, -0.1, 0.3).into_inner();
+
+  let mut ahrs = Madgwick::new_with_zeta(1.0f64 / 256.0, 0.1, 0.1, start_quat);
+
+  let bias = Vector3::new(0.02, -0.01, 0.015);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+  let mag = Vector3::new(0.6, 0.0, -0.8);
+
+  for _ in 0..20000 {
+    ahrs.update(&bias, &accel, &mag).unwrap();
+  }
+
+  let fail_message = format!("gyro bias did not match:\n\
+      actual: {:?}\n\
+      expect: {:?}", ahrs.gyro_bias(), bias);
+
+  assert!(relative_eq!(ahrs.gyro_bias(), bias, epsilon = 1.0e-3), "{}", fail_message);
+}
+
+#[test]
+fn test_madgwick_update_dt_matches_sample_period() {
+
+  let mut fixed = Madgwick::default();
+  let mut variable = Madgwick::default();
+
+  let (accel, gyro, mag) = default_sensors!();
+  let gyro = gyro * (f64::consts::PI / 180.0);
+
+  let expected = *fixed.update(&gyro, &accel, &mag).unwrap();
+  let actual = *variable.update_dt(&gyro, &accel, &mag, 1.0 / 256.0).unwrap();
+
+  assert_eq!(actual, expected);
+
+  let expected = *fixed.update_imu(&gyro, &accel).unwrap();
+  let actual = *variable.update_imu_dt(&gyro, &accel, 1.0 / 256.0).unwrap();
+
+  assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_madgwick_update_dt_invalid() {
+
+  let mut ahrs = Madgwick::default();
+
+  let (accel, gyro, mag) = default_sensors!();
+
+  assert!(ahrs.update_dt(&gyro, &accel, &mag, 0.0).is_err());
+  assert!(ahrs.update_dt(&gyro, &accel, &mag, -0.01).is_err());
+  assert!(ahrs.update_imu_dt(&gyro, &accel, f64::NAN).is_err());
+  assert!(ahrs.update_imu_dt(&gyro, &accel, f64::INFINITY).is_err());
+}
+
+#[test]
+fn test_madgwick_non_finite_input() {
+
+  let mut ahrs = Madgwick::default();
+  let start = ahrs.quat;
+
+  let (accel, gyro, mag) = default_sensors!();
+
+  let res = ahrs.update(&Vector3::new(f64::NAN, 0.0, 0.0), &accel, &mag);
+  assert_eq!(res, Err(AhrsError::NonFiniteInput));
+
+  let res = ahrs.update(&gyro, &accel, &Vector3::new(0.0, f64::INFINITY, 0.0));
+  assert_eq!(res, Err(AhrsError::NonFiniteInput));
+
+  let res = ahrs.update_imu(&gyro, &Vector3::new(0.0, 0.0, f64::NEG_INFINITY));
+  assert_eq!(res, Err(AhrsError::NonFiniteInput));
+
+  // Rejected samples must not touch the state
+  assert_eq!(ahrs.quat, start);
+}
+
+#[test]
+fn test_madgwick_zero_gradient_is_finite() {
+
+  let mut ahrs = Madgwick::default();
+
+  // Identity orientation already matches a level accelerometer, so the gradient vanishes
+  let actual = *ahrs.update_imu(&Vector3::new(0.0, 0.0, 0.0), &Vector3::new(0.0, 0.0, 1.0)).unwrap();
+
+  assert_eq!(actual, Quaternion::new(1.0, 0.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_madgwick_non_finite_state() {
+
+  let mut ahrs = Madgwick::default();
+
+  let (accel, gyro, mag) = default_sensors!();
+  let gyro = gyro * (f64::consts::PI / 180.0);
+
+  let good = *ahrs.update(&gyro, &accel, &mag).unwrap();
+
+  ahrs.quat = Quaternion::new(f64::NAN, 0.0, 0.0, 0.0);
+  assert_eq!(ahrs.update(&gyro, &accel, &mag), Err(AhrsError::NonFiniteState));
+
+  ahrs.reset();
+  assert_eq!(ahrs.quat, good);
+}
+
+#[test]
+fn test_madgwick_auto_reset() {
+
+  let mut ahrs = Madgwick::default();
+  ahrs.set_auto_reset(true);
+
+  let (accel, gyro, mag) = default_sensors!();
+  let gyro = gyro * (f64::consts::PI / 180.0);
+
+  ahrs.update(&gyro, &accel, &mag).unwrap();
+  let expected = *ahrs.clone().update(&gyro, &accel, &mag).unwrap();
+
+  ahrs.quat = Quaternion::new(f64::NAN, 0.0, 0.0, 0.0);
+  let actual = *ahrs.update(&gyro, &accel, &mag).unwrap();
+
+  assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_madgwick_set_quat_is_restored_by_reset() {
+
+  let mut ahrs = Madgwick::default();
+  ahrs.set_auto_reset(true);
+
+  let (accel, gyro, mag) = default_sensors!();
+  ahrs.update(&gyro, &accel, &mag).unwrap();
+
+  let quat = UnitQuaternion::from_euler_angles(0.1f64, -0.2, 0.3).into_inner();
+  ahrs.set_quat(quat);
+  let expected = *ahrs.clone().update(&gyro, &accel, &mag).unwrap();
+
+  ahrs.quat = Quaternion::new(f64::NAN, 0.0, 0.0, 0.0);
+  let actual = *ahrs.update(&gyro, &accel, &mag).unwrap();
+
+  assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_madgwick_orientation_outputs() {
+
+  let mut ahrs = Madgwick::default();
+  ahrs.quat = UnitQuaternion::from_euler_angles(0.1f64, -0.2, 0.3).into_inner();
+
+  let ypr = ahrs.euler_angles(EulerSequence::ZYX);
+  assert!(relative_eq!(ypr, Vector3::new(0.3, -0.2, 0.1), epsilon = 1.0e-12));
+
+  let rot = ahrs.rotation_matrix();
+  assert!(relative_eq!(rot * rot.transpose(), na::Matrix3::identity(), epsilon = 1.0e-12));
+
+  let (axis, angle) = ahrs.axis_angle();
+  assert!(relative_eq!(ahrs.rotation_vector(), axis * angle, epsilon = 1.0e-12));
+}
+
+
+#[cfg(feature = "field_access")]
+#[test]
+fn test_madgwick_field_access_gain_scheduling() {
+
+  let mut ahrs = Madgwick::default();
+
+  assert_eq!(ahrs.sample_period(), 1.0 / 256.0);
+  assert_eq!(ahrs.beta(), 0.1);
+
+  // Without gain the filter integrates the gyroscope only
+  *ahrs.beta_mut() = 0.0;
+  *ahrs.sample_period_mut() = 0.01;
+
+  let gyro = Vector3::new(0.0, 0.0, 1.0);
+  let accel = Vector3::new(0.0, 1.0, 0.0);
+
+  for _ in 0..100 {
+    ahrs.update_imu(&gyro, &accel).unwrap();
+  }
+
+  let expected = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 1.0).into_inner();
+
+  assert!(relative_eq!(ahrs.quat(), expected, epsilon = 1.0e-4));
+}
+
+#[test]
+fn test_madgwick_gain_schedule_decays_to_beta() {
+
+  let mut ahrs = Madgwick::new(0.01f64, 0.1);
+  ahrs.set_gain_schedule(1.0, 1.0);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+
+  assert_eq!(ahrs.current_beta(), 1.0);
+
+  for _ in 0..50 {
+    ahrs.update_imu(&gyro, &accel).unwrap();
+  }
+
+  assert!(relative_eq!(ahrs.current_beta(), 0.55, epsilon = 1.0e-9));
+
+  for _ in 0..60 {
+    ahrs.update_imu(&gyro, &accel).unwrap();
+  }
+
+  assert_eq!(ahrs.current_beta(), 0.1);
+}
+
+#[test]
+fn test_madgwick_gain_schedule_converges_faster() {
+
+  let start_quat = UnitQuaternion::from_euler_angles(0.5f64, -0.3, 0.0).into_inner();
+
+  let mut fixed = Madgwick::new_with_quat(0.01f64, 0.1, start_quat);
+  let mut scheduled = Madgwick::new_with_quat(0.01f64, 0.1, start_quat);
+  scheduled.set_gain_schedule(2.0, 1.0);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+
+  for _ in 0..100 {
+    fixed.update_imu(&gyro, &accel).unwrap();
+    scheduled.update_imu(&gyro, &accel).unwrap();
+  }
+
+  let tilt = |q: Quaternion<f64>| (UnitQuaternion::from_quaternion(q).inverse() * Vector3::z()).angle(&accel);
+
+  assert!(tilt(scheduled.quat) < 1.0e-3, "scheduled tilt: {}", tilt(scheduled.quat));
+  assert!(tilt(fixed.quat) > 0.1, "fixed tilt: {}", tilt(fixed.quat));
+}
+
+#[test]
+fn test_accel_adaptation_factor() {
+
+  let adaptation = AccelAdaptation::new(9.81f64, 0.1, 0.2);
+
+  assert_eq!(adaptation.factor(&Vector3::new(0.0, 0.0, 9.81)), 1.0);
+  assert!(relative_eq!(adaptation.factor(&Vector3::new(0.0, 0.0, 9.81 * 1.15)), 0.5, epsilon = 1.0e-9));
+  assert_eq!(adaptation.factor(&Vector3::new(0.0, 0.0, 9.81 * 0.7)), 0.0);
+}
+
+#[test]
+fn test_madgwick_accel_adaptation_ignores_linear_acceleration() {
+
+  let mut plain = Madgwick::default();
+  let mut adapted = Madgwick::default();
+  adapted.set_accel_adaptation(Some(AccelAdaptation::new(1.0, 0.05, 0.1)));
+
+  // Level sensor accelerating forward at 0.5 g
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = Vector3::new(0.5, 0.0, 1.0);
+
+  for _ in 0..256 {
+    plain.update_imu(&gyro, &accel).unwrap();
+    adapted.update_imu(&gyro, &accel).unwrap();
+  }
+
+  let identity = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+
+  assert_eq!(adapted.quat, identity);
+  assert!(!relative_eq!(plain.quat, identity, epsilon = 1.0e-2));
+}
+
+#[test]
+fn test_mag_rejection_checks_norm_and_dip() {
+
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+  let mut rejection = MagRejection::with_reference(50.0f64, 0.9273, 0.1, 0.05, 0.0);
+
+  // 50 µT field with a dip of 53°
+  assert!(rejection.accept(&accel, &Vector3::new(30.0, 0.0, -40.0)));
+  assert!(!rejection.accept(&accel, &Vector3::new(45.0, 0.0, -60.0)));
+  assert!(!rejection.accept(&accel, &Vector3::new(40.0, 0.0, -30.0)));
+  assert_eq!(rejection.reference(), Some((50.0, 0.9273)));
+}
+
+#[test]
+fn test_mag_rejection_learns_reference() {
+
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+  let mut rejection = MagRejection::learned(0.1f64, 0.05, 0.0, 0.5);
+
+  assert_eq!(rejection.reference(), None);
+  assert!(rejection.accept(&accel, &Vector3::new(30.0, 0.0, -40.0)));
+  assert!(rejection.accept(&accel, &Vector3::new(31.5, 0.0, -42.0)));
+
+  let (norm, dip) = rejection.reference().unwrap();
+
+  assert!(relative_eq!(norm, 51.25, epsilon = 1.0e-9));
+  assert!(relative_eq!(dip, (0.8f64).asin(), epsilon = 1.0e-9));
+  assert!(!rejection.accept(&accel, &Vector3::new(60.0, 0.0, -80.0)));
+}
+
+#[test]
+fn test_madgwick_mag_rejection_falls_back_to_imu() {
+
+  let gyro = Vector3::new(0.0, 0.0, 0.1);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+  let mag = Vector3::new(30.0, 0.0, -40.0);
+  let disturbed = Vector3::new(30.0, 25.0, -40.0);
+
+  let mut imu = Madgwick::default();
+  let mut plain = Madgwick::default();
+  let mut rejecting = Madgwick::default();
+  rejecting.set_mag_rejection(Some(MagRejection::learned(0.1, 0.05, 0.0, 0.01)));
+
+  for _ in 0..256 {
+    imu.update(&gyro, &accel, &mag).unwrap();
+    plain.update(&gyro, &accel, &mag).unwrap();
+    rejecting.update(&gyro, &accel, &mag).unwrap();
+
+    assert!(!rejecting.mag_rejected());
+  }
+
+  for _ in 0..256 {
+    imu.update_imu(&gyro, &accel).unwrap();
+    plain.update(&gyro, &accel, &disturbed).unwrap();
+    rejecting.update(&gyro, &accel, &disturbed).unwrap();
+
+    assert!(rejecting.mag_rejected());
+  }
+
+  assert!(relative_eq!(rejecting.quat, imu.quat, epsilon = 1.0e-12));
+  assert!(!relative_eq!(plain.quat, imu.quat, epsilon = 1.0e-3));
+
+  rejecting.update_imu(&gyro, &accel).unwrap();
+  assert!(!rejecting.mag_rejected());
+}
+
+#[test]
+fn test_madgwick_mag_rejection_down_weights() {
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+  let disturbed = Vector3::new(30.0, 25.0, -60.0);
+
+  let mut plain = Madgwick::default();
+  let mut weighted = Madgwick::default();
+  weighted.set_mag_rejection(Some(MagRejection::with_reference(50.0, 0.9273, 0.1, 0.05, 0.5)));
+
+  for _ in 0..10 {
+    plain.update(&gyro, &accel, &disturbed).unwrap();
+    weighted.update(&gyro, &accel, &disturbed).unwrap();
+  }
+
+  let yaw = |ahrs: &Madgwick<f64>| ahrs.euler_angles(EulerSequence::ZYX)[0].abs();
+
+  assert!(weighted.mag_rejected());
+  assert!(yaw(&weighted) > 0.0 && yaw(&weighted) < yaw(&plain));
+}
@@ -0,0 +1,160 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::mahony_filter::Mahony;
+use na::{Vector3, Quaternion, UnitQuaternion};
+use std::f64;
+
+// accel, gyro, mag values
+macro_rules! default_sensors(
+  () => {
+    (
+      Vector3::new(0.06640625, 0.9794922, -0.01269531),
+      Vector3::new(68.75, 34.25, 3.0625),
+      Vector3::new(0.171875, -0.4536133, -0.04101563)
+    )
+  };
+);
+
+#[test]
+fn test_update_accel_zero() {
+
+  let mut ahrs = Mahony::default();
+
+  let g: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+  let m: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+
+  let res = ahrs.update(&g, &a, &m);
+
+  let fail_message = "Normalizing zero-value accel should have failed.";
+
+  assert!(res.is_err(), "{}", fail_message);
+}
+
+#[test]
+fn test_update_mag_zero() {
+
+  let mut ahrs = Mahony::default();
+
+  let g: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let a: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let m: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+
+  let res = ahrs.update(&g, &a, &m);
+
+  let fail_message = "Normalizing zero-value mag should have failed.";
+
+  assert!(res.is_err(), "{}", fail_message);
+}
+
+#[test]
+fn test_update_imu_accel_zero() {
+
+  let mut ahrs = Mahony::default();
+
+  let g: Vector3<f64> = Vector3::new(1.0, 1.0, 1.0);
+  let a: Vector3<f64> = Vector3::new(0.0, 0.0, 0.0);
+
+  let res = ahrs.update_imu(&g, &a);
+
+  let fail_message = "Normalizing zero-value accel should have failed.";
+
+  assert!(res.is_err(), "{}", fail_message);
+}
+
+#[test]
+fn test_mahony_zero_gain_integrates_gyro() {
+
+  let mut ahrs = Mahony::new(0.01f64, 0.0, 0.0);
+
+  let (accel, _, mag) = default_sensors!();
+  let gyro = Vector3::new(0.0, 0.0, 1.0);
+
+  for _ in 0..100 {
+    ahrs.update(&gyro, &accel, &mag).unwrap();
+  }
+
+  // Pure gyro integration of 1 rad/s around z for one second
+  let expected = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 1.0).into_inner();
+
+  let fail_message = format!("quaternions did not match:\n\
+        actual: {:?}\n\
+        expect: {:?}", ahrs.quat, expected);
+
+  assert!(relative_eq!(ahrs.quat, expected, epsilon = 1.0e-4), "{}", fail_message);
+}
+
+#[test]
+fn test_mahony_update_imu_converges_to_level() {
+
+  let start_quat = UnitQuaternion::from_euler_angles(0.5f64, -0.3, 0.0).into_inner();
+
+  let mut ahrs = Mahony::new_with_quat(1.0f64 / 256.0, 2.0, 0.0, start_quat);
+
+  let gyro = Vector3::new(0.0, 0.0, 0.0);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+
+  for _ in 0..5000 {
+    ahrs.update_imu(&gyro, &accel).unwrap();
+  }
+
+  // The estimated gravity direction should line up with the measured one
+  let gravity = UnitQuaternion::from_quaternion(ahrs.quat).inverse() * Vector3::z();
+
+  assert!(relative_eq!(gravity, accel, epsilon = 1.0e-6));
+}
+
+#[test]
+fn test_mahony_integral_feedback_cancels_gyro_bias() {
+
+  let mut ahrs = Mahony::new(1.0f64 / 256.0, 2.0, 0.5);
+
+  let bias = Vector3::new(0.02, -0.01, 0.0);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+
+  for _ in 0..50000 {
+    ahrs.update_imu(&bias, &accel).unwrap();
+  }
+
+  assert!(relative_eq!(ahrs.integral_feedback(), -bias, epsilon = 1.0e-6));
+}
+
+#[test]
+fn test_mahony_update() {
+
+  let start_quat = Quaternion::new( 0.7252997863255918f64,
+                                    0.6869689552600526,
+                                   -0.04486780259245286,
+                                    0.0008687666471569602);
+
+  let mut ahrs = Mahony::default();
+  ahrs.quat = start_quat;
+
+  let (accel, gyro, mag) = default_sensors!();
+
+  let actual = *ahrs.update(&(gyro * (f64::consts::PI/180.0)), &accel, &mag).unwrap();
+
+  // A single step should stay close to the Madgwick result for the same inputs
+  let madgwick = Quaternion::new( 0.7235467139148768,
+                                  0.6888611247479446,
+                                 -0.04412605927634125,
+                                  0.001842413287185898);
+
+  let fail_message = format!("quaternions did not match:\n\
+        actual: {:?}\n\
+        expect: {:?}", actual, madgwick);
+
+  assert!(relative_eq!(actual, madgwick, epsilon = 1.0e-2), "{}", fail_message);
+}
+
+#[test]
+fn test_mahony_update_dt_invalid() {
+
+  let mut ahrs = Mahony::default();
+
+  let (accel, gyro, mag) = default_sensors!();
+
+  assert!(ahrs.update_dt(&gyro, &accel, &mag, 0.0).is_err());
+  assert!(ahrs.update_imu_dt(&gyro, &accel, -0.01).is_err());
+}
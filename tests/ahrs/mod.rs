@@ -1,2 +1,19 @@
+pub mod acceleration;
+pub mod allan;
+pub mod calibration;
+pub mod conversion;
+pub mod ekf;
+pub mod error;
+pub mod eval;
+pub mod frame;
+pub mod init;
+pub mod ins;
 pub mod madgwick_filter;
+pub mod mahony_filter;
+pub mod replay;
+#[cfg(feature = "serde-serialize")]
+pub mod serialization;
+pub mod sim;
+pub mod stationary;
+#[cfg(feature = "python")]
 pub mod python_interface;
\ No newline at end of file
@@ -0,0 +1,255 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::acceleration::{AccelUnit, STANDARD_GRAVITY};
+use lobster_rust::ahrs::conversion::{euler_angles, EulerSequence};
+use lobster_rust::ahrs::error::ConfigError;
+use lobster_rust::ahrs::eval::track;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use lobster_rust::ahrs::replay::{replay, BinaryReader, Columns, CsvReader, GyroUnit, LogError, LogFormat, MagUnit, ReplaySummary, RECORD_SIZE};
+use lobster_rust::ahrs::sim::{simulate, ImuModel, Segment, Simulation, Trajectory};
+use na::{Quaternion, UnitQuaternion, Vector3};
+use std::f64;
+
+fn simulation() -> Simulation<f64> {
+  let mut trajectory = Trajectory::new(UnitQuaternion::from_euler_angles(0.2, -0.1, 0.5), 0.01f64);
+  trajectory.push(Segment::sinusoidal(2.0, Vector3::new(0.3, -0.2, 0.8), Vector3::zeros(), 0.5));
+
+  simulate(&trajectory, &ImuModel::default(), 0)
+}
+
+// Log of a simulation with the default columns, in seconds, rad/s, g and µT
+fn log(sim: &Simulation<f64>) -> String {
+  let mut log = String::from("timestamp,gx,gy,gz,ax,ay,az,mx,my,mz\n");
+
+  for (n, truth) in sim.truth.iter().enumerate() {
+    let (g, a, m) = (sim.gyroscope[n], sim.accelerometer[n], sim.magnetometer[n] * 50.0);
+    log += &format!("{},{},{},{},{},{},{},{},{},{}\n", truth.time, g[0], g[1], g[2], a[0], a[1], a[2], m[0], m[1], m[2]);
+  }
+
+  log
+}
+
+// Binary log of the same simulation
+fn binary_log(sim: &Simulation<f64>) -> Vec<u8> {
+  let mut log = Vec::new();
+
+  for (n, truth) in sim.truth.iter().enumerate() {
+    let values = [truth.time].iter()
+      .chain(sim.gyroscope[n].iter())
+      .chain(sim.accelerometer[n].iter())
+      .chain((sim.magnetometer[n] * 50.0).iter())
+      .flat_map(|v| v.to_le_bytes().to_vec())
+      .collect::<Vec<u8>>();
+    log.extend(values);
+  }
+
+  log
+}
+
+// Rows of an output CSV, without the header
+fn rows(output: &[u8]) -> Vec<Vec<f64>> {
+  let text = String::from_utf8(output.to_vec()).unwrap();
+  let mut lines = text.lines();
+
+  assert_eq!(lines.next(), Some("timestamp,qw,qx,qy,qz,roll,pitch,yaw"));
+  lines.map(|line| line.split(',').map(|v| v.parse().unwrap()).collect()).collect()
+}
+
+#[test]
+fn test_csv_reader_columns_and_units() {
+
+  let log = "time_ms; wx; wy; wz; fx; fy; fz; bx; by; bz; extra\n\
+             10; 90; 0; -180; 0; 0; 9.80665; 0.3; 0; -0.4; 1\n\
+             20; 0; 45; 0; 0; 4.903325; 0; ; ; ; 2\n";
+
+  let format = LogFormat {
+    columns: Columns {
+      timestamp: "time_ms".to_string(),
+      gyroscope: ["wx".to_string(), "wy".to_string(), "wz".to_string()],
+      accelerometer: ["fx".to_string(), "fy".to_string(), "fz".to_string()],
+      magnetometer: Some(["bx".to_string(), "by".to_string(), "bz".to_string()]),
+    },
+    time_scale: 0.001,
+    gyro_unit: GyroUnit::DegreesPerSecond,
+    accel_unit: AccelUnit::MetersPerSecondSquared,
+    mag_unit: MagUnit::Gauss,
+    delimiter: b';',
+  };
+
+  let samples: Vec<_> = CsvReader::new(log.as_bytes(), format).unwrap().map(Result::unwrap).collect();
+
+  assert_eq!(samples.len(), 2);
+  assert!(relative_eq!(samples[0].timestamp, 0.01));
+  assert!(relative_eq!(samples[0].gyroscope, Vector3::new(f64::consts::FRAC_PI_2, 0.0, -f64::consts::PI)));
+  assert!(relative_eq!(samples[0].accelerometer, Vector3::new(0.0, 0.0, 1.0)));
+  assert!(relative_eq!(samples[0].magnetometer.unwrap(), Vector3::new(30.0, 0.0, -40.0)));
+
+  // Rows without magnetometer measurement
+  assert!(relative_eq!(samples[1].accelerometer, Vector3::new(0.0, 0.5, 0.0)));
+  assert_eq!(samples[1].magnetometer, None);
+}
+
+#[test]
+fn test_replay_matches_filter() {
+
+  let sim = simulation();
+  let log = log(&sim);
+  let mut output = Vec::new();
+
+  let mut ahrs = Madgwick::new(0.01, 0.1);
+  let reader = CsvReader::new(log.as_bytes(), LogFormat::default()).unwrap();
+  let summary = replay(&mut ahrs, reader, &mut output, true).unwrap();
+
+  assert_eq!(summary, ReplaySummary { samples: 200, failed: 0 });
+
+  let expected = track(&mut Madgwick::new(0.01, 0.1), &sim, true).unwrap();
+  let rows = rows(&output);
+
+  assert_eq!(rows.len(), 200);
+  for (row, quat) in rows.iter().zip(expected.iter()) {
+    let ypr = euler_angles(quat, EulerSequence::ZYX) * 180.0 / f64::consts::PI;
+
+    assert!(relative_eq!(Quaternion::new(row[1], row[2], row[3], row[4]), quat, epsilon = 1.0e-9));
+    assert!(relative_eq!(Vector3::new(row[5], row[6], row[7]), Vector3::new(ypr[2], ypr[1], ypr[0]), epsilon = 1.0e-6));
+  }
+
+  assert!(relative_eq!(rows[199][0], 1.99));
+  assert!(relative_eq!(ahrs.quat, expected[199], epsilon = 1.0e-9));
+}
+
+#[test]
+fn test_replay_without_magnetometer() {
+
+  let sim = simulation();
+  let log = log(&sim);
+  let mut output = Vec::new();
+
+  let mut format = LogFormat::default();
+  format.columns.magnetometer = None;
+
+  let reader = CsvReader::new(log.as_bytes(), format).unwrap();
+  replay(&mut Madgwick::new(0.01, 0.1), reader, &mut output, false).unwrap();
+
+  let expected = track(&mut Madgwick::new(0.01, 0.1), &sim, false).unwrap();
+  let last = &rows(&output)[199];
+
+  assert!(relative_eq!(Quaternion::new(last[1], last[2], last[3], last[4]), expected[199], epsilon = 1.0e-9));
+}
+
+#[test]
+fn test_binary_replay_matches_csv() {
+
+  let sim = simulation();
+  let log = log(&sim);
+  let binary = binary_log(&sim);
+  assert_eq!(binary.len(), 200 * RECORD_SIZE);
+
+  let mut csv_output = Vec::new();
+  let reader = CsvReader::new(log.as_bytes(), LogFormat::default()).unwrap();
+  replay(&mut Madgwick::new(0.01, 0.1), reader, &mut csv_output, false).unwrap();
+
+  let mut binary_output = Vec::new();
+  let reader = BinaryReader::new(binary.as_slice(), LogFormat::default());
+  let summary = replay(&mut Madgwick::new(0.01, 0.1), reader, &mut binary_output, false).unwrap();
+
+  assert_eq!(summary, ReplaySummary { samples: 200, failed: 0 });
+  assert_eq!(rows(&binary_output), rows(&csv_output));
+}
+
+#[test]
+fn test_binary_reader() {
+
+  let records = [
+    [10.0f64, 90.0, 0.0, 0.0, 0.0, 0.0, 9.80665, 0.3, 0.0, -0.4],
+    [20.0f64, 0.0, 0.0, 0.0, 0.0, 0.0, 9.80665, f64::NAN, f64::NAN, f64::NAN],
+  ];
+  let mut log: Vec<u8> = records.iter().flat_map(|r| r.iter()).flat_map(|v| v.to_le_bytes().to_vec()).collect();
+  log.extend_from_slice(&[0u8; 12]);
+
+  let format = LogFormat {
+    time_scale: 0.001,
+    gyro_unit: GyroUnit::DegreesPerSecond,
+    accel_unit: AccelUnit::MetersPerSecondSquared,
+    mag_unit: MagUnit::Gauss,
+    ..LogFormat::default()
+  };
+  let samples: Vec<_> = BinaryReader::new(log.as_slice(), format).collect();
+
+  assert_eq!(samples.len(), 3);
+
+  let first = samples[0].as_ref().unwrap();
+  assert!(relative_eq!(first.timestamp, 0.01));
+  assert!(relative_eq!(first.gyroscope, Vector3::new(f64::consts::FRAC_PI_2, 0.0, 0.0)));
+  assert!(relative_eq!(first.accelerometer, Vector3::new(0.0, 0.0, 1.0)));
+  assert!(relative_eq!(first.magnetometer.unwrap(), Vector3::new(30.0, 0.0, -40.0)));
+
+  // Records without magnetometer measurement, and a log ending within the third record
+  assert_eq!(samples[1].as_ref().unwrap().magnetometer, None);
+  match &samples[2] {
+    Err(e @ LogError::TruncatedRecord(3)) => assert_eq!(e.to_string(), "Log ends within record 3."),
+    r => panic!("Expected a truncated record, got {:?}", r),
+  }
+
+  // The magnetometer is ignored without magnetometer columns
+  let mut format = LogFormat::<f64>::default();
+  format.columns.magnetometer = None;
+  let sample = BinaryReader::new(&log[..RECORD_SIZE], format).next().unwrap().unwrap();
+  assert_eq!(sample.magnetometer, None);
+}
+
+#[test]
+fn test_replay_counts_failed_updates() {
+
+  let log = "timestamp,gx,gy,gz,ax,ay,az,mx,my,mz\n\
+             0.00,0,0,0,0,0,1,0.6,0,-0.8\n\
+             0.01,0,0,0,0,0,0,0.6,0,-0.8\n\
+             0.01,0,0,0,0,0,1,0.6,0,-0.8\n\
+             0.03,0,0,0,0,0,1,0.6,0,-0.8\n";
+
+  let mut output = Vec::new();
+  let reader = CsvReader::new(log.as_bytes(), LogFormat::default()).unwrap();
+  let summary = replay(&mut Madgwick::new(0.01, 0.1), reader, &mut output, false).unwrap();
+
+  // A zero accelerometer and a repeated timestamp fail, and are left out of the output
+  assert_eq!(summary, ReplaySummary { samples: 4, failed: 2 });
+  assert_eq!(rows(&output).iter().map(|r| r[0]).collect::<Vec<_>>(), vec!(0.0, 0.03));
+}
+
+#[test]
+fn test_log_errors() {
+
+  let log = "timestamp,gx,gy,gz,ax,ay,az\n0,0,0,0,0,0,1\n";
+  match CsvReader::<_, f64>::new(log.as_bytes(), LogFormat::default()) {
+    Err(LogError::MissingColumn(column)) => assert_eq!(column, "mx"),
+    _ => panic!("Missing magnetometer columns should have failed."),
+  }
+
+  let log = "timestamp,gx,gy,gz,ax,ay,az,mx,my,mz\n0,0,0,0,0,0,1,1,0,0\n0.01,0,zero,0,0,0,1,1,0,0\n0.02,0,0,0,0,,1,1,0,0\n";
+  let results: Vec<_> = CsvReader::<_, f64>::new(log.as_bytes(), LogFormat::default()).unwrap().collect();
+
+  assert!(results[0].is_ok());
+  match &results[1] {
+    Err(e @ LogError::InvalidValue { .. }) => assert_eq!(e.to_string(), "Invalid value in column 'gy' on line 3."),
+    r => panic!("Expected an invalid value, got {:?}", r),
+  }
+  match &results[2] {
+    Err(e @ LogError::InvalidValue { .. }) => assert_eq!(e.to_string(), "Invalid value in column 'ax' on line 4."),
+    r => panic!("Expected a missing value, got {:?}", r),
+  }
+
+  assert!(CsvReader::<_, f64>::from_path("/nonexistent/log.csv", LogFormat::default()).is_err());
+}
+
+#[test]
+fn test_unit_parsing() {
+
+  assert_eq!("deg/s".parse(), Ok(GyroUnit::DegreesPerSecond));
+  assert_eq!("rad/s".parse(), Ok(GyroUnit::RadiansPerSecond));
+  assert_eq!("µT".parse(), Ok(MagUnit::Microtesla));
+  assert_eq!("Gauss".parse(), Ok(MagUnit::Gauss));
+  assert_eq!("tesla".parse::<MagUnit>(), Err(ConfigError::InvalidUnit));
+
+  assert!(relative_eq!(GyroUnit::RadiansPerSecond.convert(f64::consts::PI, GyroUnit::DegreesPerSecond), 180.0));
+  assert!(relative_eq!(MagUnit::Microtesla.convert(50.0, MagUnit::Gauss), 0.5));
+  assert!(relative_eq!(AccelUnit::G.convert(1.0, AccelUnit::MetersPerSecondSquared), STANDARD_GRAVITY));
+}
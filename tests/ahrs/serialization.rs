@@ -0,0 +1,65 @@
+extern crate nalgebra as na;
+extern crate serde_json;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::calibration::{Calibrated, GyroCalibration};
+use lobster_rust::ahrs::ekf::Ekf;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use lobster_rust::ahrs::mahony_filter::Mahony;
+use na::Vector3;
+
+// accel, gyro, mag values
+macro_rules! default_sensors(
+  () => {
+    (
+      Vector3::new(0.06640625, 0.9794922, -0.01269531),
+      Vector3::new(0.2, -0.1, 0.05),
+      Vector3::new(0.171875, -0.4536133, -0.04101563)
+    )
+  };
+);
+
+#[test]
+fn test_madgwick_restore_resumes_identically() {
+
+  let (accel, gyro, mag) = default_sensors!();
+
+  let mut ahrs = Calibrated::new(Madgwick::new(1.0f64 / 256.0, 0.1));
+  ahrs.set_gyro_calibration(Some(GyroCalibration::new(Vector3::new(0.01, 0.0, -0.01))));
+
+  for _ in 0..10 {
+    ahrs.update(&gyro, &accel, &mag).unwrap();
+  }
+
+  let json = serde_json::to_string(&ahrs).unwrap();
+  let mut restored: Calibrated<f64, Madgwick<f64>> = serde_json::from_str(&json).unwrap();
+
+  assert_eq!(restored, ahrs);
+
+  for _ in 0..10 {
+    ahrs.update(&gyro, &accel, &mag).unwrap();
+    restored.update(&gyro, &accel, &mag).unwrap();
+  }
+
+  assert_eq!(restored.ahrs().quat, ahrs.ahrs().quat);
+}
+
+#[test]
+fn test_mahony_and_ekf_round_trip() {
+
+  let (accel, gyro, mag) = default_sensors!();
+
+  let mut mahony = Mahony::new(1.0f64 / 256.0, 2.0, 0.5);
+  let mut ekf = Ekf::default();
+
+  for _ in 0..10 {
+    mahony.update(&gyro, &accel, &mag).unwrap();
+    ekf.update(&gyro, &accel, &mag).unwrap();
+  }
+
+  let restored: Mahony<f64> = serde_json::from_str(&serde_json::to_string(&mahony).unwrap()).unwrap();
+  assert_eq!(restored, mahony);
+
+  let restored: Ekf<f64> = serde_json::from_str(&serde_json::to_string(&ekf).unwrap()).unwrap();
+  assert_eq!(restored, ekf);
+}
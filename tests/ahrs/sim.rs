@@ -0,0 +1,150 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use lobster_rust::ahrs::sim::{simulate, ImuModel, Segment, SensorModel, Trajectory};
+use na::{UnitQuaternion, Vector3};
+use std::f64;
+
+// Standard deviation of every component of a list of vectors
+fn deviation(samples: &[Vector3<f64>]) -> Vector3<f64> {
+  let count = samples.len() as f64;
+  let mean = samples.iter().fold(Vector3::zeros(), |sum, s| sum + s) / count;
+
+  (samples.iter().fold(Vector3::zeros(), |sum, s| sum + (s - mean).component_mul(&(s - mean))) / count).map(f64::sqrt)
+}
+
+#[test]
+fn test_trajectory_constant_rate() {
+
+  let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+  trajectory.push(Segment::constant(1.0, Vector3::new(0.0, 0.0, f64::consts::FRAC_PI_2), Vector3::zeros()));
+  trajectory.push(Segment::still(0.5));
+
+  let truth = trajectory.sample();
+
+  assert_eq!(truth.len(), 150);
+  assert_eq!(truth[99].gyroscope, Vector3::new(0.0, 0.0, f64::consts::FRAC_PI_2));
+  assert_eq!(truth[100].gyroscope, Vector3::zeros());
+
+  // A quarter turn about the vertical after one second
+  let yawed = UnitQuaternion::from_euler_angles(0.0, 0.0, f64::consts::FRAC_PI_2);
+  assert!(relative_eq!(truth[100].quat, yawed.into_inner(), epsilon = 1.0e-12));
+  assert!(relative_eq!(truth[149].magnetometer, yawed.inverse() * Vector3::new(0.6, 0.0, -0.8), epsilon = 1.0e-12));
+  assert!(relative_eq!(truth[149].accelerometer, Vector3::z(), epsilon = 1.0e-12));
+}
+
+#[test]
+fn test_trajectory_sinusoidal_and_acceleration() {
+
+  let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+  trajectory.push(Segment::sinusoidal(2.0, Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.5, 0.0, 0.0), 1.0));
+
+  let truth = trajectory.sample();
+
+  // A full sinusoidal rate period returns to the start
+  assert!(relative_eq!(truth[25].gyroscope, Vector3::new(0.0, 0.0, 1.0), epsilon = 1.0e-12));
+  assert!(relative_eq!(truth[100].quat, truth[0].quat, epsilon = 1.0e-9));
+
+  for sample in &truth {
+    let rotation = UnitQuaternion::from_quaternion(sample.quat);
+    assert!(relative_eq!(rotation * sample.accelerometer, sample.acceleration + Vector3::z(), epsilon = 1.0e-12));
+  }
+}
+
+#[test]
+fn test_madgwick_tracks_ideal_trajectory() {
+
+  let mut trajectory = Trajectory::new(UnitQuaternion::from_euler_angles(0.2, -0.1, 0.5), 0.01f64);
+  trajectory.push(Segment::still(1.0));
+  trajectory.push(Segment::sinusoidal(5.0, Vector3::new(0.3, -0.2, 0.8), Vector3::zeros(), 0.2));
+
+  let sim = simulate(&trajectory, &ImuModel::default(), 0);
+
+  let mut ahrs = Madgwick::new_with_quat(0.01, 0.1, trajectory.initial.into_inner());
+
+  for (n, truth) in sim.truth.iter().enumerate() {
+    let quat = ahrs.update(&sim.gyroscope[n], &sim.accelerometer[n], &sim.magnetometer[n]).unwrap();
+
+    let error = UnitQuaternion::from_quaternion(*quat).angle_to(&UnitQuaternion::from_quaternion(truth.quat));
+    assert!(error < 0.02, "error {} at {}", error, truth.time);
+  }
+}
+
+#[test]
+fn test_simulation_is_seeded() {
+
+  let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+  trajectory.push(Segment::still(1.0));
+
+  let mut model = ImuModel::default();
+  model.gyroscope.white_noise = 0.01;
+  model.accelerometer.random_walk = 0.01;
+
+  let a = simulate(&trajectory, &model, 7);
+  let b = simulate(&trajectory, &model, 7);
+  let c = simulate(&trajectory, &model, 8);
+
+  assert_eq!(a, b);
+  assert_ne!(a.gyroscope, c.gyroscope);
+  assert_eq!(a.magnetometer, a.truth.iter().map(|t| t.magnetometer).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_white_noise_bias_and_scale() {
+
+  let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+  trajectory.push(Segment::still(200.0));
+
+  let mut model = ImuModel::default();
+  model.gyroscope.white_noise = 0.01;
+  model.gyroscope.bias = Vector3::new(0.02, -0.01, 0.0);
+  model.accelerometer.scale_error = Vector3::new(0.0, 0.0, 0.05);
+
+  let sim = simulate(&trajectory, &model, 1);
+  let count = sim.gyroscope.len() as f64;
+  let mean = sim.gyroscope.iter().fold(Vector3::zeros(), |sum, s| sum + s) / count;
+
+  assert!(relative_eq!(mean, model.gyroscope.bias, epsilon = 3.0e-4));
+  assert!(relative_eq!(deviation(&sim.gyroscope), Vector3::repeat(0.01), epsilon = 3.0e-4));
+  assert_eq!(sim.accelerometer[0], Vector3::new(0.0, 0.0, 1.05));
+}
+
+#[test]
+fn test_random_walk_and_bias_instability() {
+
+  let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.1f64);
+  trajectory.push(Segment::still(100.0));
+
+  let mut walk = ImuModel::default();
+  walk.gyroscope.random_walk = 0.01;
+
+  let mut instability = ImuModel::default();
+  instability.gyroscope.bias_instability = 0.01;
+  instability.gyroscope.bias_correlation_time = 1.0;
+
+  // Random walk spreads as the square root of time, the Gauss-Markov bias settles at its deviation
+  let ends: Vec<Vector3<f64>> = (0..200).map(|seed| *simulate(&trajectory, &walk, seed).gyroscope.last().unwrap()).collect();
+  let settled: Vec<Vector3<f64>> = (0..200).map(|seed| *simulate(&trajectory, &instability, seed).gyroscope.last().unwrap()).collect();
+
+  for i in 0..3 {
+    assert!((deviation(&ends)[i] - 0.1).abs() < 0.02, "{}", deviation(&ends));
+    assert!((deviation(&settled)[i] - 0.01).abs() < 0.002, "{}", deviation(&settled));
+  }
+}
+
+#[test]
+fn test_quantization_and_saturation() {
+
+  let mut trajectory = Trajectory::new(UnitQuaternion::identity(), 0.01f64);
+  trajectory.push(Segment::constant(1.0, Vector3::new(3.0, 0.123, -0.5), Vector3::zeros()));
+
+  let model = ImuModel {
+    gyroscope: SensorModel { quantization: 0.01, saturation: 2.0, ..SensorModel::ideal() },
+    ..ImuModel::default()
+  };
+
+  let sim = simulate(&trajectory, &model, 0);
+
+  assert!(relative_eq!(sim.gyroscope[0], Vector3::new(2.0, 0.12, -0.5), epsilon = 1.0e-12));
+}
@@ -0,0 +1,128 @@
+extern crate nalgebra as na;
+
+use lobster_rust::ahrs::ahrs::Ahrs;
+use lobster_rust::ahrs::error::AhrsError;
+use lobster_rust::ahrs::madgwick_filter::Madgwick;
+use lobster_rust::ahrs::stationary::{intervals, StationaryDetector, MAX_WINDOW};
+use na::Vector3;
+
+// Deterministic measurement noise with roughly the given standard deviation
+fn noise(n: usize, sigma: f64) -> Vector3<f64> {
+  let t = n as f64;
+  Vector3::new((1.3 * t).sin(), (2.9 * t + 0.4).sin(), (4.1 * t + 1.1).sin()) * sigma * 2.0f64.sqrt()
+}
+
+// Still for 100 samples, rotating and accelerating for 100, then still again
+fn recording() -> (Vec<Vector3<f64>>, Vec<Vector3<f64>>) {
+  (0..300).map(|n| {
+    let moving = (100..200).contains(&n);
+    let motion = if moving { Vector3::new(0.5, -0.3, 1.0) } else { Vector3::zeros() };
+
+    (motion + noise(n, 0.005), motion * 0.2 + Vector3::new(0.0, 0.0, 1.0) + noise(n + 7, 0.01))
+  }).unzip()
+}
+
+#[test]
+fn test_stationary_detector_update() {
+
+  let mut detector = StationaryDetector::new(16, 0.01f64, 0.005, 1.0, 30.0);
+
+  for n in 0..15 {
+    assert!(!detector.update(&noise(n, 0.005), &(Vector3::z() + noise(n + 7, 0.01))));
+  }
+
+  assert!(detector.update(&noise(15, 0.005), &(Vector3::z() + noise(22, 0.01))));
+  assert!(detector.statistic() < 30.0);
+
+  for _ in 0..16 {
+    detector.update(&Vector3::new(0.0, 0.0, 0.5), &Vector3::z());
+  }
+  assert!(!detector.is_stationary());
+
+  detector.reset();
+  assert!(!detector.is_stationary());
+  assert_eq!(detector.statistic(), 0.0);
+}
+
+#[test]
+fn test_stationary_detector_window_is_clamped() {
+
+  assert_eq!(StationaryDetector::new(0, 0.01f64, 0.005, 1.0, 30.0).window(), 1);
+  assert_eq!(StationaryDetector::new(1000, 0.01f64, 0.005, 1.0, 30.0).window(), MAX_WINDOW);
+}
+
+#[test]
+fn test_stationary_detector_label_intervals() {
+
+  let (gyro, accel) = recording();
+  let mut detector = StationaryDetector::new(16, 0.01, 0.005, 1.0, 30.0);
+
+  let labels = detector.label(&gyro, &accel);
+  assert_eq!(labels.len(), 300);
+
+  let found = intervals(&labels);
+  assert_eq!(found.len(), 2);
+
+  // Detection lags by up to a window at the start of each still period
+  assert!(found[0].0 == 15 && found[0].1 == 100, "{:?}", found);
+  assert!(found[1].0 > 200 && found[1].0 < 232 && found[1].1 == 300, "{:?}", found);
+}
+
+#[test]
+fn test_intervals() {
+
+  assert_eq!(intervals(&[]), vec!());
+  assert_eq!(intervals(&[false, false]), vec!());
+  assert_eq!(intervals(&[true, false, true, true, false]), vec!((0, 1), (2, 4)));
+  assert_eq!(intervals(&[false, true, true]), vec!((1, 3)));
+}
+
+#[test]
+fn test_madgwick_reestimates_bias_while_stationary() {
+
+  let bias = Vector3::new(0.01f64, -0.005, 0.002);
+  let accel = Vector3::new(0.0, 0.0, 1.0);
+
+  let mut ahrs = Madgwick::new(0.01f64, 0.1);
+  ahrs.set_stationary_detector(Some(StationaryDetector::new(16, 0.01, 0.005, 1.0, 30.0)), 0.5);
+
+  for _ in 0..500 {
+    ahrs.update_imu(&bias, &accel).unwrap();
+  }
+
+  assert!(ahrs.is_stationary());
+  assert!(relative_eq!(ahrs.gyro_bias(), bias, epsilon = 1.0e-6));
+
+  // Rotating stops the re-estimation
+  ahrs.update_imu(&(bias + Vector3::new(0.0, 0.0, 1.0)), &accel).unwrap();
+  assert!(!ahrs.is_stationary());
+  assert!(relative_eq!(ahrs.gyro_bias(), bias, epsilon = 1.0e-6));
+
+  ahrs.set_stationary_detector(None, 0.5);
+  assert!(ahrs.stationary_detector().is_none());
+}
+
+#[test]
+fn test_madgwick_failed_update_leaves_detector_unchanged() {
+
+  let mut ahrs = Madgwick::new(0.01f64, 0.1);
+  ahrs.set_stationary_detector(Some(StationaryDetector::new(16, 0.01, 0.005, 1.0, 30.0)), 0.5);
+
+  for n in 0..15 {
+    ahrs.update_imu(&noise(n, 0.005), &(Vector3::z() + noise(n + 7, 0.01))).unwrap();
+  }
+  let detector = ahrs.stationary_detector().cloned();
+
+  let res = ahrs.update_imu(&Vector3::zeros(), &Vector3::zeros());
+  assert_eq!(res, Err(AhrsError::AccelerometerNormZero));
+  let res = ahrs.update(&Vector3::zeros(), &Vector3::z(), &Vector3::zeros());
+  assert_eq!(res, Err(AhrsError::MagnetometerNormZero));
+
+  assert_eq!(ahrs.stationary_detector().cloned(), detector);
+  assert!(!ahrs.is_stationary());
+
+  // The detector is stored inline, so filters stay `Copy`
+  let copy = ahrs;
+  ahrs.update_imu(&noise(15, 0.005), &(Vector3::z() + noise(22, 0.01))).unwrap();
+  assert!(ahrs.is_stationary() && !copy.is_stationary());
+}